@@ -0,0 +1,36 @@
+// cargo bench : the benchmarks of bench.rs with their default options (every strategy, view and size, 1 thread to one per core)
+// no harness, the table and the speedup curves are printed like mandel bench does
+// the csv and the json are written in target/tmp/bench.csv and target/tmp/bench.json, to be compared with the ones of another version
+
+use mandel::bench::{BenchOptions, csv, curves, json, run, table};
+use std::fs;
+use std::path::Path;
+
+fn main() {
+    let measures = run(&BenchOptions::default(), |m| {
+        eprintln!(
+            "{:<12} {:<9} {}x{} {} thread(s) : {:.2} ms",
+            m.strategy.to_string(),
+            m.view,
+            m.width,
+            m.height,
+            m.threads,
+            m.median()
+        );
+    });
+    println!("{}", table(&measures));
+    println!("Scaling (median with 1 thread / median with n threads)");
+    print!("{}", curves(&measures));
+
+    let directory = Path::new(env!("CARGO_TARGET_TMPDIR"));
+    for (file, text) in [
+        ("bench.csv", csv(&measures)),
+        ("bench.json", json(&measures)),
+    ] {
+        let path = directory.join(file);
+        match fs::write(&path, text) {
+            Ok(()) => println!("Saved           : {}", path.display()),
+            Err(e) => eprintln!("Cannot write {} : {e}", path.display()),
+        }
+    }
+}
@@ -0,0 +1,302 @@
+// zoom animations : mandel animate zoom.toml [--force | --y4m file.y4m | --y4m - | --apng file.png]
+// a few keyframes (center, zoom, rotation, palette offset) are given, the frames in between are interpolated
+//      - the zoom is interpolated exponentially (linearly on its logarithm) so that the zoom speed is constant
+//      - while zooming, the center moves at the pace of the zoom (see center_weight()) otherwise the target runs away from the screen
+//      - the rotation and the palette offset follow a Catmull-Rom spline, there is no sudden change of speed on the keyframes
+// each frame is rendered with mt_render_view() and saved as directory/frame_00042.png
+// the frames already on disk are skipped, so an interrupted animation restarts where it stopped (use --force to render everything)
+// the frames are written in a .tmp file and then renamed. A frame on disk is always complete, even if the program was killed while saving
+// with --y4m or --apng the frames go in one video file (or in stdout with "--y4m -") instead, see video.rs
+//
+// zoom.toml looks like :
+//
+//      frames = 240
+//      width = 640
+//      height = 480
+//      directory = "./assets/zoom"     # relative to the directory of zoom.toml
+//      fps = 25                        # optional, only used by the video files
+//
+//      [[key]]
+//      frame = 0                       # optional, by default the keys are evenly spaced
+//      center = [-0.75, 0.0]
+//      zoom = 1.0
+//      rotation = 0.0                  # degrees
+//      palette_offset = 0.0            # 1.0 is a full turn of the palette
+//
+//      [[key]]
+//      frame = 239
+//      center = ["-0.743643887", "0.131825904"]   # strings keep all the digits (see coord.rs)
+//      zoom = 5000.0
+//      rotation = 90.0
+//
+// a value missing in a key is the value of the previous key
+
+use crate::config::{get_coord, get_f64, get_str, get_tables, get_u32};
+use crate::output::save_image;
+use crate::render::mt_render_view;
+use crate::video::{VideoOutput, open_sink, write_frames};
+use crate::view::Viewport;
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Keyframe {
+    pub frame: f64,
+    pub view: Viewport,
+    pub palette_offset: f64,
+}
+
+#[derive(Debug, Clone)]
+pub struct Animation {
+    pub frames: u32,
+    pub width: u32,
+    pub height: u32,
+    pub directory: PathBuf,
+    pub fps: u32,
+    // sorted by frame
+    pub keys: Vec<Keyframe>,
+}
+
+// ----------------------------------------------------------------------------
+pub fn load_animation(path: &Path) -> Result<Animation, Box<dyn Error>> {
+    let text = fs::read_to_string(path)?;
+    let base_dir = path.parent().unwrap_or(Path::new("."));
+    parse_animation(&text, base_dir)
+}
+
+// ----------------------------------------------------------------------------
+pub fn parse_animation(text: &str, base_dir: &Path) -> Result<Animation, Box<dyn Error>> {
+    let table: toml::Table = text.parse()?;
+
+    let frames = get_u32(&table, "frames")?.ok_or("missing frames")?;
+    let width = get_u32(&table, "width")?.unwrap_or(640);
+    let height = get_u32(&table, "height")?.unwrap_or(480);
+    let directory = base_dir.join(get_str(&table, "directory")?.unwrap_or("."));
+    let fps = get_u32(&table, "fps")?.unwrap_or(25);
+    if frames == 0 || width == 0 || height == 0 || fps == 0 {
+        return Err("frames, width, height and fps must be greater than 0".into());
+    }
+
+    let tables = get_tables(&table, "key")?;
+    if tables.is_empty() {
+        return Err("at least one [[key]] is needed".into());
+    }
+
+    let mut keys: Vec<Keyframe> = Vec::with_capacity(tables.len());
+    let mut previous = Keyframe {
+        frame: 0.0,
+        view: Viewport::default(),
+        palette_offset: 0.0,
+    };
+    for (i, t) in tables.iter().enumerate() {
+        let evenly_spaced = if tables.len() == 1 {
+            0.0
+        } else {
+            i as f64 * (frames - 1) as f64 / (tables.len() - 1) as f64
+        };
+        let key = Keyframe {
+            frame: get_f64(t, "frame")?.unwrap_or(evenly_spaced),
+            view: Viewport {
+                center: get_coord(t, "center")?.unwrap_or(previous.view.center),
+                zoom: get_f64(t, "zoom")?.unwrap_or(previous.view.zoom),
+                rotation: get_f64(t, "rotation")?.unwrap_or(previous.view.rotation),
+            },
+            palette_offset: get_f64(t, "palette_offset")?.unwrap_or(previous.palette_offset),
+        };
+        if key.view.zoom <= 0.0 {
+            return Err(format!("key #{} : zoom must be greater than 0", i + 1).into());
+        }
+        previous = key.clone();
+        keys.push(key);
+    }
+    keys.sort_by(|a, b| a.frame.total_cmp(&b.frame));
+
+    Ok(Animation {
+        frames,
+        width,
+        height,
+        directory,
+        fps,
+        keys,
+    })
+}
+
+impl Animation {
+    // ----------------------------------------------------------------------------
+    pub fn frame_path(&self, n: u32) -> PathBuf {
+        self.directory.join(format!("frame_{n:05}.png"))
+    }
+
+    // ----------------------------------------------------------------------------
+    // the view and the palette offset of the frame n
+    pub fn frame_at(&self, n: u32) -> (Viewport, f64) {
+        let frame = n as f64;
+        let keys = &self.keys;
+        let last = keys.len() - 1;
+
+        // before the first key or after the last one, nothing moves
+        if frame <= keys[0].frame {
+            return (keys[0].view.clone(), keys[0].palette_offset);
+        }
+        if frame >= keys[last].frame {
+            return (keys[last].view.clone(), keys[last].palette_offset);
+        }
+
+        // keys[k] <= frame < keys[k + 1]
+        let k = keys.iter().rposition(|key| key.frame <= frame).unwrap();
+        let (k0, k1) = (&keys[k], &keys[k + 1]);
+        let before = &keys[k.saturating_sub(1)];
+        let after = &keys[(k + 2).min(last)];
+        let t = (frame - k0.frame) / (k1.frame - k0.frame);
+
+        let zoom = (k0.view.zoom.ln() + t * (k1.view.zoom.ln() - k0.view.zoom.ln())).exp();
+        // the differences with k0 keep all the digits of the centers (see coord.rs)
+        let c0 = &k0.view.center;
+        let center = match center_weight(k0.view.zoom, k1.view.zoom, zoom) {
+            Some(w) => c0.add(&k1.view.center.sub(c0).mul_f64(w)),
+            None => {
+                // the weights of a Catmull-Rom spline add up to 1, so p = p1 + w0 (p0 - p1) + w2 (p2 - p1) + w3 (p3 - p1)
+                let [w0, _, w2, w3] = catmull_rom_weights(t);
+                c0.add(&before.view.center.sub(c0).mul_f64(w0))
+                    .add(&k1.view.center.sub(c0).mul_f64(w2))
+                    .add(&after.view.center.sub(c0).mul_f64(w3))
+            }
+        };
+        let rotation = catmull_rom(
+            before.view.rotation,
+            k0.view.rotation,
+            k1.view.rotation,
+            after.view.rotation,
+            t,
+        );
+        let palette_offset = catmull_rom(
+            before.palette_offset,
+            k0.palette_offset,
+            k1.palette_offset,
+            after.palette_offset,
+            t,
+        );
+
+        (
+            Viewport {
+                center,
+                zoom,
+                rotation,
+            },
+            palette_offset,
+        )
+    }
+
+    // ----------------------------------------------------------------------------
+    // the frame producer, image must be width * height * 3 bytes
+    pub fn render_frame(&self, n: u32, image: &mut [u8]) {
+        let (view, palette_offset) = self.frame_at(n);
+        mt_render_view(&view, palette_offset, self.width, self.height, image);
+    }
+}
+
+// ----------------------------------------------------------------------------
+// when the zoom goes from z0 to z1 the size of the view goes from 1/z0 to 1/z1
+// if the center moves in proportion of the size of the view, the point of arrival keeps the same place on the screen during the zoom
+// None if the zoom does not change (then there is no "pace of the zoom" to follow)
+fn center_weight(z0: f64, z1: f64, zoom: f64) -> Option<f64> {
+    if (z1 / z0 - 1.0).abs() < 1e-9 {
+        return None;
+    }
+    Some((1.0 / z0 - 1.0 / zoom) / (1.0 / z0 - 1.0 / z1))
+}
+
+// ----------------------------------------------------------------------------
+// uniform Catmull-Rom spline between p1 (t = 0) and p2 (t = 1), p0 and p3 are the neighbours
+fn catmull_rom(p0: f64, p1: f64, p2: f64, p3: f64, t: f64) -> f64 {
+    let [w0, w1, w2, w3] = catmull_rom_weights(t);
+    w0 * p0 + w1 * p1 + w2 * p2 + w3 * p3
+}
+
+// ----------------------------------------------------------------------------
+// the weights of p0, p1, p2 and p3
+fn catmull_rom_weights(t: f64) -> [f64; 4] {
+    let t2 = t * t;
+    let t3 = t2 * t;
+    [
+        0.5 * (-t + 2.0 * t2 - t3),
+        0.5 * (2.0 - 5.0 * t2 + 3.0 * t3),
+        0.5 * (t + 4.0 * t2 - 3.0 * t3),
+        0.5 * (-t2 + t3),
+    ]
+}
+
+#[derive(Debug, Clone)]
+pub enum AnimOutput {
+    // numbered png files in the directory of the animation, the frames on disk are skipped
+    Frames { force: bool },
+    Video(VideoOutput),
+}
+
+// ----------------------------------------------------------------------------
+pub fn run(path: &Path, output: &AnimOutput) -> Result<(), Box<dyn Error>> {
+    let animation = load_animation(path)?;
+    let (width, height) = (animation.width, animation.height);
+
+    let video = match output {
+        AnimOutput::Frames { force } => return write_png_frames(&animation, *force),
+        AnimOutput::Video(video) => video,
+    };
+
+    let mut sink = open_sink(video, width, height, animation.frames, animation.fps)?;
+    write_frames(
+        animation.frames,
+        (width * height * 3) as usize,
+        |n, frame| animation.render_frame(n, frame),
+        sink.as_mut(),
+    )
+}
+
+// ----------------------------------------------------------------------------
+fn write_png_frames(animation: &Animation, force: bool) -> Result<(), Box<dyn Error>> {
+    fs::create_dir_all(&animation.directory)?;
+    println!("# of frames     : {}", animation.frames);
+    println!("Directory       : {}", animation.directory.display());
+
+    let mut image = vec![0u8; (animation.width * animation.height * 3) as usize].into_boxed_slice();
+    let mut rendered = 0;
+    let mut skipped = 0;
+    let mut render_time = Duration::ZERO;
+
+    for n in 0..animation.frames {
+        let filename = animation.frame_path(n);
+        if !force && filename.exists() {
+            skipped += 1;
+            continue;
+        }
+
+        let start = Instant::now();
+        animation.render_frame(n, &mut image);
+        let duration = start.elapsed();
+        render_time += duration;
+        rendered += 1;
+
+        let tmp = filename.with_extension("png.tmp");
+        let tmp_name = tmp.to_str().ok_or("the output path is not valid UTF-8")?;
+        save_image(tmp_name, &image, animation.width, animation.height)?;
+        fs::rename(&tmp, &filename)?;
+        println!(
+            "frame {n:5}/{} : {} ms",
+            animation.frames,
+            duration.as_millis()
+        );
+    }
+
+    println!();
+    println!("Summary         : {rendered} rendered, {skipped} skipped (already on disk)");
+    if rendered > 0 {
+        println!("Total render    : {} ms", render_time.as_millis());
+        println!(
+            "Average render  : {} ms",
+            (render_time / rendered).as_millis()
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,200 @@
+// average colorings : a value is taken from each point of the orbit and their average goes into the palette
+//      mandel render [view options] --average stripe [--stripes 5] [--average-scale 10] --output stripes.png
+//      mandel render [view options] --average tia [--average-scale 10] --output tia.png
+// the averages (both between 0.0 and 1.0) :
+//      - stripe : 0.5 + 0.5 * sin(stripes * arg(z)), the stripes follow the filaments of the set
+//      - tia (triangle inequality average) : where |z| is between the 2 bounds of the triangle inequality, ||z_prev^2| - |c|| and |z_prev^2| + |c|
+// a tia term whose 2 bounds are the same is skipped : z1 = c of the Mandelbrot set (z0 = 0) gives nothing, the first term of a Julia set counts
+// a plain average jumps from one escape iteration to the next, it is interpolated like smooth_i (see smooth_from_escape())
+// between the average of all the points and the one without the last point
+// the palette gets scale * average, so the result goes through --color, --alpha and --depth like the smooth iterations (see color.rs)
+// the points of the set have no average, they stay black
+// there is no lighting (no normal, no light) in this program, the averages only go through the palette options
+
+use crate::handle::RenderHandle;
+use crate::real::{Precision, Real};
+use crate::render::{Fractal, PerPixel, mt_pixel_map, smooth_from_escape};
+use crate::view::Viewport;
+use num_complex::Complex;
+use std::f64::consts::LN_2;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AverageKind {
+    Stripe,
+    Tia,
+}
+
+impl FromStr for AverageKind {
+    type Err = String;
+
+    // ----------------------------------------------------------------------------
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "stripe" => Ok(AverageKind::Stripe),
+            "tia" => Ok(AverageKind::Tia),
+            _ => Err(format!("{s} is not stripe or tia")),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OrbitAverage {
+    pub kind: AverageKind,
+    // the number of stripes around the origin (stripe only)
+    pub stripes: f64,
+    pub scale: f64,
+}
+
+impl OrbitAverage {
+    // ----------------------------------------------------------------------------
+    // the value given to the palette, None for a point of the set
+    pub fn value<T: Real>(
+        &self,
+        fractal: &Fractal,
+        pixel: &Complex<T>,
+        iterations: u32,
+    ) -> Option<f64> {
+        let (z0, c, _) = fractal.orbit_start(pixel);
+        let c_norm = Complex::new(c.re.to_f64(), c.im.to_f64()).norm();
+        let mut previous = Complex::new(z0.re.to_f64(), z0.im.to_f64());
+        let (mut sum, mut last, mut terms) = (0.0, 0.0, 0u32);
+
+        let (count, z) = fractal.visit_orbit(pixel, iterations, |z| {
+            let term = match self.kind {
+                AverageKind::Stripe => 0.5 + 0.5 * (self.stripes * z.arg()).sin(),
+                AverageKind::Tia => {
+                    let square = previous.norm_sqr();
+                    let (low, high) = ((square - c_norm).abs(), square + c_norm);
+                    match high > low {
+                        true => (z.norm() - low) / (high - low),
+                        false => f64::NAN,
+                    }
+                }
+            };
+            previous = z;
+            if !term.is_finite() {
+                return;
+            }
+            sum += term;
+            last = term;
+            terms += 1;
+        });
+
+        let weight = weight(count, z)?;
+        let average = sum / terms.max(1) as f64;
+        let without_last = match terms {
+            0 | 1 => average,
+            _ => (sum - last) / (terms - 1) as f64,
+        };
+        Some(self.scale * (weight * average + (1.0 - weight) * without_last))
+    }
+}
+
+// ----------------------------------------------------------------------------
+// the part of the last point in the average, from the escape of smooth_from_escape() (None for the set)
+// smooth_i is count + log2(2 / ln 2) + weight, the weight goes from 1 (|z| just above 2) to 0 (|z| = 4)
+fn weight(count: u32, z: Complex<f64>) -> Option<f64> {
+    let smooth_i = smooth_from_escape(count, z)?;
+    Some((smooth_i - count as f64 - (2.0 / LN_2).log2()).clamp(0.0, 1.0))
+}
+
+// what mt_average_map() computes
+#[derive(Debug, Clone, Copy)]
+pub struct AverageJob<'a> {
+    pub view: &'a Viewport,
+    pub fractal: Fractal,
+    pub precision: Precision,
+    pub iterations: u32,
+    pub width: u32,
+    pub height: u32,
+    pub average: OrbitAverage,
+}
+
+// ----------------------------------------------------------------------------
+// values is width * height (see mt_pixel_map())
+pub fn mt_average_map(job: &AverageJob, values: &mut [Option<f64>], handle: &RenderHandle) {
+    mt_pixel_map(
+        job.view,
+        job.precision,
+        job.width,
+        job.height,
+        values,
+        handle,
+        job,
+    );
+}
+
+impl PerPixel<Option<f64>> for AverageJob<'_> {
+    // ----------------------------------------------------------------------------
+    fn pixel<T: Real>(&self, pixel: &Complex<T>) -> Option<f64> {
+        self.average.value(&self.fractal, pixel, self.iterations)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TIA: OrbitAverage = OrbitAverage {
+        kind: AverageKind::Tia,
+        stripes: 5.0,
+        scale: 1.0,
+    };
+
+    // ----------------------------------------------------------------------------
+    #[test]
+    fn weight_goes_from_1_to_0() {
+        for count in [1, 10, 1000] {
+            let at = |norm| weight(count, Complex::new(0.0, norm)).unwrap();
+            assert!((at(2.0) - 1.0).abs() < 1e-12, "{}", at(2.0));
+            assert!(at(4.0).abs() < 1e-12, "{}", at(4.0));
+            assert!(at(2.5) > at(3.0) && at(3.0) > at(3.5));
+        }
+        assert_eq!(weight(0, Complex::new(3.0, 0.0)), None);
+    }
+
+    // ----------------------------------------------------------------------------
+    // c = 1.5 : z1 = 1.5 (the bounds are both |c|, skipped), z2 = 3.75 escapes (the term is 1)
+    #[test]
+    fn first_mandelbrot_tia_term_is_skipped() {
+        let value = TIA.value(&Fractal::Mandelbrot, &Complex::new(1.5, 0.0), 250);
+        assert_eq!(value, Some(1.0));
+    }
+
+    // ----------------------------------------------------------------------------
+    // c = 1, z0 = 0.5i : the terms of z1 = 0.75, z2 = 1.5625 and z3 = 3.44140625 are 0, 1 and 1
+    #[test]
+    fn first_julia_tia_term_counts() {
+        let julia = Fractal::Julia(Complex::new(1.0, 0.0));
+        let pixel = Complex::new(0.0, 0.5);
+        let weight = weight(2, Complex::new(3.44140625, 0.0)).unwrap();
+        let expected = weight * 2.0 / 3.0 + (1.0 - weight) * 0.5;
+        let value = TIA.value(&julia, &pixel, 250).unwrap();
+        assert!(
+            (value - expected).abs() < 1e-12,
+            "{value} instead of {expected}"
+        );
+    }
+
+    // ----------------------------------------------------------------------------
+    #[test]
+    fn points_of_the_set_have_no_average() {
+        let stripe = OrbitAverage {
+            kind: AverageKind::Stripe,
+            ..TIA
+        };
+        for average in [TIA, stripe] {
+            let julia = Fractal::Julia(Complex::new(0.0, 0.0));
+            assert_eq!(
+                average.value(&Fractal::Mandelbrot, &Complex::new(0.0, 0.0), 250),
+                None
+            );
+            assert_eq!(
+                average.value(&Fractal::Mandelbrot, &Complex::new(-1.0, 0.0), 250),
+                None
+            );
+            assert_eq!(average.value(&julia, &Complex::new(0.5, 0.5), 250), None);
+        }
+    }
+}
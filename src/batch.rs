@@ -0,0 +1,250 @@
+// batch rendering : mandel batch jobs.toml
+// renders all the images described in a job list, one after the other, with ONE pool of threads (see pool.rs)
+// an image is skipped when its file is more recent than the job list (use --force to render everything)
+// at the end a summary with the timings and the failures is printed
+//
+// jobs.toml looks like :
+//
+//      threads = 0                  # optional, 0 = as many threads as cores
+//      width = 160                  # optional, default size of the images
+//      height = 120
+//
+//      [[job]]
+//      output = "./assets/batch/full_set.png"   # relative to the directory of jobs.toml
+//      from = [-2.5, -1.315]
+//      to = [1.0, 1.315]
+//
+//      [[job]]
+//      output = "./assets/batch/seahorse.png"
+//      width = 320                  # overwrites the default
+//      height = 240
+//      from = [-0.80, 0.05]
+//      to = [-0.70, 0.125]
+
+use crate::config::{get_complex, get_str, get_tables, get_u32};
+use crate::output::save_image;
+use crate::pool::ThreadPool;
+use crate::render::pool_build_mandelbrot;
+use num_complex::Complex;
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+#[derive(Debug, Clone)]
+pub struct Job {
+    pub output: PathBuf,
+    pub width: u32,
+    pub height: u32,
+    pub from: Complex<f64>,
+    pub to: Complex<f64>,
+}
+
+#[derive(Debug)]
+pub struct JobList {
+    pub threads: usize,
+    // a job which cannot be read is not fatal, it is reported as a failure with the others
+    pub jobs: Vec<Result<Job, String>>,
+    // used to decide if an output is up to date
+    pub modified: SystemTime,
+}
+
+#[derive(Debug)]
+pub enum Outcome {
+    Rendered { render: Duration, save: Duration },
+    Skipped,
+    Failed(String),
+}
+
+// ----------------------------------------------------------------------------
+pub fn load_jobs(path: &Path) -> Result<JobList, Box<dyn Error>> {
+    let text = fs::read_to_string(path)?;
+    let modified = fs::metadata(path)?.modified()?;
+    let base_dir = path.parent().unwrap_or(Path::new("."));
+    parse_jobs(&text, base_dir, modified)
+}
+
+// ----------------------------------------------------------------------------
+pub fn parse_jobs(
+    text: &str,
+    base_dir: &Path,
+    modified: SystemTime,
+) -> Result<JobList, Box<dyn Error>> {
+    let table: toml::Table = text.parse()?;
+
+    let threads = get_u32(&table, "threads")?.unwrap_or(0) as usize;
+    let default_width = get_u32(&table, "width")?;
+    let default_height = get_u32(&table, "height")?;
+
+    let jobs = get_tables(&table, "job")?
+        .into_iter()
+        .enumerate()
+        .map(|(i, t)| {
+            parse_job(t, base_dir, default_width, default_height)
+                .map_err(|e| format!("job #{} : {e}", i + 1))
+        })
+        .collect();
+
+    Ok(JobList {
+        threads,
+        jobs,
+        modified,
+    })
+}
+
+// ----------------------------------------------------------------------------
+fn parse_job(
+    t: &toml::Table,
+    base_dir: &Path,
+    default_width: Option<u32>,
+    default_height: Option<u32>,
+) -> Result<Job, String> {
+    let output = get_str(t, "output")?.ok_or("missing output")?;
+    let width = get_u32(t, "width")?
+        .or(default_width)
+        .ok_or("missing width")?;
+    let height = get_u32(t, "height")?
+        .or(default_height)
+        .ok_or("missing height")?;
+    if width == 0 || height == 0 {
+        return Err("width and height must be greater than 0".to_string());
+    }
+    Ok(Job {
+        output: base_dir.join(output),
+        width,
+        height,
+        from: get_complex(t, "from")?.ok_or("missing from")?,
+        to: get_complex(t, "to")?.ok_or("missing to")?,
+    })
+}
+
+// ----------------------------------------------------------------------------
+// the output is up to date if it exists and if it was written after the last modification of the job list
+pub fn is_up_to_date(output: &Path, jobs_modified: SystemTime) -> bool {
+    fs::metadata(output)
+        .and_then(|m| m.modified())
+        .map(|t| t >= jobs_modified)
+        .unwrap_or(false)
+}
+
+// ----------------------------------------------------------------------------
+// the image buffer is allocated once and reused as long as the size does not change
+pub fn render_job(
+    pool: &ThreadPool,
+    job: &Job,
+    image: &mut Vec<u8>,
+) -> Result<(Duration, Duration), Box<dyn Error>> {
+    image.resize((job.width * job.height * 3) as usize, 0);
+
+    let start = Instant::now();
+    pool_build_mandelbrot(pool, &job.from, &job.to, job.width, job.height, image)?;
+    let render = start.elapsed();
+
+    let start = Instant::now();
+    if let Some(dir) = job.output.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let filename = job
+        .output
+        .to_str()
+        .ok_or("the output path is not valid UTF-8")?;
+    save_image(filename, image, job.width, job.height)?;
+    let save = start.elapsed();
+
+    Ok((render, save))
+}
+
+// ----------------------------------------------------------------------------
+// returns the number of failed jobs
+pub fn run(path: &Path, force: bool) -> Result<usize, Box<dyn Error>> {
+    let list = load_jobs(path)?;
+    let pool = ThreadPool::new(list.threads);
+    println!("# of jobs       : {}", list.jobs.len());
+    println!("# of threads    : {}", pool.size());
+
+    let start = Instant::now();
+    let mut image = Vec::new();
+    let mut outcomes = Vec::with_capacity(list.jobs.len());
+
+    for job in &list.jobs {
+        let (name, outcome) = match job {
+            Err(e) => (String::from("?"), Outcome::Failed(e.clone())),
+            Ok(job) => {
+                let name = job.output.display().to_string();
+                let outcome = if !force && is_up_to_date(&job.output, list.modified) {
+                    Outcome::Skipped
+                } else {
+                    match render_job(&pool, job, &mut image) {
+                        Ok((render, save)) => Outcome::Rendered { render, save },
+                        Err(e) => Outcome::Failed(e.to_string()),
+                    }
+                };
+                (name, outcome)
+            }
+        };
+
+        match &outcome {
+            Outcome::Rendered { render, save } => {
+                println!(
+                    "rendered  {name} ({} ms + {} ms to save)",
+                    render.as_millis(),
+                    save.as_millis()
+                )
+            }
+            Outcome::Skipped => println!("skipped   {name} (up to date)"),
+            Outcome::Failed(e) => println!("FAILED    {name} : {e}"),
+        }
+        outcomes.push((name, outcome));
+    }
+
+    Ok(print_summary(&outcomes, start.elapsed()))
+}
+
+// ----------------------------------------------------------------------------
+fn print_summary(outcomes: &[(String, Outcome)], total: Duration) -> usize {
+    let mut render_time = Duration::ZERO;
+    let mut save_time = Duration::ZERO;
+    let mut slowest: Option<(&str, Duration)> = None;
+    let (mut rendered, mut skipped) = (0, 0);
+    let mut failures = Vec::new();
+
+    for (name, outcome) in outcomes {
+        match outcome {
+            Outcome::Rendered { render, save } => {
+                rendered += 1;
+                render_time += *render;
+                save_time += *save;
+                if slowest.is_none_or(|(_, d)| *render > d) {
+                    slowest = Some((name, *render));
+                }
+            }
+            Outcome::Skipped => skipped += 1,
+            Outcome::Failed(e) => failures.push((name, e)),
+        }
+    }
+
+    println!();
+    println!(
+        "Summary         : {rendered} rendered, {skipped} skipped, {} failed",
+        failures.len()
+    );
+    println!(
+        "Total           : {} ms (render {} ms, save {} ms)",
+        total.as_millis(),
+        render_time.as_millis(),
+        save_time.as_millis()
+    );
+    if rendered > 0 {
+        println!(
+            "Average render  : {} ms",
+            (render_time / rendered).as_millis()
+        );
+    }
+    if let Some((name, d)) = slowest {
+        println!("Slowest         : {name} ({} ms)", d.as_millis());
+    }
+    for (name, e) in &failures {
+        println!("Failed          : {name} : {e}");
+    }
+    failures.len()
+}
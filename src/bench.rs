@@ -0,0 +1,489 @@
+// benchmarks : mandel bench [--strategies stripes,tiles] [--sizes 320x240,640x480] [--threads 1,2,4] [--views whole,seahorse] [--warmup 1] [--runs 5] [--csv bench.csv] [--json bench.json]
+// (and cargo bench, see benches/render.rs)
+// each strategy renders each view at each size with each number of threads (set_threads(), see render.rs)
+// the warmup runs are not measured, then the runs give the median, the minimum, the mean and the standard deviation
+//      - speedup : median of the single threaded pixel by pixel rendering (Strategy::Single) / median of the strategy, same view and size
+//      - scaling : median of the strategy with 1 thread / median with n threads, the speedup curve of the strategy
+// the single threaded strategies (single, simd) are measured once, with 1 thread
+// the csv and the json carry the version of the crate, the results of 2 versions can be compared
+// all the strategies use ITERATIONS and the auto precision of the view (Precision::for_view()), but simd and pool which only have f64
+// the precision of each measure is in the table, the csv and the json : a speedup between f32 and f64 is not only the strategy
+
+use crate::cycle::color_frame;
+use crate::ddouble::DoubleDouble;
+use crate::mariani::{Escape, mt_mariani_silver};
+use crate::pool::ThreadPool;
+use crate::progressive::mt_progressive_view;
+use crate::real::Precision;
+use crate::render::{
+    ITERATIONS, mt_render_view, mt_smooth_map, pool_build_mandelbrot, render_view_rows, set_threads,
+};
+use crate::simd::{Kernel, render_zone_simd};
+use crate::view::Viewport;
+use num_complex::Complex;
+use std::fmt::{self, Write};
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+// one run of a strategy, it renders the rgb image
+type Run<'a> = Box<dyn FnMut(&mut [u8]) + 'a>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Strategy {
+    // render_view_rows() on the whole image, the reference of the speedups
+    Single,
+    // render_zone_simd() with the best kernel of the CPU (see simd.rs)
+    Simd,
+    // mt_render_view(), one stripe per thread
+    Stripes,
+    // mt_smooth_map() then color_frame(), tiles of TILE_ROWS rows dealt to the threads
+    Tiles,
+    // pool_build_mandelbrot(), bands sent to a ThreadPool created before the runs
+    Pool,
+    // mt_mariani_silver(), see mariani.rs
+    Mariani,
+    // mt_progressive_view(), all the passes, see progressive.rs
+    Progressive,
+}
+
+impl Strategy {
+    pub const ALL: [Strategy; 7] = [
+        Strategy::Single,
+        Strategy::Simd,
+        Strategy::Stripes,
+        Strategy::Tiles,
+        Strategy::Pool,
+        Strategy::Mariani,
+        Strategy::Progressive,
+    ];
+
+    // ----------------------------------------------------------------------------
+    pub fn threaded(self) -> bool {
+        !matches!(self, Strategy::Single | Strategy::Simd)
+    }
+
+    // ----------------------------------------------------------------------------
+    // the precision of the iterations of the strategy for the view
+    pub fn precision(self, view: &Viewport, height: u32) -> Precision {
+        match self {
+            Strategy::Simd | Strategy::Pool => Precision::Double,
+            _ => Precision::for_view(view, height),
+        }
+    }
+
+    // ----------------------------------------------------------------------------
+    // what a run does, the rgb image is width * height * 3
+    // the pool is created here, once per measure, and not in the runs
+    fn prepare(self, view: &Viewport, width: u32, height: u32, nthreads: usize) -> Run<'_> {
+        let precision = Precision::for_view(view, height);
+        match self {
+            Strategy::Single => Box::new(move |image| match precision {
+                Precision::Single => {
+                    render_view_rows::<f32>(view, 0.0, width, height, 0, height, image)
+                }
+                Precision::Double => {
+                    render_view_rows::<f64>(view, 0.0, width, height, 0, height, image)
+                }
+                Precision::DoubleDouble | Precision::Perturbation => {
+                    render_view_rows::<DoubleDouble>(view, 0.0, width, height, 0, height, image)
+                }
+            }),
+            Strategy::Simd => {
+                let (from, to) = corners(view, width, height);
+                let kernel = Kernel::detect();
+                Box::new(move |image| {
+                    render_zone_simd(kernel, &from, &to, width, height, ITERATIONS, image)
+                })
+            }
+            Strategy::Stripes => {
+                Box::new(move |image| mt_render_view(view, 0.0, width, height, image))
+            }
+            Strategy::Tiles => Box::new(move |image| {
+                let mut smooth = vec![None; (width * height) as usize];
+                mt_smooth_map(view, width, height, &mut smooth);
+                color_frame(&smooth, 0.0, image);
+            }),
+            Strategy::Pool => {
+                let pool = ThreadPool::new(nthreads);
+                let (from, to) = corners(view, width, height);
+                Box::new(move |image| {
+                    pool_build_mandelbrot(&pool, &from, &to, width, height, image)
+                        .expect("a band of the pool failed");
+                })
+            }
+            Strategy::Mariani => Box::new(move |image| {
+                let mut data = vec![Escape::default(); (width * height) as usize];
+                match precision {
+                    Precision::Single => {
+                        mt_mariani_silver::<f32>(view, ITERATIONS, width, height, &mut data)
+                    }
+                    Precision::Double => {
+                        mt_mariani_silver::<f64>(view, ITERATIONS, width, height, &mut data)
+                    }
+                    Precision::DoubleDouble | Precision::Perturbation => {
+                        mt_mariani_silver::<DoubleDouble>(
+                            view, ITERATIONS, width, height, &mut data,
+                        )
+                    }
+                };
+                let smooth: Vec<Option<f64>> = data.iter().map(|e| e.smooth).collect();
+                color_frame(&smooth, 0.0, image);
+            }),
+            Strategy::Progressive => Box::new(move |image| {
+                mt_progressive_view(view, ITERATIONS, 0.0, width, height, image, |_, _| ())
+            }),
+        }
+    }
+}
+
+impl FromStr for Strategy {
+    type Err = String;
+
+    // ----------------------------------------------------------------------------
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Strategy::ALL
+            .into_iter()
+            .find(|strategy| strategy.to_string() == s)
+            .ok_or(format!(
+                "{s} is not single, simd, stripes, tiles, pool, mariani or progressive"
+            ))
+    }
+}
+
+impl fmt::Display for Strategy {
+    // ----------------------------------------------------------------------------
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Strategy::Single => "single",
+            Strategy::Simd => "simd",
+            Strategy::Stripes => "stripes",
+            Strategy::Tiles => "tiles",
+            Strategy::Pool => "pool",
+            Strategy::Mariani => "mariani",
+            Strategy::Progressive => "progressive",
+        })
+    }
+}
+
+// ----------------------------------------------------------------------------
+// the from/to of render_zone() which covers the view (the rotation is ignored, the benchmark views have none)
+fn corners(view: &Viewport, width: u32, height: u32) -> (Complex<f64>, Complex<f64>) {
+    let mapper = view.mapper(width, height);
+    (mapper.map(0, 0), mapper.map(width, height))
+}
+
+// ----------------------------------------------------------------------------
+// the views of the benchmark : a lot of interior, a lot of detail, a deeper one in f64
+pub fn views() -> Vec<(&'static str, Viewport)> {
+    let view = |re: &str, im: &str, zoom: f64| Viewport {
+        center: format!("{re},{im}").parse().unwrap(),
+        zoom,
+        rotation: 0.0,
+    };
+    vec![
+        ("whole", Viewport::default()),
+        ("seahorse", view("-0.7436", "0.1318", 200.0)),
+        (
+            "spiral",
+            view("-0.743643887037151", "0.131825904205330", 1e5),
+        ),
+    ]
+}
+
+#[derive(Debug, Clone)]
+pub struct BenchOptions {
+    pub strategies: Vec<Strategy>,
+    pub sizes: Vec<(u32, u32)>,
+    pub threads: Vec<usize>,
+    pub views: Vec<(&'static str, Viewport)>,
+    pub warmup: u32,
+    pub runs: u32,
+}
+
+impl Default for BenchOptions {
+    // ----------------------------------------------------------------------------
+    // everything, from 1 thread to one per core
+    fn default() -> Self {
+        let cores = std::thread::available_parallelism()
+            .map(|n| n.get())
+            .unwrap_or(1);
+        BenchOptions {
+            strategies: Strategy::ALL.to_vec(),
+            sizes: vec![(320, 240), (640, 480)],
+            threads: (1..=cores).collect(),
+            views: views(),
+            warmup: 1,
+            runs: 5,
+        }
+    }
+}
+
+// one strategy, one view, one size, one number of threads
+#[derive(Debug, Clone)]
+pub struct Measure {
+    pub strategy: Strategy,
+    pub view: &'static str,
+    pub width: u32,
+    pub height: u32,
+    pub threads: usize,
+    pub precision: Precision,
+    pub runs: Vec<Duration>,
+    // filled by run() once all the measures are there
+    pub speedup: Option<f64>,
+    pub scaling: Option<f64>,
+}
+
+impl Measure {
+    // ----------------------------------------------------------------------------
+    fn ms(&self) -> Vec<f64> {
+        let mut ms: Vec<f64> = self.runs.iter().map(|d| d.as_secs_f64() * 1000.0).collect();
+        ms.sort_by(f64::total_cmp);
+        ms
+    }
+
+    // ----------------------------------------------------------------------------
+    pub fn median(&self) -> f64 {
+        let ms = self.ms();
+        match ms.len() {
+            0 => 0.0,
+            n if n % 2 == 1 => ms[n / 2],
+            n => (ms[n / 2 - 1] + ms[n / 2]) / 2.0,
+        }
+    }
+
+    // ----------------------------------------------------------------------------
+    pub fn min(&self) -> f64 {
+        self.ms().first().copied().unwrap_or(0.0)
+    }
+
+    // ----------------------------------------------------------------------------
+    pub fn mean(&self) -> f64 {
+        let ms = self.ms();
+        ms.iter().sum::<f64>() / ms.len().max(1) as f64
+    }
+
+    // ----------------------------------------------------------------------------
+    // of the population of the runs
+    pub fn stddev(&self) -> f64 {
+        let (ms, mean) = (self.ms(), self.mean());
+        (ms.iter().map(|t| (t - mean) * (t - mean)).sum::<f64>() / ms.len().max(1) as f64).sqrt()
+    }
+}
+
+// ----------------------------------------------------------------------------
+// all the measures, on_measure(measure) is called after each one (to show the progress)
+// the number of threads of the mt_ functions is restored at the end
+pub fn run<F: FnMut(&Measure)>(options: &BenchOptions, mut on_measure: F) -> Vec<Measure> {
+    let mut measures = Vec::new();
+    for (name, view) in &options.views {
+        for &(width, height) in &options.sizes {
+            let mut image = vec![0u8; (width * height * 3) as usize];
+            for &strategy in &options.strategies {
+                let threads = match strategy.threaded() {
+                    true => options.threads.clone(),
+                    false => vec![1],
+                };
+                for nthreads in threads {
+                    set_threads(nthreads);
+                    let mut render = strategy.prepare(view, width, height, nthreads);
+                    for _ in 0..options.warmup {
+                        render(&mut image);
+                    }
+                    let runs = (0..options.runs.max(1))
+                        .map(|_| {
+                            let start = Instant::now();
+                            render(&mut image);
+                            start.elapsed()
+                        })
+                        .collect();
+                    let measure = Measure {
+                        strategy,
+                        view: name,
+                        width,
+                        height,
+                        threads: nthreads,
+                        precision: strategy.precision(view, height),
+                        runs,
+                        speedup: None,
+                        scaling: None,
+                    };
+                    on_measure(&measure);
+                    measures.push(measure);
+                }
+            }
+        }
+    }
+    set_threads(0);
+    compare(&mut measures);
+    measures
+}
+
+// ----------------------------------------------------------------------------
+// speedup and scaling, when their references were measured
+fn compare(measures: &mut [Measure]) {
+    let medians: Vec<(Strategy, &str, u32, u32, usize, f64)> = measures
+        .iter()
+        .map(|m| (m.strategy, m.view, m.width, m.height, m.threads, m.median()))
+        .collect();
+    let find = |strategy: Strategy, m: &Measure, threads: usize| {
+        medians
+            .iter()
+            .find(|r| {
+                r.0 == strategy
+                    && r.1 == m.view
+                    && (r.2, r.3) == (m.width, m.height)
+                    && r.4 == threads
+            })
+            .map(|r| r.5)
+    };
+    for measure in measures.iter_mut() {
+        let median = measure.median();
+        let ratio = |reference: Option<f64>| reference.filter(|_| median > 0.0).map(|r| r / median);
+        measure.speedup = ratio(find(Strategy::Single, measure, 1));
+        measure.scaling = ratio(find(measure.strategy, measure, 1));
+    }
+}
+
+// ----------------------------------------------------------------------------
+fn ratio(value: Option<f64>) -> String {
+    value.map_or("-".to_string(), |v| format!("{v:.2}"))
+}
+
+// ----------------------------------------------------------------------------
+// one line per measure, aligned columns
+pub fn table(measures: &[Measure]) -> String {
+    let mut out = format!(
+        "{:<12} {:<9} {:>9} {:>7} {:>9} {:>10} {:>10} {:>8} {:>8} {:>8}\n",
+        "strategy",
+        "view",
+        "size",
+        "threads",
+        "precision",
+        "median ms",
+        "min ms",
+        "stddev",
+        "speedup",
+        "scaling"
+    );
+    for m in measures {
+        let _ = writeln!(
+            out,
+            "{:<12} {:<9} {:>9} {:>7} {:>9} {:>10.2} {:>10.2} {:>8.2} {:>8} {:>8}",
+            m.strategy.to_string(),
+            m.view,
+            format!("{}x{}", m.width, m.height),
+            m.threads,
+            m.precision.to_string(),
+            m.median(),
+            m.min(),
+            m.stddev(),
+            ratio(m.speedup),
+            ratio(m.scaling)
+        );
+    }
+    out
+}
+
+// ----------------------------------------------------------------------------
+// the speedup curve of each threaded strategy : its scaling for each number of threads on one line
+pub fn curves(measures: &[Measure]) -> String {
+    let mut out = String::new();
+    for (i, m) in measures.iter().enumerate() {
+        let same = |other: &Measure| {
+            other.strategy == m.strategy
+                && other.view == m.view
+                && (other.width, other.height) == (m.width, m.height)
+        };
+        // the first measure of each curve writes it
+        if !m.strategy.threaded() || measures[..i].iter().any(same) {
+            continue;
+        }
+        let points: Vec<String> = measures
+            .iter()
+            .filter(|other| same(other))
+            .map(|other| format!("{} => {}", other.threads, ratio(other.scaling)))
+            .collect();
+        let _ = writeln!(
+            out,
+            "{:<12} {:<9} {:>9} : {}",
+            m.strategy.to_string(),
+            m.view,
+            format!("{}x{}", m.width, m.height),
+            points.join(", ")
+        );
+    }
+    out
+}
+
+// ----------------------------------------------------------------------------
+// one header line, then one line per measure
+pub fn csv(measures: &[Measure]) -> String {
+    let mut out = String::from(
+        "version,strategy,view,width,height,threads,precision,runs,median_ms,min_ms,mean_ms,stddev_ms,speedup,scaling\n",
+    );
+    let optional = |value: Option<f64>| value.map_or(String::new(), |v| format!("{v:.4}"));
+    for m in measures {
+        let _ = writeln!(
+            out,
+            "{},{},{},{},{},{},{},{},{:.4},{:.4},{:.4},{:.4},{},{}",
+            env!("CARGO_PKG_VERSION"),
+            m.strategy,
+            m.view,
+            m.width,
+            m.height,
+            m.threads,
+            m.precision,
+            m.runs.len(),
+            m.median(),
+            m.min(),
+            m.mean(),
+            m.stddev(),
+            optional(m.speedup),
+            optional(m.scaling)
+        );
+    }
+    out
+}
+
+// ----------------------------------------------------------------------------
+// written by hand, the names of the strategies and of the views need no escaping
+pub fn json(measures: &[Measure]) -> String {
+    let optional = |value: Option<f64>| value.map_or("null".to_string(), |v| format!("{v:.4}"));
+    let results: Vec<String> = measures
+        .iter()
+        .map(|m| {
+            let runs: Vec<String> = m
+                .runs
+                .iter()
+                .map(|d| format!("{:.4}", d.as_secs_f64() * 1000.0))
+                .collect();
+            format!(
+                "    {{\"strategy\": \"{}\", \"view\": \"{}\", \"width\": {}, \"height\": {}, \"threads\": {}, \"precision\": \"{}\", \
+                 \"runs_ms\": [{}], \"median_ms\": {:.4}, \"min_ms\": {:.4}, \"mean_ms\": {:.4}, \"stddev_ms\": {:.4}, \
+                 \"speedup\": {}, \"scaling\": {}}}",
+                m.strategy,
+                m.view,
+                m.width,
+                m.height,
+                m.threads,
+                m.precision,
+                runs.join(", "),
+                m.median(),
+                m.min(),
+                m.mean(),
+                m.stddev(),
+                optional(m.speedup),
+                optional(m.scaling)
+            )
+        })
+        .collect();
+    format!(
+        "{{\n  \"version\": \"{}\",\n  \"cores\": {},\n  \"kernel\": \"{}\",\n  \"results\": [\n{}\n  ]\n}}\n",
+        env!("CARGO_PKG_VERSION"),
+        std::thread::available_parallelism()
+            .map(|n| n.get())
+            .unwrap_or(1),
+        Kernel::detect(),
+        results.join(",\n")
+    )
+}
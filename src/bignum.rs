@@ -0,0 +1,436 @@
+// a very simple arbitrary precision fixed point number, only what the reference orbits of deep.rs need
+// the value is sign * (limbs as an unsigned integer) / 2^(64 * frac_limbs)
+// limbs are little endian : limbs[0] is the least significant part of the fraction, the last limb is the integer part
+// there is only ONE limb for the integer part. It is more than enough, in the Mandelbrot iteration the values stay below 16
+// all the operands of an operation must have the same precision (same number of limbs)
+
+use std::cmp::Ordering;
+
+// the most digits of a decimal number, and the largest exponent, read by parse_decimal()
+// 10000 digits go far beyond any zoom, a longer text (or 1e-3000000000) is an error and not gigabytes of zeros
+pub const MAX_DIGITS: usize = 10_000;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BigFixed {
+    negative: bool,
+    limbs: Vec<u64>,
+}
+
+impl BigFixed {
+    // ----------------------------------------------------------------------------
+    pub fn zero(frac_limbs: usize) -> Self {
+        BigFixed {
+            negative: false,
+            limbs: vec![0; frac_limbs + 1],
+        }
+    }
+
+    // ----------------------------------------------------------------------------
+    pub fn frac_limbs(&self) -> usize {
+        self.limbs.len() - 1
+    }
+
+    // ----------------------------------------------------------------------------
+    // the number of limbs needed to tell apart two pixels of size pixel_size, plus a safety margin of 64 bits
+    pub fn limbs_for_pixel_size(pixel_size: f64) -> usize {
+        let bits = (-pixel_size.log2()).max(0.0) + 64.0;
+        (bits / 64.0).ceil() as usize + 1
+    }
+
+    // ----------------------------------------------------------------------------
+    pub fn is_zero(&self) -> bool {
+        self.limbs.iter().all(|&l| l == 0)
+    }
+
+    // ----------------------------------------------------------------------------
+    // exact, the mantissa of a f64 is 53 bits (the bits below the precision are lost)
+    pub fn from_f64(value: f64, frac_limbs: usize) -> Self {
+        let mut result = BigFixed::zero(frac_limbs);
+        if value == 0.0 || !value.is_finite() {
+            return result;
+        }
+        result.negative = value < 0.0;
+
+        // value = mantissa * 2^exponent, mantissa is an integer
+        let bits = value.abs().to_bits();
+        let raw_exponent = ((bits >> 52) & 0x7ff) as i64;
+        let (mantissa, exponent) = if raw_exponent == 0 {
+            (bits & ((1 << 52) - 1), -1074)
+        } else {
+            ((bits & ((1 << 52) - 1)) | (1 << 52), raw_exponent - 1075)
+        };
+
+        // position of the bit 0 of the mantissa, counted from the least significant bit of limbs[0]
+        let shift = exponent + 64 * frac_limbs as i64;
+        for bit in 0..53 {
+            if mantissa & (1 << bit) != 0 {
+                let position = shift + bit;
+                if position >= 0 && position < 64 * (frac_limbs as i64 + 1) {
+                    result.limbs[(position / 64) as usize] |= 1 << (position % 64);
+                }
+            }
+        }
+        result
+    }
+
+    // ----------------------------------------------------------------------------
+    // the 3 most significant limbs (from the first non null one) are more than the 53 bits of a f64
+    pub fn to_f64(&self) -> f64 {
+        let frac_limbs = self.frac_limbs() as i32;
+        let mut value = 0.0;
+        let mut used = 0;
+        for (i, &limb) in self.limbs.iter().enumerate().rev() {
+            if limb == 0 && used == 0 {
+                continue;
+            }
+            value += limb as f64 * 2f64.powi(64 * (i as i32 - frac_limbs));
+            used += 1;
+            if used == 3 {
+                break;
+            }
+        }
+        if self.negative { -value } else { value }
+    }
+
+    // ----------------------------------------------------------------------------
+    // "-0.7436438870371587047521915061147745", "1.5e-3"... (see parse_decimal())
+    pub fn parse(text: &str, frac_limbs: usize) -> Result<Self, String> {
+        let (negative, mut digits, mut point) = parse_decimal(text)?;
+        if point < 0 {
+            let mut padded = vec![0; (-point) as usize];
+            padded.extend(digits);
+            digits = padded;
+            point = 0;
+        }
+        while (digits.len() as i64) < point {
+            digits.push(0);
+        }
+        let (int_part, frac_part) = digits.split_at(point as usize);
+
+        let mut integer: u64 = 0;
+        for &d in int_part {
+            integer = integer
+                .checked_mul(10)
+                .and_then(|n| n.checked_add(d as u64))
+                .ok_or("the integer part is too large")?;
+        }
+
+        // Horner from the last digit : x = (d + x) / 10
+        // one extra limb keeps the truncation errors away from the result
+        let mut fraction = BigFixed::zero(frac_limbs + 1);
+        for &d in frac_part.iter().rev() {
+            fraction.limbs[frac_limbs + 1] = d as u64;
+            fraction.div_small(10);
+        }
+
+        let mut result = BigFixed::zero(frac_limbs);
+        result.limbs[..frac_limbs].copy_from_slice(&fraction.limbs[1..frac_limbs + 1]);
+        result.limbs[frac_limbs] = integer;
+        result.negative = negative && !result.is_zero();
+        Ok(result)
+    }
+
+    // ----------------------------------------------------------------------------
+    // magnitude only, long division from the most significant limb
+    fn div_small(&mut self, divisor: u64) {
+        let mut remainder: u128 = 0;
+        for limb in self.limbs.iter_mut().rev() {
+            let current = (remainder << 64) | *limb as u128;
+            *limb = (current / divisor as u128) as u64;
+            remainder = current % divisor as u128;
+        }
+    }
+
+    // ----------------------------------------------------------------------------
+    fn cmp_magnitude(&self, other: &Self) -> Ordering {
+        self.limbs.iter().rev().cmp(other.limbs.iter().rev())
+    }
+
+    // ----------------------------------------------------------------------------
+    // |a| + |b|
+    fn add_magnitudes(a: &[u64], b: &[u64]) -> Vec<u64> {
+        let mut carry = false;
+        a.iter()
+            .zip(b)
+            .map(|(&x, &y)| {
+                let (sum, c1) = x.overflowing_add(y);
+                let (sum, c2) = sum.overflowing_add(carry as u64);
+                carry = c1 || c2;
+                sum
+            })
+            .collect()
+    }
+
+    // ----------------------------------------------------------------------------
+    // |a| - |b| with |a| >= |b|
+    fn sub_magnitudes(a: &[u64], b: &[u64]) -> Vec<u64> {
+        let mut borrow = false;
+        a.iter()
+            .zip(b)
+            .map(|(&x, &y)| {
+                let (diff, b1) = x.overflowing_sub(y);
+                let (diff, b2) = diff.overflowing_sub(borrow as u64);
+                borrow = b1 || b2;
+                diff
+            })
+            .collect()
+    }
+
+    // ----------------------------------------------------------------------------
+    pub fn add(&self, other: &Self) -> Self {
+        let mut result = if self.negative == other.negative {
+            BigFixed {
+                negative: self.negative,
+                limbs: Self::add_magnitudes(&self.limbs, &other.limbs),
+            }
+        } else if self.cmp_magnitude(other) != Ordering::Less {
+            BigFixed {
+                negative: self.negative,
+                limbs: Self::sub_magnitudes(&self.limbs, &other.limbs),
+            }
+        } else {
+            BigFixed {
+                negative: other.negative,
+                limbs: Self::sub_magnitudes(&other.limbs, &self.limbs),
+            }
+        };
+        result.negative &= !result.is_zero();
+        result
+    }
+
+    // ----------------------------------------------------------------------------
+    pub fn neg(&self) -> Self {
+        BigFixed {
+            negative: !self.negative && !self.is_zero(),
+            limbs: self.limbs.clone(),
+        }
+    }
+
+    // ----------------------------------------------------------------------------
+    pub fn sub(&self, other: &Self) -> Self {
+        self.add(&other.neg())
+    }
+
+    // ----------------------------------------------------------------------------
+    // schoolbook multiplication, the result has 2 * frac_limbs limbs of fraction
+    // the frac_limbs least significant ones are dropped (truncation)
+    pub fn mul(&self, other: &Self) -> Self {
+        let n = self.limbs.len();
+        let frac_limbs = n - 1;
+        let mut product = vec![0u64; 2 * n];
+        for (i, &a) in self.limbs.iter().enumerate() {
+            if a == 0 {
+                continue;
+            }
+            let mut carry: u128 = 0;
+            for (j, &b) in other.limbs.iter().enumerate() {
+                let current = product[i + j] as u128 + a as u128 * b as u128 + carry;
+                product[i + j] = current as u64;
+                carry = current >> 64;
+            }
+            product[i + n] = carry as u64;
+        }
+
+        let mut result = BigFixed {
+            negative: self.negative != other.negative,
+            limbs: product[frac_limbs..frac_limbs + n].to_vec(),
+        };
+        result.negative &= !result.is_zero();
+        result
+    }
+
+    // ----------------------------------------------------------------------------
+    // multiplication by 2, a shift of one bit
+    pub fn double(&self) -> Self {
+        let mut carry = 0;
+        let limbs = self
+            .limbs
+            .iter()
+            .map(|&l| {
+                let shifted = (l << 1) | carry;
+                carry = l >> 63;
+                shifted
+            })
+            .collect();
+        BigFixed {
+            negative: self.negative,
+            limbs,
+        }
+    }
+}
+
+// ----------------------------------------------------------------------------
+// a complex number made of 2 BigFixed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BigComplex {
+    pub re: BigFixed,
+    pub im: BigFixed,
+}
+
+impl BigComplex {
+    // ----------------------------------------------------------------------------
+    pub fn zero(frac_limbs: usize) -> Self {
+        BigComplex {
+            re: BigFixed::zero(frac_limbs),
+            im: BigFixed::zero(frac_limbs),
+        }
+    }
+
+    // ----------------------------------------------------------------------------
+    pub fn to_complex(&self) -> num_complex::Complex<f64> {
+        num_complex::Complex::new(self.re.to_f64(), self.im.to_f64())
+    }
+
+    // ----------------------------------------------------------------------------
+    pub fn add(&self, other: &Self) -> Self {
+        BigComplex {
+            re: self.re.add(&other.re),
+            im: self.im.add(&other.im),
+        }
+    }
+
+    // ----------------------------------------------------------------------------
+    // z * z + c : (a + ib)^2 = a^2 - b^2 + 2iab, 3 multiplications
+    pub fn square_add(&self, c: &Self) -> Self {
+        let a2 = self.re.mul(&self.re);
+        let b2 = self.im.mul(&self.im);
+        let ab = self.re.mul(&self.im);
+        BigComplex {
+            re: a2.sub(&b2).add(&c.re),
+            im: ab.double().add(&c.im),
+        }
+    }
+}
+
+// ----------------------------------------------------------------------------
+// the decimal numbers of BigFixed::parse() and of the coordinates (see coord.rs) : "-0.74364388", "1.5e-3", "2", ".5"...
+// returns the sign, the digits (the most significant first) and the position of the decimal point in them
+// the position is negative when the point is before the first digit ("1e-3" is 1 with the point at -2)
+pub fn parse_decimal(text: &str) -> Result<(bool, Vec<u8>, i64), String> {
+    let error = || format!("{text} is not a valid number");
+    let trimmed = text.trim();
+    let (negative, unsigned) = match trimmed.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, trimmed.strip_prefix('+').unwrap_or(trimmed)),
+    };
+    let (mantissa, exponent) = match unsigned.split_once(['e', 'E']) {
+        Some((m, e)) => (m, e.parse::<i64>().map_err(|_| error())?),
+        None => (unsigned, 0),
+    };
+    let (int_digits, frac_digits) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+    if int_digits.is_empty() && frac_digits.is_empty() {
+        return Err(error());
+    }
+    if !int_digits
+        .chars()
+        .chain(frac_digits.chars())
+        .all(|c| c.is_ascii_digit())
+    {
+        return Err(error());
+    }
+    if int_digits.len() + frac_digits.len() > MAX_DIGITS
+        || exponent.unsigned_abs() > MAX_DIGITS as u64
+    {
+        return Err(format!("{text} : more than {MAX_DIGITS} digits"));
+    }
+
+    // the exponent only moves the decimal point, both are bounded : no overflow
+    let digits = int_digits
+        .bytes()
+        .chain(frac_digits.bytes())
+        .map(|c| c - b'0')
+        .collect();
+    Ok((negative, digits, int_digits.len() as i64 + exponent))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // ----------------------------------------------------------------------------
+    fn fixed(negative: bool, limbs: &[u64]) -> BigFixed {
+        BigFixed {
+            negative,
+            limbs: limbs.to_vec(),
+        }
+    }
+
+    // ----------------------------------------------------------------------------
+    #[test]
+    fn parse_fills_the_limbs() {
+        assert_eq!(
+            BigFixed::parse("0.5", 1).unwrap(),
+            fixed(false, &[1 << 63, 0])
+        );
+        assert_eq!(
+            BigFixed::parse("-2.25", 2).unwrap(),
+            fixed(true, &[0, 1 << 62, 2])
+        );
+        assert_eq!(BigFixed::parse("-0.0", 1).unwrap(), BigFixed::zero(1));
+        // 2^-64 = 5.42101086242752217003726400434970855712890625e-20, exactly
+        assert_eq!(
+            BigFixed::parse("5.42101086242752217003726400434970855712890625e-20", 1).unwrap(),
+            fixed(false, &[1, 0])
+        );
+        assert!(BigFixed::parse("18446744073709551616", 1).is_err());
+    }
+
+    // ----------------------------------------------------------------------------
+    #[test]
+    fn add_carries_across_the_limbs() {
+        let almost_one = fixed(false, &[u64::MAX, u64::MAX, 0]);
+        let tiny = fixed(false, &[1, 0, 0]);
+        assert_eq!(almost_one.add(&tiny), fixed(false, &[0, 0, 1]));
+        assert_eq!(fixed(false, &[0, 0, 1]).sub(&tiny), almost_one);
+        // the borrow goes the other way
+        assert_eq!(tiny.sub(&fixed(false, &[0, 0, 1])), almost_one.neg());
+    }
+
+    // ----------------------------------------------------------------------------
+    #[test]
+    fn add_of_opposite_signs() {
+        let quarter = BigFixed::parse("0.25", 2).unwrap();
+        let three_quarters = BigFixed::parse("-0.75", 2).unwrap();
+        assert_eq!(
+            quarter.add(&three_quarters),
+            BigFixed::parse("-0.5", 2).unwrap()
+        );
+        assert_eq!(
+            three_quarters.add(&quarter),
+            BigFixed::parse("-0.5", 2).unwrap()
+        );
+        // no negative zero
+        assert_eq!(quarter.add(&quarter.neg()), BigFixed::zero(2));
+    }
+
+    // ----------------------------------------------------------------------------
+    #[test]
+    fn neg_keeps_zero_positive() {
+        assert_eq!(BigFixed::zero(2).neg(), BigFixed::zero(2));
+        let x = BigFixed::parse("-1.125", 2).unwrap();
+        assert_eq!(x.neg(), BigFixed::parse("1.125", 2).unwrap());
+        assert_eq!(x.neg().neg(), x);
+    }
+
+    // ----------------------------------------------------------------------------
+    #[test]
+    fn mul_signs_and_carries() {
+        let parse = |text| BigFixed::parse(text, 2).unwrap();
+        assert_eq!(parse("-1.5").mul(&parse("2.25")), parse("-3.375"));
+        assert_eq!(parse("1.5").mul(&parse("-2.25")), parse("-3.375"));
+        assert_eq!(parse("-0.5").mul(&parse("-0.5")), parse("0.25"));
+        assert_eq!(parse("-0.5").mul(&BigFixed::zero(2)), BigFixed::zero(2));
+        // (1 - 2^-128)^2 = 1 - 2^-127 + 2^-256, the 2^-256 is truncated
+        let almost_one = fixed(false, &[u64::MAX, u64::MAX, 0]);
+        assert_eq!(
+            almost_one.mul(&almost_one),
+            fixed(false, &[u64::MAX - 1, u64::MAX, 0])
+        );
+        // 0.1 is truncated by parse(), 10 * 0.1 is 1 minus a few units of the last limb
+        let error = parse("0.1").mul(&parse("10")).sub(&parse("1"));
+        assert!(
+            error.to_f64() <= 0.0 && error.to_f64() > -1e-37,
+            "{error:?}"
+        );
+    }
+}
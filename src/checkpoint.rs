@@ -0,0 +1,250 @@
+// checkpoint of a long rendering : the tiles already computed are saved in a file from time to time
+// a rendering killed at 90 % (crash, reboot, Ctrl-C) is resumed from the file and only computes the missing tiles
+// the tiles are the bands of TILE_ROWS rows of mt_smooth_map_tiles() (see render.rs)
+// the file keeps the smooth iterations themselves (the escape data, bit for bit), not the colors
+// so the resumed image is exactly the one of an uninterrupted rendering, whatever the palette
+// a checkpoint belongs to one job : view, precision, iterations and size are checked by load()
+//
+// the file is (little-endian) :
+//      MANDELCK, version u32
+//      key length u32, key (the Debug of the view, the precision and the iterations)
+//      width u32, height u32, tile rows u32
+//      one byte per tile : 1 if it is done
+//      one record per pixel : 0 for the set or the pixels not done yet, 1 followed by the f64 smooth iteration
+// it is written to a .tmp file then renamed, a crash during a save leaves the previous checkpoint
+
+use crate::handle::RenderHandle;
+use crate::render::{SmoothJob, TILE_ROWS, mt_smooth_map_tiles};
+use std::error::Error;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+const MAGIC: &[u8; 8] = b"MANDELCK";
+const VERSION: u32 = 1;
+
+// time between 2 saves while rendering
+pub const SAVE_PERIOD: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone)]
+pub struct Checkpoint {
+    path: PathBuf,
+    key: String,
+    width: u32,
+    height: u32,
+    done: Vec<bool>,
+    smooth: Vec<Option<f64>>,
+    saved: Instant,
+}
+
+impl Checkpoint {
+    // ----------------------------------------------------------------------------
+    // nothing done yet, nothing written until save()
+    pub fn new(path: &Path, job: &SmoothJob) -> Self {
+        Checkpoint {
+            path: path.to_path_buf(),
+            key: key(job),
+            width: job.width,
+            height: job.height,
+            done: vec![false; job.height.div_ceil(TILE_ROWS) as usize],
+            smooth: vec![None; (job.width * job.height) as usize],
+            saved: Instant::now(),
+        }
+    }
+
+    // ----------------------------------------------------------------------------
+    // Err if the file cannot be read or if it is the checkpoint of another job
+    pub fn load(path: &Path, job: &SmoothJob) -> Result<Self, Box<dyn Error>> {
+        let bytes = fs::read(path)?;
+        let mut reader = Reader { bytes: &bytes };
+
+        if reader.take(MAGIC.len())? != MAGIC {
+            return Err("not a checkpoint file".into());
+        }
+        let version = reader.u32()?;
+        if version != VERSION {
+            return Err(format!("checkpoint version {version}, expected {VERSION}").into());
+        }
+        let length = reader.u32()? as usize;
+        let key_bytes = reader.take(length)?;
+        let (width, height, tile_rows) = (reader.u32()?, reader.u32()?, reader.u32()?);
+
+        let mut checkpoint = Checkpoint::new(path, job);
+        if key_bytes != checkpoint.key.as_bytes() {
+            return Err(format!(
+                "the checkpoint is for another view : {}",
+                String::from_utf8_lossy(key_bytes)
+            )
+            .into());
+        }
+        if (width, height, tile_rows) != (job.width, job.height, TILE_ROWS) {
+            return Err(format!(
+                "the checkpoint is for a {width}x{height} image with tiles of {tile_rows} rows"
+            )
+            .into());
+        }
+
+        for done in checkpoint.done.iter_mut() {
+            *done = reader.take(1)?[0] == 1;
+        }
+        for smooth in checkpoint.smooth.iter_mut() {
+            *smooth = match reader.take(1)?[0] {
+                0 => None,
+                _ => Some(f64::from_le_bytes(reader.take(8)?.try_into()?)),
+            };
+        }
+        Ok(checkpoint)
+    }
+
+    // ----------------------------------------------------------------------------
+    pub fn tiles_done(&self) -> usize {
+        self.done.iter().filter(|&&done| done).count()
+    }
+
+    // ----------------------------------------------------------------------------
+    pub fn tiles(&self) -> usize {
+        self.done.len()
+    }
+
+    // ----------------------------------------------------------------------------
+    // the tile of rows y_start.. is computed
+    pub fn record(&mut self, y_start: u32, tile: &[Option<f64>]) {
+        let start = (y_start * self.width) as usize;
+        self.smooth[start..start + tile.len()].copy_from_slice(tile);
+        self.done[(y_start / TILE_ROWS) as usize] = true;
+    }
+
+    // ----------------------------------------------------------------------------
+    pub fn save(&mut self) -> io::Result<()> {
+        write_file(&self.path, &self.encode())?;
+        self.saved = Instant::now();
+        Ok(())
+    }
+
+    // ----------------------------------------------------------------------------
+    // the content of the file, in memory : made from a copy of the checkpoint, the workers do not wait for it (see mt_resumable_smooth_map())
+    fn encode(&self) -> Vec<u8> {
+        let mut out =
+            Vec::with_capacity(32 + self.key.len() + self.done.len() + 9 * self.smooth.len());
+        out.extend_from_slice(MAGIC);
+        out.extend_from_slice(&VERSION.to_le_bytes());
+        out.extend_from_slice(&(self.key.len() as u32).to_le_bytes());
+        out.extend_from_slice(self.key.as_bytes());
+        for value in [self.width, self.height, TILE_ROWS] {
+            out.extend_from_slice(&value.to_le_bytes());
+        }
+        out.extend(self.done.iter().map(|&done| done as u8));
+        for smooth in &self.smooth {
+            match smooth {
+                Some(smooth_i) => {
+                    out.push(1);
+                    out.extend_from_slice(&smooth_i.to_le_bytes());
+                }
+                None => out.push(0),
+            }
+        }
+        out
+    }
+
+    // ----------------------------------------------------------------------------
+    // once the image is saved the checkpoint is useless
+    pub fn remove(self) -> io::Result<()> {
+        match fs::remove_file(&self.path) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        }
+    }
+}
+
+// ----------------------------------------------------------------------------
+// mt_smooth_map_tiles() which starts from the checkpoint : smooth gets the tiles already done, the others are computed
+// the checkpoint is saved every SAVE_PERIOD while rendering, and at the end (finished or cancelled)
+// Err is the first failed save, the rendering itself goes on
+pub fn mt_resumable_smooth_map(
+    job: &SmoothJob,
+    checkpoint: &mut Checkpoint,
+    smooth: &mut [Option<f64>],
+    handle: &RenderHandle,
+) -> io::Result<()> {
+    smooth.copy_from_slice(&checkpoint.smooth);
+    let done = checkpoint.done.clone();
+    let shared = Mutex::new(&mut *checkpoint);
+    let status = Mutex::new(Ok(()));
+    // held by the worker which writes the file, the others do not wait for it and go on rendering
+    let saving = Mutex::new(());
+
+    mt_smooth_map_tiles(
+        job,
+        smooth,
+        handle,
+        |y_start| done[(y_start / TILE_ROWS) as usize],
+        |y_start, tile| {
+            let due = {
+                let mut checkpoint = shared.lock().unwrap();
+                checkpoint.record(y_start, tile);
+                checkpoint.saved.elapsed() >= SAVE_PERIOD
+            };
+            if !due || status.lock().unwrap().is_err() {
+                return;
+            }
+            let Ok(_saving) = saving.try_lock() else {
+                return;
+            };
+            // the copy is taken under the lock, it is encoded and written without it
+            let copy = {
+                let mut checkpoint = shared.lock().unwrap();
+                checkpoint.saved = Instant::now();
+                Checkpoint::clone(&checkpoint)
+            };
+            if let Err(e) = write_file(&copy.path, &copy.encode()) {
+                *status.lock().unwrap() = Err(e);
+            }
+        },
+    );
+
+    status
+        .into_inner()
+        .unwrap()
+        .and_then(|()| checkpoint.save())
+}
+
+// ----------------------------------------------------------------------------
+// to a .tmp file then renamed, a crash during the write leaves the previous checkpoint
+fn write_file(path: &Path, bytes: &[u8]) -> io::Result<()> {
+    let mut tmp = path.to_path_buf().into_os_string();
+    tmp.push(".tmp");
+    let mut out = fs::File::create(&tmp)?;
+    out.write_all(bytes)?;
+    out.sync_all()?;
+    fs::rename(&tmp, path)
+}
+
+// ----------------------------------------------------------------------------
+// what identifies the job, all the digits of the center are in the Debug of the view
+fn key(job: &SmoothJob) -> String {
+    format!("{:?} {} {}", job.view, job.precision, job.iterations)
+}
+
+// the bytes of the file not read yet
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    // ----------------------------------------------------------------------------
+    fn take(&mut self, count: usize) -> Result<&'a [u8], Box<dyn Error>> {
+        if self.bytes.len() < count {
+            return Err("truncated checkpoint file".into());
+        }
+        let (head, tail) = self.bytes.split_at(count);
+        self.bytes = tail;
+        Ok(head)
+    }
+
+    // ----------------------------------------------------------------------------
+    fn u32(&mut self) -> Result<u32, Box<dyn Error>> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into()?))
+    }
+}
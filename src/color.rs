@@ -0,0 +1,204 @@
+// the colors of an image before they are written : f64 components between 0.0 and 1.0, and an alpha
+// 8 bits rgb (palette() bytes) bands in the smooth gradients, the same buffer written with 16 bits per component does not (see output.rs)
+// the modes :
+//      - palette : the colors of palette(), the set is black
+//      - gray : the luminance of the palette colors (Rec. 709), a grayscale image
+//      - bw : black and white, the renderer of main_00 and main_01 : the points which escaped are white, the set is black
+// with alpha the set is transparent (to composite the image on something else), the other pixels are opaque
+// the set is told by a flag per pixel (escaped_pixels(), see render.rs) and not by the smooth iteration :
+// the points which escape at the first iteration (|c| > 2) have none, they are black in the palette but white in bw and opaque
+// rgb8() gives exactly the bytes of color_frame() for the palette without alpha, the usual images did not change
+
+use crate::render::palette_unit;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ColorMode {
+    #[default]
+    Palette,
+    Gray,
+    BlackWhite,
+}
+
+impl FromStr for ColorMode {
+    type Err = String;
+
+    // ----------------------------------------------------------------------------
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "palette" => Ok(ColorMode::Palette),
+            "gray" => Ok(ColorMode::Gray),
+            "bw" => Ok(ColorMode::BlackWhite),
+            _ => Err(format!("{s} is not palette, gray or bw")),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ColorImage {
+    pub width: u32,
+    pub height: u32,
+    // true for gray and bw, the 3 components of a pixel are then the same
+    pub gray: bool,
+    // the set is transparent
+    pub alpha: bool,
+    // r, g, b, a
+    pub pixels: Vec<[f64; 4]>,
+}
+
+impl ColorImage {
+    // ----------------------------------------------------------------------------
+    // smooth and escaped are width * height, a None smooth iteration is black
+    pub fn from_smooth(
+        smooth: &[Option<f64>],
+        escaped: &[bool],
+        offset: f64,
+        mode: ColorMode,
+        alpha: bool,
+        width: u32,
+        height: u32,
+    ) -> Self {
+        let colors: Vec<_> = smooth
+            .iter()
+            .map(|smooth_i| smooth_i.map(|smooth_i| palette_unit(smooth_i, offset)))
+            .collect();
+        Self::from_colors(&colors, escaped, mode, alpha, width, height)
+    }
+
+    // ----------------------------------------------------------------------------
+    // colors computed some other way (the orbit traps, see trap.rs), None is black
+    // palette keeps them, gray takes their luminance, bw makes the escaped pixels white and the others black
+    // with alpha the pixels which did not escape are transparent
+    pub fn from_colors(
+        colors: &[Option<[f64; 3]>],
+        escaped: &[bool],
+        mode: ColorMode,
+        alpha: bool,
+        width: u32,
+        height: u32,
+    ) -> Self {
+        let pixels = colors
+            .iter()
+            .zip(escaped)
+            .map(|(color, &escaped)| {
+                let [r, g, b] = match (color, mode) {
+                    (_, ColorMode::BlackWhite) if escaped => [1.0; 3],
+                    (None, _) | (_, ColorMode::BlackWhite) => [0.0; 3],
+                    (Some(color), ColorMode::Palette) => *color,
+                    (Some([r, g, b]), ColorMode::Gray) => [0.2126 * r + 0.7152 * g + 0.0722 * b; 3],
+                };
+                [r, g, b, if alpha && !escaped { 0.0 } else { 1.0 }]
+            })
+            .collect();
+        ColorImage {
+            width,
+            height,
+            gray: mode != ColorMode::Palette,
+            alpha,
+            pixels,
+        }
+    }
+
+    // ----------------------------------------------------------------------------
+    // 8 bits rgb without alpha, for the terminal and the outputs which only take rgb
+    // the components are truncated like in palette()
+    pub fn rgb8(&self) -> Vec<u8> {
+        self.pixels
+            .iter()
+            .flat_map(|&[r, g, b, _]| [to_u8(r), to_u8(g), to_u8(b)])
+            .collect()
+    }
+
+    // ----------------------------------------------------------------------------
+    // the channels of the image (1 gray, 2 gray + alpha, 3 rgb, 4 rgba), 8 bits per sample
+    pub fn samples8(&self) -> Vec<u8> {
+        self.samples(|v, out| out.push(to_u8(v)))
+    }
+
+    // ----------------------------------------------------------------------------
+    // the same with 16 bits per sample, big endian (the order of png)
+    // rounded to the nearest value : 65535 levels, the truncation of the bytes does not matter any more
+    pub fn samples16(&self) -> Vec<u8> {
+        self.samples(|v, out| {
+            out.extend_from_slice(&((v.clamp(0.0, 1.0) * 65535.0).round() as u16).to_be_bytes())
+        })
+    }
+
+    // ----------------------------------------------------------------------------
+    fn samples(&self, sample: impl Fn(f64, &mut Vec<u8>)) -> Vec<u8> {
+        let mut out = Vec::new();
+        for &[r, g, b, a] in &self.pixels {
+            let components: &[f64] = match (self.gray, self.alpha) {
+                (true, false) => &[r],
+                (true, true) => &[r, a],
+                (false, false) => &[r, g, b],
+                (false, true) => &[r, g, b, a],
+            };
+            for &v in components {
+                sample(v, &mut out);
+            }
+        }
+        out
+    }
+}
+
+// ----------------------------------------------------------------------------
+fn to_u8(v: f64) -> u8 {
+    (v * 255.0) as u8
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::handle::RenderHandle;
+    use crate::real::Precision;
+    use crate::render::{Fractal, ITERATIONS, escaped_pixels, mt_smooth_map_with};
+    use crate::view::Viewport;
+
+    // ----------------------------------------------------------------------------
+    // the default view at 64x48 : (0, 0) and (0, 24) are around c = -2.5, (32, 24) is c = -0.75 in the set
+    fn default_view(mode: ColorMode, alpha: bool) -> ColorImage {
+        let (view, width, height) = (Viewport::default(), 64, 48);
+        let mut smooth = vec![None; (width * height) as usize];
+        mt_smooth_map_with(
+            &view,
+            Precision::Double,
+            ITERATIONS,
+            width,
+            height,
+            &mut smooth,
+            &RenderHandle::new(),
+        );
+        let escaped = escaped_pixels(
+            &view,
+            Fractal::Mandelbrot,
+            Precision::Double,
+            &smooth,
+            width,
+            height,
+        );
+        assert_eq!((smooth[0], smooth[24 * 64]), (None, None));
+        ColorImage::from_smooth(&smooth, &escaped, 0.0, mode, alpha, width, height)
+    }
+
+    // ----------------------------------------------------------------------------
+    #[test]
+    fn bw_paints_the_first_escapes_white() {
+        let image = default_view(ColorMode::BlackWhite, false);
+        assert_eq!(image.pixels[0], [1.0; 4]);
+        assert_eq!(image.pixels[24 * 64], [1.0; 4]);
+        assert_eq!(image.pixels[24 * 64 + 32], [0.0, 0.0, 0.0, 1.0]);
+    }
+
+    // ----------------------------------------------------------------------------
+    #[test]
+    fn alpha_keeps_the_first_escapes_opaque() {
+        let image = default_view(ColorMode::Palette, true);
+        // black as in palette(), but opaque
+        assert_eq!(image.pixels[0], [0.0, 0.0, 0.0, 1.0]);
+        assert_eq!(image.pixels[24 * 64], [0.0, 0.0, 0.0, 1.0]);
+        assert_eq!(image.pixels[24 * 64 + 32][3], 0.0);
+        let image = default_view(ColorMode::BlackWhite, true);
+        assert_eq!(image.pixels[0], [1.0; 4]);
+    }
+}
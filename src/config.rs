@@ -0,0 +1,97 @@
+// small helpers to read the values of the toml files (job lists, animations...)
+// the errors are Strings, they are displayed as they are
+
+use crate::coord::{ComplexCoord, Coord};
+use num_complex::Complex;
+
+// ----------------------------------------------------------------------------
+pub fn get_u32(table: &toml::Table, key: &str) -> Result<Option<u32>, String> {
+    match table.get(key) {
+        Some(value) => value
+            .as_integer()
+            .and_then(|n| u32::try_from(n).ok())
+            .map(Some)
+            .ok_or(format!("{key} must be a positive integer")),
+        None => Ok(None),
+    }
+}
+
+// ----------------------------------------------------------------------------
+// integers are accepted (zoom = 2 is easier to type than zoom = 2.0)
+pub fn get_f64(table: &toml::Table, key: &str) -> Result<Option<f64>, String> {
+    match table.get(key) {
+        Some(value) => as_f64(value)
+            .map(Some)
+            .ok_or(format!("{key} must be a number")),
+        None => Ok(None),
+    }
+}
+
+// ----------------------------------------------------------------------------
+// [re, im]
+pub fn get_complex(table: &toml::Table, key: &str) -> Result<Option<Complex<f64>>, String> {
+    let Some(value) = table.get(key) else {
+        return Ok(None);
+    };
+    let error = || format!("{key} must be [re, im]");
+    let array = value.as_array().ok_or_else(error)?;
+    let parts: Vec<f64> = array.iter().filter_map(as_f64).collect();
+    match parts[..] {
+        [re, im] if array.len() == 2 => Ok(Some(Complex::new(re, im))),
+        _ => Err(error()),
+    }
+}
+
+// ----------------------------------------------------------------------------
+pub fn get_str<'a>(table: &'a toml::Table, key: &str) -> Result<Option<&'a str>, String> {
+    match table.get(key) {
+        Some(value) => value
+            .as_str()
+            .map(Some)
+            .ok_or(format!("{key} must be a string")),
+        None => Ok(None),
+    }
+}
+
+// ----------------------------------------------------------------------------
+// [[name]] sections. An absent section is an empty list
+pub fn get_tables<'a>(table: &'a toml::Table, key: &str) -> Result<Vec<&'a toml::Table>, String> {
+    match table.get(key) {
+        Some(value) => value
+            .as_array()
+            .and_then(|array| array.iter().map(|v| v.as_table()).collect())
+            .ok_or(format!("{key} must be an array of tables ([[{key}]])")),
+        None => Ok(Vec::new()),
+    }
+}
+
+// ----------------------------------------------------------------------------
+fn as_f64(value: &toml::Value) -> Option<f64> {
+    value.as_float().or(value.as_integer().map(|n| n as f64))
+}
+
+// ----------------------------------------------------------------------------
+// a point with all its digits (see coord.rs) : "re,im" or [re, im]
+// in toml a number is a f64, so the deep coordinates must be written as strings : ["-0.743643887037158704752191506114774", "0.131825904205311970493132056385139"]
+pub fn get_coord(table: &toml::Table, key: &str) -> Result<Option<ComplexCoord>, String> {
+    let Some(value) = table.get(key) else {
+        return Ok(None);
+    };
+    let error = || format!("{key} must be \"re,im\" or [re, im]");
+    if let Some(text) = value.as_str() {
+        return text.parse().map(Some).map_err(|e| format!("{key} : {e}"));
+    }
+
+    let array = value.as_array().ok_or_else(error)?;
+    let parts = array
+        .iter()
+        .map(|v| match v {
+            toml::Value::String(text) => text.parse::<Coord>(),
+            _ => as_f64(v).map(Coord::from_f64).ok_or_else(error),
+        })
+        .collect::<Result<Vec<Coord>, String>>()?;
+    match <[Coord; 2]>::try_from(parts) {
+        Ok([re, im]) => Ok(Some(ComplexCoord { re, im })),
+        Err(_) => Err(error()),
+    }
+}
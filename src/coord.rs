@@ -0,0 +1,424 @@
+// arbitrary precision coordinates, for the center of the views
+// a f64 has 17 significant digits, below a zoom of about 1e15 the center of a view cannot even be written down
+// Coord is a DECIMAL number : all the digits which are read are kept, and printed back exactly as they were read
+//      "-0.743643887037158704752191506114774" => Coord => "-0.743643887037158704752191506114774"
+// the value is sign * digits / 10^scale (digits is little endian, digits[0] is the least significant one)
+// the computations on the coordinates are rare (move a view, interpolate 2 keyframes...) so the digits are simply stored one per byte
+// for the iterations, a Coord is converted to a f64 (to_f64()) or to a binary BigFixed (to_fixed(), see bignum.rs)
+
+use crate::bignum::{BigComplex, BigFixed, parse_decimal};
+use num_complex::Complex;
+use std::cmp::Ordering;
+use std::fmt;
+use std::str::FromStr;
+
+// extra digits kept by mul_f64()
+const GUARD_DIGITS: usize = 20;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Coord {
+    negative: bool,
+    digits: Vec<u8>,
+    scale: usize,
+}
+
+impl Coord {
+    // ----------------------------------------------------------------------------
+    pub fn zero() -> Self {
+        Coord {
+            negative: false,
+            digits: Vec::new(),
+            scale: 0,
+        }
+    }
+
+    // ----------------------------------------------------------------------------
+    // the shortest decimal which gives back the same f64 ("{:e}" of Rust), -0.75 is -0.75 and not -0.75000000000000001...
+    pub fn from_f64(value: f64) -> Self {
+        if !value.is_finite() {
+            return Coord::zero();
+        }
+        format!("{value:e}")
+            .parse()
+            .unwrap_or_else(|_| Coord::zero())
+    }
+
+    // ----------------------------------------------------------------------------
+    // the nearest f64 (the parser of the standard library rounds correctly)
+    pub fn to_f64(&self) -> f64 {
+        self.to_string().parse().unwrap_or(0.0)
+    }
+
+    // ----------------------------------------------------------------------------
+    // Err when the integer part does not fit the only integer limb of a BigFixed (see bignum.rs)
+    // the digits beyond frac_limbs are rounded away first : a limb is less than 20 decimal digits
+    pub fn to_fixed(&self, frac_limbs: usize) -> Result<BigFixed, String> {
+        BigFixed::parse(&self.round(20 * frac_limbs + 1).to_string(), frac_limbs)
+    }
+
+    // ----------------------------------------------------------------------------
+    // number of digits after the decimal point
+    pub fn scale(&self) -> usize {
+        self.scale
+    }
+
+    // ----------------------------------------------------------------------------
+    pub fn is_zero(&self) -> bool {
+        self.digits.is_empty()
+    }
+
+    // ----------------------------------------------------------------------------
+    // no zero on the left of the number, no zero on the right of the fraction, no "-0"
+    fn normalize(mut self) -> Self {
+        while self.digits.last() == Some(&0) {
+            self.digits.pop();
+        }
+        let trailing = self
+            .digits
+            .iter()
+            .take(self.scale)
+            .take_while(|&&d| d == 0)
+            .count();
+        self.digits.drain(..trailing);
+        self.scale -= trailing;
+        if self.digits.is_empty() {
+            self.scale = 0;
+        }
+        self.negative &= !self.digits.is_empty();
+        self
+    }
+
+    // ----------------------------------------------------------------------------
+    // the digits with exactly scale digits after the point (scale >= self.scale)
+    fn digits_at_scale(&self, scale: usize) -> Vec<u8> {
+        let mut digits = vec![0; scale - self.scale];
+        digits.extend(&self.digits);
+        digits
+    }
+
+    // ----------------------------------------------------------------------------
+    fn cmp_digits(a: &[u8], b: &[u8]) -> Ordering {
+        a.len()
+            .cmp(&b.len())
+            .then_with(|| a.iter().rev().cmp(b.iter().rev()))
+    }
+
+    // ----------------------------------------------------------------------------
+    pub fn add(&self, other: &Self) -> Self {
+        let scale = self.scale.max(other.scale);
+        let a = self.digits_at_scale(scale);
+        let b = other.digits_at_scale(scale);
+
+        let (negative, digits) = if self.negative == other.negative {
+            let mut digits = Vec::with_capacity(a.len().max(b.len()) + 1);
+            let mut carry = 0;
+            for i in 0..a.len().max(b.len()) {
+                let sum = a.get(i).unwrap_or(&0) + b.get(i).unwrap_or(&0) + carry;
+                digits.push(sum % 10);
+                carry = sum / 10;
+            }
+            digits.push(carry);
+            (self.negative, digits)
+        } else {
+            // the largest magnitude gives the sign
+            let (big, small, negative) = match Self::cmp_digits(&a, &b) {
+                Ordering::Less => (&b, &a, other.negative),
+                _ => (&a, &b, self.negative),
+            };
+            let mut digits = Vec::with_capacity(big.len());
+            let mut borrow = 0;
+            for (i, &digit) in big.iter().enumerate() {
+                let mut diff = digit as i8 - *small.get(i).unwrap_or(&0) as i8 - borrow;
+                borrow = (diff < 0) as i8;
+                if diff < 0 {
+                    diff += 10;
+                }
+                digits.push(diff as u8);
+            }
+            (negative, digits)
+        };
+
+        Coord {
+            negative,
+            digits,
+            scale,
+        }
+        .normalize()
+    }
+
+    // ----------------------------------------------------------------------------
+    pub fn neg(&self) -> Self {
+        Coord {
+            negative: !self.negative && !self.is_zero(),
+            ..self.clone()
+        }
+    }
+
+    // ----------------------------------------------------------------------------
+    pub fn sub(&self, other: &Self) -> Self {
+        self.add(&other.neg())
+    }
+
+    // ----------------------------------------------------------------------------
+    // exact, the scale of the result is the sum of the scales
+    pub fn mul(&self, other: &Self) -> Self {
+        if self.is_zero() || other.is_zero() {
+            return Coord::zero();
+        }
+        let mut product = vec![0u32; self.digits.len() + other.digits.len()];
+        for (i, &a) in self.digits.iter().enumerate() {
+            for (j, &b) in other.digits.iter().enumerate() {
+                product[i + j] += a as u32 * b as u32;
+            }
+        }
+        let mut digits = Vec::with_capacity(product.len() + 1);
+        let mut carry = 0;
+        for p in product {
+            let current = p + carry;
+            digits.push((current % 10) as u8);
+            carry = current / 10;
+        }
+        while carry > 0 {
+            digits.push((carry % 10) as u8);
+            carry /= 10;
+        }
+
+        Coord {
+            negative: self.negative != other.negative,
+            digits,
+            scale: self.scale + other.scale,
+        }
+        .normalize()
+    }
+
+    // ----------------------------------------------------------------------------
+    // a f64 factor (an interpolation weight, an offset in pixels...) is only 17 digits
+    // the result keeps the digits of self plus GUARD_DIGITS, not all the digits of the exact product
+    pub fn mul_f64(&self, factor: f64) -> Self {
+        self.mul(&Coord::from_f64(factor))
+            .round(self.scale + GUARD_DIGITS)
+    }
+
+    // ----------------------------------------------------------------------------
+    // at most scale digits after the point, rounded half away from zero
+    pub fn round(&self, scale: usize) -> Self {
+        if self.scale <= scale {
+            return self.clone();
+        }
+        let dropped = self.scale - scale;
+        let round_up = self.digits.get(dropped - 1).is_some_and(|&d| d >= 5);
+        let mut digits: Vec<u8> = self.digits.iter().skip(dropped).copied().collect();
+        if round_up {
+            let mut i = 0;
+            loop {
+                if i == digits.len() {
+                    digits.push(1);
+                    break;
+                }
+                if digits[i] == 9 {
+                    digits[i] = 0;
+                    i += 1;
+                } else {
+                    digits[i] += 1;
+                    break;
+                }
+            }
+        }
+        Coord {
+            negative: self.negative,
+            digits,
+            scale,
+        }
+        .normalize()
+    }
+}
+
+impl FromStr for Coord {
+    type Err = String;
+
+    // ----------------------------------------------------------------------------
+    // "-0.7436438870371587047521915061147745", "1.5e-3", "2", ".5"... (see parse_decimal())
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let (negative, digits, point) = parse_decimal(text)?;
+        let mut digits: Vec<u8> = digits.into_iter().rev().collect();
+        // the exponent moved the decimal point
+        let scale = digits.len() as i64 - point;
+        if scale < 0 {
+            let mut padded = vec![0; (-scale) as usize];
+            padded.extend(digits);
+            digits = padded;
+        }
+
+        Ok(Coord {
+            negative,
+            digits,
+            scale: scale.max(0) as usize,
+        }
+        .normalize())
+    }
+}
+
+impl fmt::Display for Coord {
+    // ----------------------------------------------------------------------------
+    // plain decimal notation, never an exponent : 0.000000000000000000012 and not 1.2e-20
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut text = String::with_capacity(self.digits.len() + 3);
+        if self.negative {
+            text.push('-');
+        }
+        if self.digits.len() <= self.scale {
+            text.push('0');
+        } else {
+            for &d in self.digits[self.scale..].iter().rev() {
+                text.push((b'0' + d) as char);
+            }
+        }
+        if self.scale > 0 {
+            text.push('.');
+            for i in (0..self.scale).rev() {
+                text.push((b'0' + self.digits.get(i).copied().unwrap_or(0)) as char);
+            }
+        }
+        f.write_str(&text)
+    }
+}
+
+// ----------------------------------------------------------------------------
+// a point of the complex plane with Coord parts
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ComplexCoord {
+    pub re: Coord,
+    pub im: Coord,
+}
+
+impl ComplexCoord {
+    // ----------------------------------------------------------------------------
+    pub fn from_complex(c: Complex<f64>) -> Self {
+        ComplexCoord {
+            re: Coord::from_f64(c.re),
+            im: Coord::from_f64(c.im),
+        }
+    }
+
+    // ----------------------------------------------------------------------------
+    pub fn to_complex(&self) -> Complex<f64> {
+        Complex::new(self.re.to_f64(), self.im.to_f64())
+    }
+
+    // ----------------------------------------------------------------------------
+    // for the reference orbits (see deep.rs)
+    pub fn to_big_complex(&self, frac_limbs: usize) -> Result<BigComplex, String> {
+        Ok(BigComplex {
+            re: self.re.to_fixed(frac_limbs)?,
+            im: self.im.to_fixed(frac_limbs)?,
+        })
+    }
+
+    // ----------------------------------------------------------------------------
+    pub fn add(&self, other: &Self) -> Self {
+        ComplexCoord {
+            re: self.re.add(&other.re),
+            im: self.im.add(&other.im),
+        }
+    }
+
+    // ----------------------------------------------------------------------------
+    pub fn sub(&self, other: &Self) -> Self {
+        ComplexCoord {
+            re: self.re.sub(&other.re),
+            im: self.im.sub(&other.im),
+        }
+    }
+
+    // ----------------------------------------------------------------------------
+    // moves the point by a small f64 offset (a few pixels for example), the digits of the point are kept
+    pub fn add_offset(&self, offset: Complex<f64>) -> Self {
+        self.add(&ComplexCoord::from_complex(offset))
+    }
+
+    // ----------------------------------------------------------------------------
+    pub fn mul_f64(&self, factor: f64) -> Self {
+        ComplexCoord {
+            re: self.re.mul_f64(factor),
+            im: self.im.mul_f64(factor),
+        }
+    }
+}
+
+impl FromStr for ComplexCoord {
+    type Err = String;
+
+    // ----------------------------------------------------------------------------
+    // "re,im"
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let (re, im) = text.split_once(',').ok_or(format!("{text} is not re,im"))?;
+        Ok(ComplexCoord {
+            re: re.parse()?,
+            im: im.parse()?,
+        })
+    }
+}
+
+impl fmt::Display for ComplexCoord {
+    // ----------------------------------------------------------------------------
+    // "re,im", the format read by from_str()
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{},{}", self.re, self.im)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // ----------------------------------------------------------------------------
+    #[test]
+    fn hundreds_of_digits_are_printed_back() {
+        let digits: String = (0..400)
+            .map(|i| (b'0' + (i * 7 % 10) as u8) as char)
+            .collect();
+        let text = format!("-0.7436438870371587047521915061147745{digits}1,0.000{digits}3");
+        let center: ComplexCoord = text.parse().unwrap();
+        assert_eq!(center.to_string(), text);
+        assert_eq!(center.re.scale(), 34 + 400 + 1);
+    }
+
+    // ----------------------------------------------------------------------------
+    #[test]
+    fn exponents_move_the_point() {
+        let parse = |text: &str| text.parse::<Coord>().unwrap().to_string();
+        assert_eq!(parse("1.5e-3"), "0.0015");
+        assert_eq!(parse("-25e2"), "-2500");
+        assert_eq!(parse(".5"), "0.5");
+        assert_eq!(parse("-0.000"), "0");
+        assert_eq!(parse("1e-10000").len(), 10002);
+    }
+
+    // ----------------------------------------------------------------------------
+    #[test]
+    fn huge_numbers_are_errors() {
+        for text in [
+            "1e-9223372036854775808",
+            "1e9223372036854775807",
+            "1e-3000000000",
+            "1e10001",
+            "",
+            "1.2.3",
+            "--1",
+        ] {
+            assert!(text.parse::<Coord>().is_err(), "{text}");
+            assert!(BigFixed::parse(text, 2).is_err(), "{text}");
+        }
+        let too_long = "1".repeat(10_001);
+        assert!(too_long.parse::<Coord>().is_err());
+    }
+
+    // ----------------------------------------------------------------------------
+    #[test]
+    fn to_fixed_fails_beyond_the_integer_limb() {
+        let big: Coord = "123456789012345678901234".parse().unwrap();
+        assert!(big.to_fixed(2).is_err());
+        let small: Coord = "-1.25".parse().unwrap();
+        assert_eq!(small.to_fixed(2).unwrap().to_f64(), -1.25);
+    }
+}
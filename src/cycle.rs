@@ -0,0 +1,132 @@
+// palette cycling : mandel cycle [view options] --frames 64 (--gif file.gif | --apng file.png | --png directory | --y4m file.y4m)
+// the classic way to animate a still fractal : the image does not move, the colors flow
+// the smooth iterations of the pixels are computed ONCE (mt_smooth_map()), then each frame only calls palette() with a new offset
+// the offset goes from 0 to 1 (a full turn of the palette) so the last frame joins the first one and the animation loops
+//
+// gif files have 256 colors at most. The palette only depends on the hue, so the hue is cut in 255 bins (+ black for the set)
+// the index of each pixel never changes, only the 256 colors of the palette of each frame rotate
+
+use crate::render::{mt_smooth_map, palette};
+use crate::video::{VideoOutput, open_sink, write_frames};
+use crate::view::Viewport;
+use std::borrow::Cow;
+use std::error::Error;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+// bins of hue in the gif palette, index 0 is black
+const HUE_BINS: usize = 255;
+
+#[derive(Debug, Clone)]
+pub enum CycleOutput {
+    Gif(PathBuf),
+    Video(VideoOutput),
+}
+
+#[derive(Debug, Clone)]
+pub struct Cycle {
+    pub view: Viewport,
+    pub width: u32,
+    pub height: u32,
+    pub frames: u32,
+    pub fps: u32,
+}
+
+impl Cycle {
+    // ----------------------------------------------------------------------------
+    // the palette offset of the frame n
+    pub fn offset(&self, n: u32) -> f64 {
+        n as f64 / self.frames as f64
+    }
+}
+
+// ----------------------------------------------------------------------------
+// colors an image from the smooth iterations
+pub fn color_frame(smooth: &[Option<f64>], offset: f64, frame: &mut [u8]) {
+    for (pixel, smooth_i) in frame.chunks_exact_mut(3).zip(smooth) {
+        let (r, g, b) = match smooth_i {
+            Some(smooth_i) => palette(*smooth_i, offset),
+            None => (0, 0, 0),
+        };
+        pixel[0] = r;
+        pixel[1] = g;
+        pixel[2] = b;
+    }
+}
+
+// ----------------------------------------------------------------------------
+pub fn run(cycle: &Cycle, output: &CycleOutput) -> Result<(), Box<dyn Error>> {
+    let (width, height) = (cycle.width, cycle.height);
+
+    let start = Instant::now();
+    let mut smooth = vec![None; (width * height) as usize];
+    mt_smooth_map(&cycle.view, width, height, &mut smooth);
+    eprintln!("Smooth map      : {} ms.", start.elapsed().as_millis());
+
+    match output {
+        CycleOutput::Gif(filename) => write_gif(filename, cycle, &smooth),
+        CycleOutput::Video(video) => {
+            let mut sink = open_sink(video, width, height, cycle.frames, cycle.fps)?;
+            write_frames(
+                cycle.frames,
+                (width * height * 3) as usize,
+                |n, frame| color_frame(&smooth, cycle.offset(n), frame),
+                sink.as_mut(),
+            )
+        }
+    }
+}
+
+// ----------------------------------------------------------------------------
+// the hue of a pixel is smooth_i * 0.1 + offset (see palette()), only its fractional part matters
+fn write_gif(filename: &Path, cycle: &Cycle, smooth: &[Option<f64>]) -> Result<(), Box<dyn Error>> {
+    let width =
+        u16::try_from(cycle.width).map_err(|_| "the gif files are 65535 pixels wide at most")?;
+    let height =
+        u16::try_from(cycle.height).map_err(|_| "the gif files are 65535 pixels high at most")?;
+
+    let indices: Vec<u8> = smooth
+        .iter()
+        .map(|smooth_i| match smooth_i {
+            Some(smooth_i) => {
+                let hue = (smooth_i * 0.1).rem_euclid(1.0);
+                1 + ((hue * HUE_BINS as f64) as usize).min(HUE_BINS - 1) as u8
+            }
+            None => 0,
+        })
+        .collect();
+
+    let mut encoder =
+        gif::Encoder::new(BufWriter::new(File::create(filename)?), width, height, &[])?;
+    encoder.set_repeat(gif::Repeat::Infinite)?;
+    // the delay is in 1/100 s
+    let delay = (100.0 / cycle.fps as f64).round().max(1.0) as u16;
+
+    for n in 0..cycle.frames {
+        let mut colors = vec![0u8; 3 * (HUE_BINS + 1)];
+        for bin in 0..HUE_BINS {
+            // the color in the middle of the bin
+            let hue = (bin as f64 + 0.5) / HUE_BINS as f64;
+            let (r, g, b) = palette(0.0, hue + cycle.offset(n));
+            colors[3 * (bin + 1)..3 * (bin + 2)].copy_from_slice(&[r, g, b]);
+        }
+
+        let frame = gif::Frame {
+            width,
+            height,
+            delay,
+            palette: Some(colors),
+            buffer: Cow::Borrowed(&indices),
+            ..gif::Frame::default()
+        };
+        encoder.write_frame(&frame)?;
+    }
+    eprintln!(
+        "Saved           : {} ({} frames)",
+        filename.display(),
+        cycle.frames
+    );
+    Ok(())
+}
@@ -0,0 +1,212 @@
+// double-double : a software float made of 2 f64, value = hi + lo with |lo| <= half an ulp of hi
+// about 106 bits of mantissa (32 digits) instead of 53, roughly 10 times slower than a f64
+// between the limit of f64 (zoom ~1e11) and the limit of double-double (zoom ~1e26) it is much simpler than perturbation (see deep.rs)
+// the algorithms are the classic ones (Dekker, Knuth) : the rounding error of each f64 operation is computed exactly and kept in lo
+// no fused multiply-add is used, f64::mul_add() is emulated (slow) on the CPUs without FMA
+
+use crate::bignum::BigFixed;
+use crate::coord::Coord;
+use std::ops::{Add, Div, Mul, Neg, Sub};
+
+// 2^27 + 1, splits a f64 in 2 halves of 26 bits
+const SPLITTER: f64 = 134_217_729.0;
+
+// precision used to compute lo from a decimal Coord
+const COORD_LIMBS: usize = 4;
+
+// hi first : the derived comparison (hi, then lo) is the right one for normalized values
+#[derive(Debug, Clone, Copy, Default, PartialEq, PartialOrd)]
+pub struct DoubleDouble {
+    pub hi: f64,
+    pub lo: f64,
+}
+
+impl DoubleDouble {
+    // ----------------------------------------------------------------------------
+    pub fn from_f64(value: f64) -> Self {
+        DoubleDouble { hi: value, lo: 0.0 }
+    }
+
+    // ----------------------------------------------------------------------------
+    pub fn to_f64(self) -> f64 {
+        self.hi + self.lo
+    }
+
+    // ----------------------------------------------------------------------------
+    // hi is the nearest f64, lo is what remains, computed exactly with a BigFixed (see bignum.rs)
+    // beyond 2^64 (no BigFixed) lo does not matter : such a point escapes at the first iteration
+    pub fn from_coord(value: &Coord) -> Self {
+        let Ok(fixed) = value.to_fixed(COORD_LIMBS) else {
+            return DoubleDouble::from_f64(value.to_f64());
+        };
+        let hi = fixed.to_f64();
+        let lo = fixed.sub(&BigFixed::from_f64(hi, COORD_LIMBS)).to_f64();
+        quick_two_sum(hi, lo)
+    }
+}
+
+// ----------------------------------------------------------------------------
+// a + b = s + e exactly
+fn two_sum(a: f64, b: f64) -> DoubleDouble {
+    let s = a + b;
+    let bb = s - a;
+    DoubleDouble {
+        hi: s,
+        lo: (a - (s - bb)) + (b - bb),
+    }
+}
+
+// ----------------------------------------------------------------------------
+// same as two_sum() when |a| >= |b|, 3 operations instead of 6
+fn quick_two_sum(a: f64, b: f64) -> DoubleDouble {
+    let s = a + b;
+    DoubleDouble {
+        hi: s,
+        lo: b - (s - a),
+    }
+}
+
+// ----------------------------------------------------------------------------
+// a = hi + lo, each half fits in 26 bits so their products are exact
+fn split(a: f64) -> (f64, f64) {
+    let t = SPLITTER * a;
+    let hi = t - (t - a);
+    (hi, a - hi)
+}
+
+// ----------------------------------------------------------------------------
+// a * b = p + e exactly
+fn two_prod(a: f64, b: f64) -> DoubleDouble {
+    let p = a * b;
+    let (ah, al) = split(a);
+    let (bh, bl) = split(b);
+    DoubleDouble {
+        hi: p,
+        lo: ((ah * bh - p) + ah * bl + al * bh) + al * bl,
+    }
+}
+
+impl Add for DoubleDouble {
+    type Output = Self;
+
+    // ----------------------------------------------------------------------------
+    // the accurate version : the low parts are summed with their own error, z^2 - y^2 cancels a lot in the iterations
+    fn add(self, other: Self) -> Self {
+        let s = two_sum(self.hi, other.hi);
+        let t = two_sum(self.lo, other.lo);
+        let s = quick_two_sum(s.hi, s.lo + t.hi);
+        quick_two_sum(s.hi, s.lo + t.lo)
+    }
+}
+
+impl Neg for DoubleDouble {
+    type Output = Self;
+
+    // ----------------------------------------------------------------------------
+    fn neg(self) -> Self {
+        DoubleDouble {
+            hi: -self.hi,
+            lo: -self.lo,
+        }
+    }
+}
+
+impl Sub for DoubleDouble {
+    type Output = Self;
+
+    // ----------------------------------------------------------------------------
+    fn sub(self, other: Self) -> Self {
+        self + -other
+    }
+}
+
+impl Mul for DoubleDouble {
+    type Output = Self;
+
+    // ----------------------------------------------------------------------------
+    // lo * lo is below the precision and ignored
+    fn mul(self, other: Self) -> Self {
+        let p = two_prod(self.hi, other.hi);
+        quick_two_sum(p.hi, p.lo + (self.hi * other.lo + self.lo * other.hi))
+    }
+}
+
+impl Div for DoubleDouble {
+    type Output = Self;
+
+    // ----------------------------------------------------------------------------
+    // long division : a first quotient in f64, then the quotient of the remainder
+    fn div(self, other: Self) -> Self {
+        let q1 = self.hi / other.hi;
+        let r = self - other * DoubleDouble::from_f64(q1);
+        let q2 = r.hi / other.hi;
+        let r = r - other * DoubleDouble::from_f64(q2);
+        let q3 = r.hi / other.hi;
+        quick_two_sum(q1, q2) + DoubleDouble::from_f64(q3)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // ----------------------------------------------------------------------------
+    fn dd(hi: f64, lo: f64) -> DoubleDouble {
+        DoubleDouble { hi, lo }
+    }
+
+    // ----------------------------------------------------------------------------
+    #[test]
+    fn from_f64_and_back() {
+        for value in [0.0, -1.5, 0.1, 1e300, f64::MIN_POSITIVE] {
+            assert_eq!(DoubleDouble::from_f64(value), dd(value, 0.0));
+            assert_eq!(DoubleDouble::from_f64(value).to_f64(), value);
+        }
+        assert_eq!(dd(1.0, 2f64.powi(-60)).to_f64(), 1.0);
+    }
+
+    // ----------------------------------------------------------------------------
+    #[test]
+    fn add_keeps_what_f64_loses() {
+        let one = DoubleDouble::from_f64(1.0);
+        assert_eq!(one + DoubleDouble::from_f64(1e-20), dd(1.0, 1e-20));
+        // 2^53 + 1 is not a f64
+        let big = DoubleDouble::from_f64(2f64.powi(53));
+        assert_eq!(big + one, dd(2f64.powi(53), 1.0));
+        assert_eq!(big + one - big, one);
+        // 1 + 2^-100 - 1 cancels the high parts
+        assert_eq!(
+            (one + DoubleDouble::from_f64(2f64.powi(-100))) - one,
+            dd(2f64.powi(-100), 0.0)
+        );
+    }
+
+    // ----------------------------------------------------------------------------
+    #[test]
+    fn mul_is_exact_to_106_bits() {
+        // (1 + 2^-52)^2 = 1 + 2^-51 + 2^-104
+        let x = DoubleDouble::from_f64(1.0 + f64::EPSILON);
+        assert_eq!(x * x, dd(1.0 + 2.0 * f64::EPSILON, 2f64.powi(-104)));
+        // (2^27 + 1) * (2^27 - 1) = 2^54 - 1, one bit more than a f64
+        let product = DoubleDouble::from_f64(134_217_729.0) * DoubleDouble::from_f64(134_217_727.0);
+        assert_eq!(product, dd(2f64.powi(54), -1.0));
+        assert_eq!(-x * x, dd(-1.0 - 2.0 * f64::EPSILON, -(2f64.powi(-104))));
+    }
+
+    // ----------------------------------------------------------------------------
+    #[test]
+    fn decimals_are_read_to_106_bits() {
+        // 0.1 is 0.1000000000000000055511151231257827... in f64, lo is the -5.55e-18 left
+        let tenth = DoubleDouble::from_coord(&"0.1".parse().unwrap());
+        assert_eq!(tenth.hi, 0.1);
+        let sum = BigFixed::from_f64(tenth.hi, 4).add(&BigFixed::from_f64(tenth.lo, 4));
+        let error = BigFixed::parse("0.1", 4).unwrap().sub(&sum).to_f64();
+        assert!(tenth.lo < -5.55e-18 && tenth.lo > -5.56e-18);
+        assert!(error.abs() < 1e-33, "{error}");
+        let one = tenth * DoubleDouble::from_f64(10.0);
+        assert!((one - DoubleDouble::from_f64(1.0)).to_f64().abs() < 1e-31);
+        let third = DoubleDouble::from_f64(1.0) / DoubleDouble::from_f64(3.0);
+        let error = third * DoubleDouble::from_f64(3.0) - DoubleDouble::from_f64(1.0);
+        assert!(error.to_f64().abs() < 1e-31, "{error:?}");
+    }
+}
@@ -0,0 +1,292 @@
+// perturbation rendering for the deep zooms : mandel deep --center re,im --zoom 1e20 --iterations 5000
+// with Complex<f64> the image turns into blocks past a zoom of about 1e13, two neighbour pixels get the same c
+// here :
+//      - ONE point, the reference, is iterated with an arbitrary precision (BigComplex, see bignum.rs). Its orbit Z[n] is kept in f64
+//      - each pixel is c = C + dc, its orbit is z[n] = Z[n] + d[n], only the small difference d[n] is iterated, in f64
+//          d[n + 1] = 2 Z[n] d[n] + d[n]^2 + dc
+//        d and dc are tiny but a f64 goes down to 1e-308, there is no problem of precision on the differences
+//      - when z[n] gets much smaller than Z[n] the difference loses its precision (glitch). Pauldelbrot's criterion :
+//          |z[n]|^2 < GLITCH_TOLERANCE * |Z[n]|^2
+//        a pixel is also glitched if the reference escaped before it
+//      - the glitched pixels are computed again with a new reference taken among them, until there are no more glitches
+// the center of the view is a ComplexCoord (see coord.rs), it is converted to a BigComplex with just enough bits for the size of the pixels
+// the pixels are spread over the threads by bands of rows (mt_for_each_band()) and colored with smooth_from_escape() and palette() as usual
+// the zoom is a f64 so the limit is now around 1e300
+// the first iterations, the same for all the pixels of the first reference, are skipped with a series approximation (see series.rs)
+
+use crate::bignum::{BigComplex, BigFixed};
+use crate::coord::ComplexCoord;
+use crate::handle::RenderHandle;
+use crate::render::{mt_for_each_band, palette, smooth_from_escape};
+use crate::series::Series;
+use crate::view::{PixelMapper, Viewport};
+use num_complex::Complex;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Instant;
+
+// 1e-3 on |z| / |Z|
+pub const GLITCH_TOLERANCE: f64 = 1e-6;
+
+// the series is checked against PROBE_GRID x PROBE_GRID pixels spread over the image (the corners included)
+const PROBE_GRID: u32 = 5;
+
+// rows between 2 progress reports (see handle.rs)
+const PROGRESS_ROWS: u32 = 4;
+
+#[derive(Debug, Clone)]
+pub struct DeepView {
+    // the center keeps all its digits (see coord.rs)
+    pub view: Viewport,
+    pub iterations: u32,
+    // maximum number of references (the first one included)
+    pub max_references: u32,
+    // number of terms of the series approximation, 0 to iterate every pixel from the start
+    pub series_terms: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Sample {
+    // None is a point of the set
+    Done(Option<f64>),
+    Glitched,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct DeepStats {
+    pub references: u32,
+    // number of glitched pixels after each pass
+    pub glitched: Vec<usize>,
+    pub orbit_ms: u128,
+    pub render_ms: u128,
+    // iterations skipped by each pixel of the first reference
+    pub series_skipped: usize,
+    pub series_ms: u128,
+    // totals over all the pixels and all the references
+    pub iterations_computed: u64,
+    pub iterations_skipped: u64,
+}
+
+impl DeepView {
+    // ----------------------------------------------------------------------------
+    // the offsets of the pixels are relative to the center of the image, the center itself is never added in f64
+    fn mapper(&self, width: u32, height: u32) -> PixelMapper {
+        Viewport {
+            center: ComplexCoord::from_complex(Complex::new(0.0, 0.0)),
+            ..self.view.clone()
+        }
+        .mapper(width, height)
+    }
+
+    // ----------------------------------------------------------------------------
+    // enough bits to tell apart two pixels (see BigFixed::limbs_for_pixel_size())
+    pub fn frac_limbs(&self, height: u32) -> usize {
+        BigFixed::limbs_for_pixel_size(self.view.pixel_size(height))
+    }
+
+    // ----------------------------------------------------------------------------
+    // the offsets of the probe pixels of the series
+    fn probes(&self, mapper: &PixelMapper, width: u32, height: u32) -> Vec<Complex<f64>> {
+        let step = |size: u32, i: u32| (size - 1) as f64 * i as f64 / (PROBE_GRID - 1) as f64;
+        (0..PROBE_GRID)
+            .flat_map(|j| (0..PROBE_GRID).map(move |i| (i, j)))
+            .map(|(i, j)| mapper.offset_f64(step(width, i), step(height, j)))
+            .collect()
+    }
+
+    // ----------------------------------------------------------------------------
+    // the smooth iterations of the image (None for the set)
+    pub fn render(&self, width: u32, height: u32) -> (Vec<Option<f64>>, DeepStats) {
+        self.render_with(width, height, &RenderHandle::new())
+    }
+
+    // ----------------------------------------------------------------------------
+    // the same, watched and cancellable through handle (see handle.rs)
+    // each pass adds its pending pixels to the total of handle. When cancelled, the pixels not done are black
+    pub fn render_with(
+        &self,
+        width: u32,
+        height: u32,
+        handle: &RenderHandle,
+    ) -> (Vec<Option<f64>>, DeepStats) {
+        let mapper = self.mapper(width, height);
+        let frac_limbs = self.frac_limbs(height);
+        let mut stats = DeepStats::default();
+        // a center beyond 2^64 : every pixel escapes at the first iteration, they are all black (see smooth_from_escape())
+        let Ok(center) = self.view.center.to_big_complex(frac_limbs) else {
+            return (vec![None; (width * height) as usize], stats);
+        };
+        let mut samples = vec![Sample::Glitched; (width * height) as usize];
+
+        // first reference : the center of the image
+        let mut reference = center.clone();
+        let mut reference_offset = Complex::new(0.0, 0.0);
+        let computed = AtomicU64::new(0);
+
+        while stats.references < self.max_references.max(1) {
+            let start = Instant::now();
+            let orbit = reference_orbit(&reference, self.iterations);
+            stats.orbit_ms += start.elapsed().as_millis();
+            stats.references += 1;
+
+            // the series is only used around the first reference, the next ones only have a few scattered pixels
+            let start = Instant::now();
+            let series = if stats.references == 1 {
+                Series::compute(
+                    &orbit,
+                    &self.probes(&mapper, width, height),
+                    self.series_terms,
+                    self.iterations,
+                )
+            } else {
+                Series::none()
+            };
+            stats.series_ms += start.elapsed().as_millis();
+            if stats.references == 1 {
+                stats.series_skipped = series.skipped;
+            }
+            let pending = samples.iter().filter(|&&s| s == Sample::Glitched).count();
+            stats.iterations_skipped += (series.skipped * pending) as u64;
+            handle.add_total(pending as u64);
+
+            let start = Instant::now();
+            mt_for_each_band(
+                height,
+                PROGRESS_ROWS,
+                &mut samples,
+                |y_start, y_end, band| {
+                    let mut band_iterations = 0;
+                    for y in y_start..y_end {
+                        if handle.is_cancelled() {
+                            break;
+                        }
+                        let mut row_pixels = 0;
+                        for x in 0..width {
+                            let sample = &mut band[((y - y_start) * width + x) as usize];
+                            if *sample == Sample::Glitched {
+                                let dc = mapper.offset_f64(x as f64, y as f64) - reference_offset;
+                                let (result, n) = perturbed_from(
+                                    &orbit,
+                                    series.skipped,
+                                    series.evaluate(dc),
+                                    dc,
+                                    self.iterations,
+                                );
+                                *sample = result;
+                                band_iterations += (n - series.skipped) as u64;
+                                row_pixels += 1;
+                            }
+                        }
+                        handle.advance(row_pixels);
+                    }
+                    computed.fetch_add(band_iterations, Ordering::Relaxed);
+                },
+            );
+            stats.render_ms += start.elapsed().as_millis();
+            if handle.is_cancelled() {
+                break;
+            }
+
+            let glitched: Vec<usize> = (0..samples.len())
+                .filter(|&i| samples[i] == Sample::Glitched)
+                .collect();
+            stats.glitched.push(glitched.len());
+            if glitched.is_empty() {
+                break;
+            }
+
+            // the next reference is the glitched pixel in the middle of the list, most likely inside the largest glitched area
+            let i = glitched[glitched.len() / 2] as u32;
+            reference_offset = mapper.offset_f64((i % width) as f64, (i / width) as f64);
+            reference = center.add(&BigComplex {
+                re: BigFixed::from_f64(reference_offset.re, frac_limbs),
+                im: BigFixed::from_f64(reference_offset.im, frac_limbs),
+            });
+        }
+
+        stats.iterations_computed = computed.into_inner();
+
+        // the pixels still glitched after the last reference are drawn black
+        let smooth = samples
+            .iter()
+            .map(|s| match s {
+                Sample::Done(smooth_i) => *smooth_i,
+                Sample::Glitched => None,
+            })
+            .collect();
+        (smooth, stats)
+    }
+
+    // ----------------------------------------------------------------------------
+    // rgb image, image is width * height * 3 bytes
+    pub fn render_image(
+        &self,
+        width: u32,
+        height: u32,
+        palette_offset: f64,
+        image: &mut [u8],
+        handle: &RenderHandle,
+    ) -> DeepStats {
+        let (smooth, stats) = self.render_with(width, height, handle);
+        for (pixel, smooth_i) in image.chunks_exact_mut(3).zip(&smooth) {
+            let (r, g, b) = match smooth_i {
+                Some(smooth_i) => palette(*smooth_i, palette_offset),
+                None => (0, 0, 0),
+            };
+            pixel.copy_from_slice(&[r, g, b]);
+        }
+        stats
+    }
+}
+
+// ----------------------------------------------------------------------------
+// Z[0] = 0, Z[n + 1] = Z[n]^2 + C with an arbitrary precision, rounded to f64 when stored
+// stops when the reference escapes (the orbit is shorter than iterations + 1 then)
+pub fn reference_orbit(c: &BigComplex, iterations: u32) -> Vec<Complex<f64>> {
+    let mut orbit = Vec::with_capacity(iterations as usize + 1);
+    let mut z = BigComplex::zero(c.re.frac_limbs());
+    orbit.push(Complex::new(0.0, 0.0));
+
+    for _ in 0..iterations {
+        z = z.square_add(c);
+        let z64 = z.to_complex();
+        orbit.push(z64);
+        if z64.norm_sqr() > 4.0 {
+            break;
+        }
+    }
+    orbit
+}
+
+// ----------------------------------------------------------------------------
+// the same loop as smooth_iteration() but on the difference with the reference
+pub fn perturbed(orbit: &[Complex<f64>], dc: Complex<f64>, iterations: u32) -> Sample {
+    perturbed_from(orbit, 0, Complex::new(0.0, 0.0), dc, iterations).0
+}
+
+// ----------------------------------------------------------------------------
+// starts at the iteration start with the difference d (given by the series approximation)
+// returns the sample and the iteration where it stopped
+pub fn perturbed_from(
+    orbit: &[Complex<f64>],
+    start: usize,
+    mut d: Complex<f64>,
+    dc: Complex<f64>,
+    iterations: u32,
+) -> (Sample, usize) {
+    for t in start..iterations as usize {
+        let Some(&reference) = orbit.get(t + 1) else {
+            // the reference escaped before this pixel
+            return (Sample::Glitched, t);
+        };
+        d = 2.0 * orbit[t] * d + d * d + dc;
+        let z = reference + d;
+        let norm = z.norm_sqr();
+        if norm > 4.0 {
+            return (Sample::Done(smooth_from_escape(t as u32, z)), t + 1);
+        }
+        if norm < GLITCH_TOLERANCE * reference.norm_sqr() {
+            return (Sample::Glitched, t + 1);
+        }
+    }
+    (Sample::Done(None), (iterations as usize).max(start))
+}
@@ -0,0 +1,454 @@
+// interactive explorer in the terminal : mandel explore [view options]
+// the view fills the terminal with half blocks (see terminal.rs), the last line is a status line
+//      arrows      pan by 1/8 of the screen
+//      + -         zoom in / out (x2)
+//      i I         more / fewer iterations (x2)
+//      p P         shift the palette
+//      j           the Julia set of the point under the mouse (the center without a mouse), j again goes back to the Mandelbrot set
+//      r           back to the whole set (or the whole Julia set)
+//      s           saves the view at --size (1920x1080 by default) as explore_NNN.png with its parameters in explore_NNN.toml
+//      q Esc       quit
+// the rendering is progressive (see progressive.rs) and runs in a worker thread, the keyboard is never blocked
+// a key which changes the view cancels the rendering in progress (see handle.rs) and starts the new one
+// the passes of the worker are tagged with a generation number, the passes of a cancelled rendering are ignored
+
+use crate::coord::ComplexCoord;
+use crate::handle::RenderHandle;
+use crate::output::save_image;
+use crate::progressive::{ProgressiveJob, mt_progressive};
+use crate::real::Precision;
+use crate::render::Fractal;
+use crate::terminal::half_block_rows;
+use crate::view::Viewport;
+use crossterm::cursor::{Hide, MoveTo, Show};
+use crossterm::event::{
+    self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEvent, KeyEventKind,
+    KeyModifiers, MouseEventKind,
+};
+use crossterm::style::Print;
+use crossterm::terminal::{self, Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen};
+use crossterm::{execute, queue};
+use num_complex::Complex;
+use std::error::Error;
+use std::fs;
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::time::Duration;
+
+// time between 2 looks at the passes of the worker while no key is pressed
+const POLL: Duration = Duration::from_millis(30);
+
+const MAX_ITERATIONS: u32 = 1 << 20;
+const MIN_ITERATIONS: u32 = 16;
+
+#[derive(Debug, Clone)]
+pub struct ExploreOptions {
+    pub view: Viewport,
+    pub iterations: u32,
+    // size of the saved images
+    pub width: u32,
+    pub height: u32,
+    // where the saved images go
+    pub directory: PathBuf,
+}
+
+// what is on screen
+#[derive(Debug, Clone)]
+struct State {
+    view: Viewport,
+    fractal: Fractal,
+    iterations: u32,
+    palette_offset: f64,
+    // the Mandelbrot view to come back to from a Julia set
+    mandelbrot: Option<Viewport>,
+    // the cell under the mouse
+    mouse: Option<(u16, u16)>,
+    message: String,
+}
+
+// a rendering for the worker
+struct Job {
+    generation: u64,
+    view: Viewport,
+    fractal: Fractal,
+    iterations: u32,
+    palette_offset: f64,
+    width: u32,
+    height: u32,
+    handle: RenderHandle,
+}
+
+// a pass of the worker
+struct Frame {
+    generation: u64,
+    step: u32,
+    width: u32,
+    height: u32,
+    image: Vec<u8>,
+}
+
+enum Action {
+    Nothing,
+    Render,
+    Redraw,
+    Save,
+    Quit,
+}
+
+// ----------------------------------------------------------------------------
+// the terminal is restored even when something fails (or panics) in between
+pub fn run(options: &ExploreOptions) -> Result<(), Box<dyn Error>> {
+    struct Restore;
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            let _ = execute!(
+                io::stdout(),
+                DisableMouseCapture,
+                Show,
+                LeaveAlternateScreen
+            );
+            let _ = terminal::disable_raw_mode();
+        }
+    }
+
+    terminal::enable_raw_mode()?;
+    let _restore = Restore;
+    execute!(io::stdout(), EnterAlternateScreen, Hide, EnableMouseCapture)?;
+
+    let (jobs, worker_jobs) = mpsc::channel();
+    let (worker_frames, frames) = mpsc::channel();
+    std::thread::scope(|scope| {
+        scope.spawn(move || worker(worker_jobs, worker_frames));
+        // jobs is dropped at the end of explore(), the worker leaves its loop
+        explore(options, jobs, &frames)
+    })
+}
+
+// ----------------------------------------------------------------------------
+// only the last job is rendered when several are waiting
+fn worker(jobs: Receiver<Job>, frames: Sender<Frame>) {
+    while let Ok(mut job) = jobs.recv() {
+        while let Ok(next) = jobs.try_recv() {
+            job = next;
+        }
+        let progressive = ProgressiveJob {
+            view: &job.view,
+            fractal: job.fractal,
+            iterations: job.iterations,
+            palette_offset: job.palette_offset,
+            width: job.width,
+            height: job.height,
+        };
+        let mut image = vec![0u8; (job.width * job.height * 3) as usize];
+        mt_progressive(&progressive, &mut image, &job.handle, |step, image| {
+            let _ = frames.send(Frame {
+                generation: job.generation,
+                step,
+                width: job.width,
+                height: job.height,
+                image: image.to_vec(),
+            });
+        });
+    }
+}
+
+// ----------------------------------------------------------------------------
+fn explore(
+    options: &ExploreOptions,
+    jobs: Sender<Job>,
+    frames: &Receiver<Frame>,
+) -> Result<(), Box<dyn Error>> {
+    let mut state = State {
+        view: options.view.clone(),
+        fractal: Fractal::Mandelbrot,
+        iterations: options.iterations,
+        palette_offset: 0.0,
+        mandelbrot: None,
+        mouse: None,
+        message: String::new(),
+    };
+    let mut generation = 0;
+    let mut handle = RenderHandle::new();
+    let mut last: Option<Frame> = None;
+    start(&state, generation, &handle, &jobs)?;
+
+    loop {
+        while let Ok(frame) = frames.try_recv() {
+            if frame.generation == generation {
+                draw(&state, &frame)?;
+                last = Some(frame);
+            }
+        }
+        if !event::poll(POLL)? {
+            continue;
+        }
+
+        // all the events waiting (a key held down), one rendering for all of them
+        let mut render = false;
+        let mut redraw = false;
+        loop {
+            match handle_event(&mut state, event::read()?) {
+                Action::Nothing => {}
+                Action::Render => render = true,
+                Action::Redraw => redraw = true,
+                Action::Save => {
+                    state.message = "Saving...".to_string();
+                    if let Some(frame) = &last {
+                        draw(&state, frame)?;
+                    }
+                    state.message = match save(&state, options) {
+                        Ok(name) => format!("Saved {name}"),
+                        Err(e) => format!("Cannot save : {e}"),
+                    };
+                    redraw = true;
+                }
+                Action::Quit => {
+                    handle.cancel();
+                    return Ok(());
+                }
+            }
+            if !event::poll(Duration::ZERO)? {
+                break;
+            }
+        }
+
+        if render {
+            handle.cancel();
+            handle = RenderHandle::new();
+            generation += 1;
+            start(&state, generation, &handle, &jobs)?;
+        } else if redraw && let Some(frame) = &last {
+            draw(&state, frame)?;
+        }
+    }
+}
+
+// ----------------------------------------------------------------------------
+// the image fills the terminal but its last line
+fn start(
+    state: &State,
+    generation: u64,
+    handle: &RenderHandle,
+    jobs: &Sender<Job>,
+) -> Result<(), Box<dyn Error>> {
+    let (width, height) = screen_size()?;
+    jobs.send(Job {
+        generation,
+        view: state.view.clone(),
+        fractal: state.fractal,
+        iterations: state.iterations,
+        palette_offset: state.palette_offset,
+        width,
+        height,
+        handle: handle.clone(),
+    })
+    .map_err(|_| "the rendering thread is gone")?;
+    Ok(())
+}
+
+// ----------------------------------------------------------------------------
+// in pixels, 2 per cell
+fn screen_size() -> io::Result<(u32, u32)> {
+    let (columns, rows) = terminal::size()?;
+    Ok((
+        columns.max(1) as u32,
+        2 * rows.saturating_sub(1).max(1) as u32,
+    ))
+}
+
+// ----------------------------------------------------------------------------
+fn handle_event(state: &mut State, event: Event) -> Action {
+    match event {
+        Event::Key(key) if key.kind != KeyEventKind::Release => handle_key(state, key),
+        Event::Mouse(mouse)
+            if matches!(mouse.kind, MouseEventKind::Moved | MouseEventKind::Down(_)) =>
+        {
+            state.mouse = Some((mouse.column, mouse.row));
+            Action::Redraw
+        }
+        Event::Resize(_, _) => Action::Render,
+        _ => Action::Nothing,
+    }
+}
+
+// ----------------------------------------------------------------------------
+fn handle_key(state: &mut State, key: KeyEvent) -> Action {
+    let Ok((width, height)) = screen_size() else {
+        return Action::Nothing;
+    };
+    let mapper = state.view.mapper(width, height);
+    let (half_width, half_height) = (width as f64 / 2.0, height as f64 / 2.0);
+    let pan = |state: &mut State, dx: f64, dy: f64| {
+        state.view.center = state
+            .view
+            .center
+            .add_offset(mapper.offset_f64(half_width + dx, half_height + dy));
+    };
+    state.message.clear();
+
+    match key.code {
+        KeyCode::Char('q') | KeyCode::Esc => return Action::Quit,
+        KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => return Action::Quit,
+        KeyCode::Left => pan(state, -(width as f64) / 8.0, 0.0),
+        KeyCode::Right => pan(state, width as f64 / 8.0, 0.0),
+        KeyCode::Up => pan(state, 0.0, -(height as f64) / 8.0),
+        KeyCode::Down => pan(state, 0.0, height as f64 / 8.0),
+        KeyCode::Char('+') | KeyCode::Char('=') => state.view.zoom *= 2.0,
+        KeyCode::Char('-') => state.view.zoom /= 2.0,
+        KeyCode::Char('i') => state.iterations = (state.iterations * 2).min(MAX_ITERATIONS),
+        KeyCode::Char('I') => state.iterations = (state.iterations / 2).max(MIN_ITERATIONS),
+        KeyCode::Char('p') => state.palette_offset = (state.palette_offset + 0.05).rem_euclid(1.0),
+        KeyCode::Char('P') => state.palette_offset = (state.palette_offset - 0.05).rem_euclid(1.0),
+        KeyCode::Char('j') => match state.mandelbrot.take() {
+            Some(view) => {
+                state.view = view;
+                state.fractal = Fractal::Mandelbrot;
+            }
+            None => {
+                let (x, y) = state
+                    .mouse
+                    .map(|(column, row)| (column as f64 + 0.5, 2.0 * row as f64 + 1.0))
+                    .unwrap_or((half_width, half_height));
+                let c = state.view.mapper(width, height).map_f64(x, y);
+                state.mandelbrot = Some(state.view.clone());
+                state.fractal = Fractal::Julia(c);
+                state.view = julia_view();
+            }
+        },
+        KeyCode::Char('r') => {
+            state.view = match state.fractal {
+                Fractal::Mandelbrot => Viewport::default(),
+                Fractal::Julia(_) => julia_view(),
+            };
+        }
+        KeyCode::Char('s') => return Action::Save,
+        _ => return Action::Nothing,
+    }
+    Action::Render
+}
+
+// ----------------------------------------------------------------------------
+// the Julia sets are around 0
+fn julia_view() -> Viewport {
+    Viewport {
+        center: ComplexCoord::from_complex(Complex::new(0.0, 0.0)),
+        zoom: 0.8,
+        rotation: 0.0,
+    }
+}
+
+// ----------------------------------------------------------------------------
+// the image, the mouse as a + and the status line
+fn draw(state: &State, frame: &Frame) -> io::Result<()> {
+    let mut out = io::stdout().lock();
+    for (row, line) in half_block_rows(&frame.image, frame.width, frame.height)
+        .iter()
+        .enumerate()
+    {
+        queue!(out, MoveTo(0, row as u16), Print(line))?;
+    }
+    if let Some((column, row)) = state.mouse
+        && (column as u32) < frame.width
+        && (row as u32) < frame.height / 2
+    {
+        queue!(out, MoveTo(column, row), Print("\x1b[0m+"))?;
+    }
+
+    let fractal = match state.fractal {
+        Fractal::Mandelbrot => "mandelbrot".to_string(),
+        Fractal::Julia(c) => format!("julia {},{}", c.re, c.im),
+    };
+    let pass = if frame.step > 1 {
+        format!("1/{}", frame.step)
+    } else {
+        "done".to_string()
+    };
+    let mut status = format!(
+        " {fractal} | {} | zoom {:.3e} | {} iterations | {} | {pass} | ",
+        state.view.center,
+        state.view.zoom,
+        state.iterations,
+        Precision::for_view(&state.view, frame.height),
+    );
+    if state.message.is_empty() {
+        status += "arrows +- i I p P j r s q";
+    } else {
+        status += &state.message;
+    }
+    let status: String = status.chars().take(frame.width as usize).collect();
+    queue!(
+        out,
+        MoveTo(0, (frame.height / 2) as u16),
+        Clear(ClearType::CurrentLine),
+        Print(status)
+    )?;
+    out.flush()
+}
+
+// ----------------------------------------------------------------------------
+// the first explore_NNN.png which does not exist yet, rendered at full size, and its parameters
+// returns the name of the image
+fn save(state: &State, options: &ExploreOptions) -> Result<String, Box<dyn Error>> {
+    fs::create_dir_all(&options.directory)?;
+    let (image_path, parameters_path) = (1..)
+        .map(|n| {
+            (
+                options.directory.join(format!("explore_{n:03}.png")),
+                options.directory.join(format!("explore_{n:03}.toml")),
+            )
+        })
+        .find(|(image, parameters)| !image.exists() && !parameters.exists())
+        .unwrap();
+
+    let (width, height) = (options.width, options.height);
+    let job = ProgressiveJob {
+        view: &state.view,
+        fractal: state.fractal,
+        iterations: state.iterations,
+        palette_offset: state.palette_offset,
+        width,
+        height,
+    };
+    // the passes together compute each pixel once, the last one is the full image
+    let mut image = vec![0u8; (width * height * 3) as usize];
+    mt_progressive(&job, &mut image, &RenderHandle::new(), |_, _| {});
+    save_image(&image_path.to_string_lossy(), &image, width, height)?;
+    fs::write(&parameters_path, parameters(state, options, &image_path))?;
+    Ok(image_path.display().to_string())
+}
+
+// ----------------------------------------------------------------------------
+// toml, the center is written as strings to keep all its digits (see coord.rs)
+fn parameters(state: &State, options: &ExploreOptions, image: &std::path::Path) -> String {
+    let view = &state.view;
+    let mut text = String::from("# saved by mandel explore\n");
+    if state.fractal == Fractal::Mandelbrot {
+        text += &format!(
+            "# mandel render --center {} --zoom {:e} --rotation {} --iterations {} --size {}x{} --output {}\n",
+            view.center,
+            view.zoom,
+            view.rotation,
+            state.iterations,
+            options.width,
+            options.height,
+            image.display()
+        );
+    }
+    text += &format!(
+        "image = \"{}\"\ncenter = [\"{}\", \"{}\"]\nzoom = {:e}\nrotation = {:?}\niterations = {}\npalette_offset = {:?}\nwidth = {}\nheight = {}\n",
+        image.display(),
+        view.center.re,
+        view.center.im,
+        view.zoom,
+        view.rotation,
+        state.iterations,
+        state.palette_offset,
+        options.width,
+        options.height
+    );
+    if let Fractal::Julia(c) = state.fractal {
+        text += &format!("julia = [{:?}, {:?}]\n", c.re, c.im);
+    }
+    text
+}
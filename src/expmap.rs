@@ -0,0 +1,321 @@
+// exponential map (log-polar) rendering for the zoom videos
+// when the center does not move, all the frames of a zoom show the same points, only the scale changes
+// so, instead of rendering every frame, ONE tall strip is rendered :
+//      - x is the angle around the center (0..2 pi)
+//      - y is the logarithm of the distance to the center, the top row is the corner of the first frame, the bottom row is half a pixel of the last frame
+// the angular step and the radial step are the same (2 pi / strip width) so the pixels of the strip are "square" at every radius
+// then each frame is a reprojection of the strip : for each pixel, take its angle and the log of its distance to the center and read the strip there
+// the strip is rendered once with the multithreaded renderer, the reprojection is just a lookup per pixel
+// the strip is iterated with the cheapest type adequate for the pixels of the last frame, as render does (Precision::for_view(), see real.rs)
+// a zoom_end which needs the perturbation is refused, use mandel deep for these zooms
+//
+//      mandel expmap strip expmap.toml     => renders the strip and saves it
+//      mandel expmap frames expmap.toml    => reads the strip and writes the frames (png files, --y4m or --apng as for animate)
+//
+// expmap.toml looks like :
+//
+//      center = [-0.743643887037151, 0.131825904205330]
+//      zoom_start = 1.0
+//      zoom_end = 100000.0
+//      rotation = 0.0                  # optional, degrees
+//      frames = 300
+//      width = 640
+//      height = 480
+//      fps = 25                        # optional
+//      iterations = 250                # optional
+//      strip = "./assets/expmap/strip.png"     # relative to the directory of expmap.toml, always a png
+//      directory = "./assets/expmap"   # where the png frames go
+
+use crate::config::{get_coord, get_f64, get_str, get_u32};
+use crate::coord::ComplexCoord;
+use crate::ddouble::DoubleDouble;
+use crate::output::{load_image, save_image};
+use crate::real::{Precision, Real};
+use crate::render::{ITERATIONS, escape_time, mt_for_each_stripe, palette};
+use crate::video::{VideoOutput, open_sink, write_frames};
+use crate::view::Viewport;
+use num_complex::Complex;
+use std::error::Error;
+use std::f64::consts::TAU;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+#[derive(Debug, Clone)]
+pub struct ExpMap {
+    pub center: ComplexCoord,
+    pub zoom_start: f64,
+    pub zoom_end: f64,
+    pub rotation: f64,
+    pub frames: u32,
+    pub width: u32,
+    pub height: u32,
+    pub fps: u32,
+    pub iterations: u32,
+    pub strip: PathBuf,
+    pub directory: PathBuf,
+}
+
+// ----------------------------------------------------------------------------
+pub fn load_expmap(path: &Path) -> Result<ExpMap, Box<dyn Error>> {
+    let text = fs::read_to_string(path)?;
+    let base_dir = path.parent().unwrap_or(Path::new("."));
+    let table: toml::Table = text.parse()?;
+
+    let expmap = ExpMap {
+        center: get_coord(&table, "center")?.ok_or("missing center")?,
+        zoom_start: get_f64(&table, "zoom_start")?.unwrap_or(1.0),
+        zoom_end: get_f64(&table, "zoom_end")?.ok_or("missing zoom_end")?,
+        rotation: get_f64(&table, "rotation")?.unwrap_or(0.0),
+        frames: get_u32(&table, "frames")?.ok_or("missing frames")?,
+        width: get_u32(&table, "width")?.unwrap_or(640),
+        height: get_u32(&table, "height")?.unwrap_or(480),
+        fps: get_u32(&table, "fps")?.unwrap_or(25),
+        iterations: get_u32(&table, "iterations")?.unwrap_or(ITERATIONS),
+        strip: base_dir.join(get_str(&table, "strip")?.unwrap_or("strip.png")),
+        directory: base_dir.join(get_str(&table, "directory")?.unwrap_or(".")),
+    };
+
+    if [
+        expmap.frames,
+        expmap.width,
+        expmap.height,
+        expmap.fps,
+        expmap.iterations,
+    ]
+    .contains(&0)
+    {
+        return Err("frames, width, height, fps and iterations must be greater than 0".into());
+    }
+    if expmap.zoom_start <= 0.0 || expmap.zoom_end <= expmap.zoom_start {
+        return Err("0 < zoom_start < zoom_end is expected".into());
+    }
+    // the frames read the strip back with load_image(), which only reads png
+    if !expmap
+        .strip
+        .extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("png"))
+    {
+        return Err(format!("the strip {} must be a .png file", expmap.strip.display()).into());
+    }
+    Ok(expmap)
+}
+
+impl ExpMap {
+    // ----------------------------------------------------------------------------
+    // the frames zoom in at constant speed (exponentially)
+    pub fn frame_view(&self, n: u32) -> Viewport {
+        let t = if self.frames > 1 {
+            n as f64 / (self.frames - 1) as f64
+        } else {
+            0.0
+        };
+        Viewport {
+            center: self.center.clone(),
+            zoom: (self.zoom_start.ln() + t * (self.zoom_end.ln() - self.zoom_start.ln())).exp(),
+            rotation: self.rotation,
+        }
+    }
+
+    // ----------------------------------------------------------------------------
+    // distance in pixels between the center and a corner of a frame
+    fn half_diagonal(&self) -> f64 {
+        (self.width as f64).hypot(self.height as f64) / 2.0
+    }
+
+    // ----------------------------------------------------------------------------
+    // on the circle which goes through the corners of a frame there is one sample per pixel
+    pub fn strip_width(&self) -> u32 {
+        (TAU * self.half_diagonal()).ceil() as u32
+    }
+
+    // ----------------------------------------------------------------------------
+    // radial step, the same as the angular step
+    fn log_step(&self) -> f64 {
+        TAU / self.strip_width() as f64
+    }
+
+    // ----------------------------------------------------------------------------
+    // log of the radius of the top row (the corner of the first frame)
+    fn log_radius_top(&self) -> f64 {
+        let view = self.frame_view(0);
+        (self.half_diagonal() * view.pixel_size(self.height)).ln()
+    }
+
+    // ----------------------------------------------------------------------------
+    // from the corner of the first frame down to half a pixel of the last frame
+    pub fn strip_height(&self) -> u32 {
+        let view = self.frame_view(self.frames - 1);
+        let log_radius_bottom = (0.5 * view.pixel_size(self.height)).ln();
+        ((self.log_radius_top() - log_radius_bottom) / self.log_step()).ceil() as u32 + 1
+    }
+
+    // ----------------------------------------------------------------------------
+    // the pixels of the last frame are the smallest ones, the strip is iterated with the type they need
+    pub fn precision(&self) -> Precision {
+        Precision::for_view(&self.frame_view(self.frames - 1), self.height)
+    }
+
+    // ----------------------------------------------------------------------------
+    // the strip is rendered in place, with one stripe of rows per thread
+    // Precision::Perturbation is refused by run_strip(), it is done with DoubleDouble here
+    pub fn render_strip(&self, strip: &mut [u8]) {
+        let height = self.strip_height();
+        match self.precision() {
+            Precision::Single => mt_for_each_stripe(height, strip, |y_start, y_end, band| {
+                self.strip_rows::<f32>(y_start, y_end, band)
+            }),
+            Precision::Double => mt_for_each_stripe(height, strip, |y_start, y_end, band| {
+                self.strip_rows::<f64>(y_start, y_end, band)
+            }),
+            Precision::DoubleDouble | Precision::Perturbation => {
+                mt_for_each_stripe(height, strip, |y_start, y_end, band| {
+                    self.strip_rows::<DoubleDouble>(y_start, y_end, band)
+                })
+            }
+        }
+    }
+
+    // ----------------------------------------------------------------------------
+    // the rows y_start..y_end of the strip, band only contains these rows
+    // the points are the center in the type T plus their offset in f64, as in PixelMapper::map_f64() (see view.rs)
+    fn strip_rows<T: Real>(&self, y_start: u32, y_end: u32, band: &mut [u8]) {
+        let strip_width = self.strip_width();
+        let log_step = self.log_step();
+        let log_radius_top = self.log_radius_top();
+        let center = Complex::new(
+            T::from_coord(&self.center.re),
+            T::from_coord(&self.center.im),
+        );
+
+        for y in y_start..y_end {
+            let radius = (log_radius_top - y as f64 * log_step).exp();
+            for x in 0..strip_width {
+                let offset = Complex::from_polar(radius, x as f64 * log_step);
+                let c = Complex::new(
+                    center.re + T::from_f64(offset.re),
+                    center.im + T::from_f64(offset.im),
+                );
+                let (r, g, b) = match escape_time(&c, self.iterations) {
+                    Some(smooth_i) => palette(smooth_i, 0.0),
+                    None => (0, 0, 0),
+                };
+                let idx = ((y - y_start) * strip_width + x) as usize * 3;
+                band[idx] = r;
+                band[idx + 1] = g;
+                band[idx + 2] = b;
+            }
+        }
+    }
+
+    // ----------------------------------------------------------------------------
+    // the frame n, read from the strip (bilinear interpolation)
+    // the angle wraps around, the radius is clamped (only the few pixels at the very center of the last frames are concerned)
+    pub fn reproject(&self, strip: &[u8], n: u32, frame: &mut [u8]) {
+        let (width, height) = (self.width, self.height);
+        let strip_width = self.strip_width() as usize;
+        let last_row = (self.strip_height() - 1) as f64;
+        let log_step = self.log_step();
+        let log_radius_top = self.log_radius_top();
+        let mapper = self.frame_view(n).mapper(width, height);
+
+        mt_for_each_stripe(height, frame, |y_start, y_end, band| {
+            for y in y_start..y_end {
+                for x in 0..width {
+                    let offset = mapper.offset_f64(x as f64, y as f64);
+                    let fy =
+                        ((log_radius_top - offset.norm().ln()) / log_step).clamp(0.0, last_row);
+                    let fx = (offset.arg() / log_step).rem_euclid(strip_width as f64);
+
+                    let (x0, y0) = (fx.floor() as usize % strip_width, fy.floor() as usize);
+                    let (x1, y1) = ((x0 + 1) % strip_width, (y0 + 1).min(last_row as usize));
+                    let (tx, ty) = (fx - fx.floor(), fy - fy.floor());
+
+                    let idx = ((y - y_start) * width + x) as usize * 3;
+                    for channel in 0..3 {
+                        let sample = |sx: usize, sy: usize| {
+                            strip[(sy * strip_width + sx) * 3 + channel] as f64
+                        };
+                        let top = sample(x0, y0) * (1.0 - tx) + sample(x1, y0) * tx;
+                        let bottom = sample(x0, y1) * (1.0 - tx) + sample(x1, y1) * tx;
+                        band[idx + channel] = (top * (1.0 - ty) + bottom * ty).round() as u8;
+                    }
+                }
+            }
+        });
+    }
+}
+
+// ----------------------------------------------------------------------------
+pub fn run_strip(path: &Path) -> Result<(), Box<dyn Error>> {
+    let expmap = load_expmap(path)?;
+    let precision = expmap.precision();
+    if precision == Precision::Perturbation {
+        return Err(format!(
+            "zoom_end {} needs the perturbation, the strip cannot go beyond the double-double (see mandel deep)",
+            expmap.zoom_end
+        )
+        .into());
+    }
+    let (strip_width, strip_height) = (expmap.strip_width(), expmap.strip_height());
+    println!("Strip           : {strip_width} x {strip_height}");
+    println!("Precision       : {precision}");
+    println!("Iterations      : {}", expmap.iterations);
+
+    let mut strip = vec![0u8; (strip_width * strip_height * 3) as usize].into_boxed_slice();
+    let start = Instant::now();
+    expmap.render_strip(&mut strip);
+    println!("Render          : {} ms.", start.elapsed().as_millis());
+
+    // to compare with the number of pixels of a classic rendering
+    let frame_pixels = expmap.frames as f64 * (expmap.width * expmap.height) as f64;
+    println!(
+        "Pixels computed : {:.1} % of the frames",
+        100.0 * (strip_width * strip_height) as f64 / frame_pixels
+    );
+
+    if let Some(dir) = expmap.strip.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let filename = expmap
+        .strip
+        .to_str()
+        .ok_or("the strip path is not valid UTF-8")?;
+    save_image(filename, &strip, strip_width, strip_height)?;
+    println!("Saved           : {filename}");
+    Ok(())
+}
+
+// ----------------------------------------------------------------------------
+// video is None for the png files in the directory of expmap.toml
+pub fn run_frames(path: &Path, video: Option<&VideoOutput>) -> Result<(), Box<dyn Error>> {
+    let expmap = load_expmap(path)?;
+    let filename = expmap
+        .strip
+        .to_str()
+        .ok_or("the strip path is not valid UTF-8")?;
+    let (strip, strip_width, strip_height) = load_image(filename)?;
+    if (strip_width, strip_height) != (expmap.strip_width(), expmap.strip_height()) {
+        return Err(format!(
+            "{filename} does not match the settings of {}, render the strip again",
+            path.display()
+        )
+        .into());
+    }
+
+    let png = VideoOutput::Png(expmap.directory.clone());
+    let video = video.unwrap_or(&png);
+    let mut sink = open_sink(
+        video,
+        expmap.width,
+        expmap.height,
+        expmap.frames,
+        expmap.fps,
+    )?;
+    write_frames(
+        expmap.frames,
+        (expmap.width * expmap.height * 3) as usize,
+        |n, frame| expmap.reproject(&strip, n, frame),
+        sink.as_mut(),
+    )
+}
@@ -0,0 +1,537 @@
+// the image file formats, chosen by the extension of the output (writer_for())
+//      - png : the png crate, every kind of image (the default, and the format of the names without a known extension)
+//      - ppm / pgm / pnm : netpbm binary (P6 rgb, P5 grayscale), 8 or 16 bits, no alpha
+//      - bmp : 24 bits, or 32 bits with alpha (BITMAPV4HEADER and its masks), 8 bits per component
+//      - qoi : the "Quite OK Image" format (https://qoiformat.org), rgb or rgba, 8 bits per component
+//      - tif / tiff : baseline tiff, little endian, one uncompressed strip, rgb or grayscale, 8 or 16 bits, alpha as an extra sample, 72 dpi
+// ppm, pgm, bmp and qoi are written without any dependency and without compression (or almost, for qoi) : fast for the intermediate frames
+// the grayscale images are expanded to rgb for the formats without grayscale (ppm, bmp, qoi)
+// a format which cannot hold the image (alpha in a ppm, 16 bits in a bmp...) returns an error instead of losing something silently
+// a name ending with .tmp has the format of the name without it (the frames of anim.rs and the tiles of pyramid.rs are written as x.png.tmp first)
+
+use std::error::Error;
+use std::io::Write;
+use std::path::Path;
+
+// the samples of an image, row after row, channel after channel, what ColorImage::samples8() and samples16() give (see color.rs)
+// 16 bits samples are big endian
+#[derive(Debug, Clone, Copy)]
+pub struct Samples<'a> {
+    pub width: u32,
+    pub height: u32,
+    // 1 gray, 2 gray + alpha, 3 rgb, 4 rgba
+    pub channels: u8,
+    pub sixteen_bits: bool,
+    pub data: &'a [u8],
+}
+
+impl Samples<'_> {
+    // ----------------------------------------------------------------------------
+    fn gray(&self) -> bool {
+        self.channels <= 2
+    }
+
+    // ----------------------------------------------------------------------------
+    fn alpha(&self) -> bool {
+        self.channels.is_multiple_of(2)
+    }
+
+    // ----------------------------------------------------------------------------
+    // 8 bits rgb or rgba (the gray is copied in r, g and b), for the formats without grayscale
+    fn rgb8(&self) -> Vec<u8> {
+        match self.channels {
+            1 => self.data.iter().flat_map(|&v| [v, v, v]).collect(),
+            2 => self
+                .data
+                .chunks_exact(2)
+                .flat_map(|p| [p[0], p[0], p[0], p[1]])
+                .collect(),
+            _ => self.data.to_vec(),
+        }
+    }
+}
+
+pub trait ImageWriter {
+    // ----------------------------------------------------------------------------
+    fn write(&self, out: &mut dyn Write, image: &Samples) -> Result<(), Box<dyn Error>>;
+}
+
+// ----------------------------------------------------------------------------
+// png when the extension is unknown (or missing), as save_image() always did
+pub fn writer_for(path: &Path) -> Box<dyn ImageWriter> {
+    let extension = |path: &Path| {
+        path.extension()
+            .and_then(|e| e.to_str())
+            .map(str::to_ascii_lowercase)
+    };
+    let extension = match extension(path).as_deref() {
+        Some("tmp") => extension(&path.with_extension("")),
+        _ => extension(path),
+    };
+    match extension.as_deref() {
+        Some("ppm" | "pnm") => Box::new(Netpbm { gray_only: false }),
+        Some("pgm") => Box::new(Netpbm { gray_only: true }),
+        Some("bmp") => Box::new(Bmp),
+        Some("qoi") => Box::new(Qoi),
+        Some("tif" | "tiff") => Box::new(Tiff),
+        _ => Box::new(Png),
+    }
+}
+
+pub struct Png;
+
+impl ImageWriter for Png {
+    // ----------------------------------------------------------------------------
+    fn write(&self, out: &mut dyn Write, image: &Samples) -> Result<(), Box<dyn Error>> {
+        let mut encoder = png::Encoder::new(out, image.width, image.height);
+        encoder.set_color(match image.channels {
+            1 => png::ColorType::Grayscale,
+            2 => png::ColorType::GrayscaleAlpha,
+            3 => png::ColorType::Rgb,
+            _ => png::ColorType::Rgba,
+        });
+        encoder.set_depth(match image.sixteen_bits {
+            true => png::BitDepth::Sixteen,
+            false => png::BitDepth::Eight,
+        });
+        let mut writer = encoder.write_header()?;
+        writer.write_image_data(image.data)?;
+        Ok(())
+    }
+}
+
+// P6 (rgb) or P5 (grayscale), the maxval is 255 or 65535 (then 2 bytes per sample, big endian as ours)
+pub struct Netpbm {
+    // pgm : the rgb images are refused
+    pub gray_only: bool,
+}
+
+impl ImageWriter for Netpbm {
+    // ----------------------------------------------------------------------------
+    fn write(&self, out: &mut dyn Write, image: &Samples) -> Result<(), Box<dyn Error>> {
+        if image.alpha() {
+            return Err("ppm and pgm have no alpha, use png, bmp, qoi or tiff".into());
+        }
+        if self.gray_only && !image.gray() {
+            return Err("pgm is grayscale, use --color gray or bw (or ppm)".into());
+        }
+        let maxval = if image.sixteen_bits { 65535 } else { 255 };
+        let (width, height) = (image.width, image.height);
+        match (image.gray(), self.gray_only) {
+            (true, true) | (false, _) => {
+                let magic = if image.gray() { "P5" } else { "P6" };
+                write!(out, "{magic}\n{width} {height}\n{maxval}\n")?;
+                out.write_all(image.data)?;
+            }
+            // a grayscale image in a ppm : each sample 3 times
+            (true, false) => {
+                write!(out, "P6\n{width} {height}\n{maxval}\n")?;
+                let size = if image.sixteen_bits { 2 } else { 1 };
+                for sample in image.data.chunks_exact(size) {
+                    out.write_all(&sample.repeat(3))?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+// BITMAPFILEHEADER, then BITMAPINFOHEADER (24 bits) or BITMAPV4HEADER (32 bits with the masks of the alpha)
+// the rows go from the bottom to the top, bgr(a), each row padded to 4 bytes
+pub struct Bmp;
+
+impl ImageWriter for Bmp {
+    // ----------------------------------------------------------------------------
+    fn write(&self, out: &mut dyn Write, image: &Samples) -> Result<(), Box<dyn Error>> {
+        if image.sixteen_bits {
+            return Err("bmp has 8 bits per component, use png or tiff for 16 bits".into());
+        }
+        let alpha = image.alpha();
+        let (bytes_per_pixel, info_size): (u32, u32) = if alpha { (4, 108) } else { (3, 40) };
+        let row_size = (image.width * bytes_per_pixel).next_multiple_of(4);
+        let data_offset = 14 + info_size;
+        let data_size = row_size * image.height;
+
+        let mut header = Vec::with_capacity(data_offset as usize);
+        header.extend_from_slice(b"BM");
+        header.extend_from_slice(&(data_offset + data_size).to_le_bytes());
+        header.extend_from_slice(&0u32.to_le_bytes());
+        header.extend_from_slice(&data_offset.to_le_bytes());
+
+        header.extend_from_slice(&info_size.to_le_bytes());
+        header.extend_from_slice(&(image.width as i32).to_le_bytes());
+        header.extend_from_slice(&(image.height as i32).to_le_bytes());
+        header.extend_from_slice(&1u16.to_le_bytes());
+        header.extend_from_slice(&(bytes_per_pixel as u16 * 8).to_le_bytes());
+        // BI_RGB, or BI_BITFIELDS with the masks below
+        header.extend_from_slice(&(if alpha { 3u32 } else { 0 }).to_le_bytes());
+        header.extend_from_slice(&data_size.to_le_bytes());
+        // 2835 pixels per meter, 72 dpi
+        header.extend_from_slice(&2835u32.to_le_bytes());
+        header.extend_from_slice(&2835u32.to_le_bytes());
+        header.extend_from_slice(&0u32.to_le_bytes());
+        header.extend_from_slice(&0u32.to_le_bytes());
+        if alpha {
+            for mask in [0x00ff0000u32, 0x0000ff00, 0x000000ff, 0xff000000] {
+                header.extend_from_slice(&mask.to_le_bytes());
+            }
+            // LCS_sRGB, then the endpoints and the gammas which it ignores
+            header.extend_from_slice(b"BGRs");
+            header.extend_from_slice(&[0u8; 48]);
+        }
+        out.write_all(&header)?;
+
+        let rgb = image.rgb8();
+        let channels = if alpha { 4 } else { 3 };
+        let mut row = vec![0u8; row_size as usize];
+        for pixels in rgb.chunks_exact((image.width * channels) as usize).rev() {
+            for (bgr, p) in row
+                .chunks_exact_mut(bytes_per_pixel as usize)
+                .zip(pixels.chunks_exact(channels as usize))
+            {
+                bgr[0] = p[2];
+                bgr[1] = p[1];
+                bgr[2] = p[0];
+                if alpha {
+                    bgr[3] = p[3];
+                }
+            }
+            out.write_all(&row)?;
+        }
+        Ok(())
+    }
+}
+
+// the 6 operations of the specification, the end marker is 7 zeros and a 1
+pub struct Qoi;
+
+impl ImageWriter for Qoi {
+    // ----------------------------------------------------------------------------
+    fn write(&self, out: &mut dyn Write, image: &Samples) -> Result<(), Box<dyn Error>> {
+        if image.sixteen_bits {
+            return Err("qoi has 8 bits per component, use png or tiff for 16 bits".into());
+        }
+        let channels: u8 = if image.alpha() { 4 } else { 3 };
+        let mut bytes = b"qoif".to_vec();
+        bytes.extend_from_slice(&image.width.to_be_bytes());
+        bytes.extend_from_slice(&image.height.to_be_bytes());
+        // sRGB with a linear alpha
+        bytes.extend_from_slice(&[channels, 0]);
+
+        let rgb = image.rgb8();
+        let mut index = [[0u8; 4]; 64];
+        let mut previous = [0, 0, 0, 255];
+        let mut run = 0u8;
+        let pixels: Vec<[u8; 4]> = rgb
+            .chunks_exact(channels as usize)
+            .map(|p| [p[0], p[1], p[2], if channels == 4 { p[3] } else { 255 }])
+            .collect();
+        for (i, &pixel) in pixels.iter().enumerate() {
+            if pixel == previous {
+                run += 1;
+                if run == 62 || i + 1 == pixels.len() {
+                    bytes.push(0xc0 | (run - 1));
+                    run = 0;
+                }
+                continue;
+            }
+            if run > 0 {
+                bytes.push(0xc0 | (run - 1));
+                run = 0;
+            }
+
+            let [r, g, b, a] = pixel;
+            let hash = (r as usize * 3 + g as usize * 5 + b as usize * 7 + a as usize * 11) % 64;
+            if index[hash] == pixel {
+                bytes.push(hash as u8);
+            } else if a == previous[3] {
+                let dr = r.wrapping_sub(previous[0]) as i8;
+                let dg = g.wrapping_sub(previous[1]) as i8;
+                let db = b.wrapping_sub(previous[2]) as i8;
+                let (dr_dg, db_dg) = (dr.wrapping_sub(dg), db.wrapping_sub(dg));
+                if (-2..=1).contains(&dr) && (-2..=1).contains(&dg) && (-2..=1).contains(&db) {
+                    bytes.push(
+                        0x40 | ((dr + 2) as u8) << 4 | ((dg + 2) as u8) << 2 | (db + 2) as u8,
+                    );
+                } else if (-32..=31).contains(&dg)
+                    && (-8..=7).contains(&dr_dg)
+                    && (-8..=7).contains(&db_dg)
+                {
+                    bytes.push(0x80 | (dg + 32) as u8);
+                    bytes.push(((dr_dg + 8) as u8) << 4 | (db_dg + 8) as u8);
+                } else {
+                    bytes.extend_from_slice(&[0xfe, r, g, b]);
+                }
+            } else {
+                bytes.extend_from_slice(&[0xff, r, g, b, a]);
+            }
+            index[hash] = pixel;
+            previous = pixel;
+        }
+        bytes.extend_from_slice(&[0, 0, 0, 0, 0, 0, 0, 1]);
+        out.write_all(&bytes)?;
+        Ok(())
+    }
+}
+
+pub struct Tiff;
+
+impl ImageWriter for Tiff {
+    // ----------------------------------------------------------------------------
+    // the 16 bits samples become little endian like the rest of the file
+    fn write(&self, out: &mut dyn Write, image: &Samples) -> Result<(), Box<dyn Error>> {
+        let data = match image.sixteen_bits {
+            true => image
+                .data
+                .chunks_exact(2)
+                .flat_map(|s| [s[1], s[0]])
+                .collect(),
+            false => image.data.to_vec(),
+        };
+        let layout = TiffLayout {
+            width: image.width,
+            height: image.height,
+            channels: image.channels as u16,
+            bits: if image.sixteen_bits { 16 } else { 8 },
+            float: false,
+        };
+        out.write_all(&tiff(&layout, &data))?;
+        Ok(())
+    }
+}
+
+// what the tags of tiff() describe
+#[derive(Debug, Clone, Copy)]
+pub struct TiffLayout {
+    pub width: u32,
+    pub height: u32,
+    // 1 gray, 2 gray + alpha, 3 rgb, 4 rgba
+    pub channels: u16,
+    pub bits: u16,
+    // IEEE floats (the raw exports, see raw.rs) instead of unsigned integers
+    pub float: bool,
+}
+
+// ----------------------------------------------------------------------------
+// little endian, one IFD, one strip, no compression, data is the samples already in little endian
+// the values which do not fit in the 4 bytes of an entry (BitsPerSample of rgb, the resolutions) follow the IFD
+// the resolution is 72 dpi, as in the bmp files, baseline tiff requires one
+pub fn tiff(layout: &TiffLayout, data: &[u8]) -> Vec<u8> {
+    let (gray, alpha) = (layout.channels <= 2, layout.channels.is_multiple_of(2));
+    let entries = 13 + alpha as u32 + layout.float as u32;
+    let ifd_size = 2 + entries * 12 + 4;
+    let bits_offset = 8 + ifd_size;
+    let bits_size = if layout.channels > 2 {
+        layout.channels as u32 * 2
+    } else {
+        0
+    };
+    let resolution_offset = bits_offset + bits_size;
+    let data_offset = resolution_offset + 16;
+
+    // tag, type (3 short, 4 long, 5 rational), count, value (or offset)
+    let mut tags: Vec<(u16, u16, u32, u32)> = vec![
+        (256, 4, 1, layout.width),
+        (257, 4, 1, layout.height),
+        match layout.channels > 2 {
+            true => (258, 3, layout.channels as u32, bits_offset),
+            false => (
+                258,
+                3,
+                layout.channels as u32,
+                (layout.bits as u32) * (1 + 0x10000 * alpha as u32),
+            ),
+        },
+        (259, 3, 1, 1),
+        // BlackIsZero or RGB
+        (262, 3, 1, if gray { 1 } else { 2 }),
+        (273, 4, 1, data_offset),
+        (277, 3, 1, layout.channels as u32),
+        (278, 4, 1, layout.height),
+        (279, 4, 1, data.len() as u32),
+        // XResolution and YResolution, 72 / 1 each
+        (282, 5, 1, resolution_offset),
+        (283, 5, 1, resolution_offset + 8),
+        // chunky
+        (284, 3, 1, 1),
+        // inch
+        (296, 3, 1, 2),
+    ];
+    if alpha {
+        // unassociated alpha
+        tags.push((338, 3, 1, 2));
+    }
+    if layout.float {
+        // IEEE float, the default is unsigned integer (the count would be the number of samples per pixel)
+        tags.push((339, 3, 1, 3));
+    }
+
+    let mut out = b"II*\0".to_vec();
+    out.extend_from_slice(&8u32.to_le_bytes());
+    out.extend_from_slice(&(tags.len() as u16).to_le_bytes());
+    for (tag, kind, count, value) in tags {
+        out.extend_from_slice(&tag.to_le_bytes());
+        out.extend_from_slice(&kind.to_le_bytes());
+        out.extend_from_slice(&count.to_le_bytes());
+        out.extend_from_slice(&value.to_le_bytes());
+    }
+    out.extend_from_slice(&0u32.to_le_bytes());
+    if layout.channels > 2 {
+        for _ in 0..layout.channels {
+            out.extend_from_slice(&layout.bits.to_le_bytes());
+        }
+    }
+    for value in [72u32, 1, 72, 1] {
+        out.extend_from_slice(&value.to_le_bytes());
+    }
+    out.extend_from_slice(data);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // ----------------------------------------------------------------------------
+    fn encode(writer: &dyn ImageWriter, image: &Samples) -> Vec<u8> {
+        let mut out = Vec::new();
+        writer.write(&mut out, image).unwrap();
+        out
+    }
+
+    // ----------------------------------------------------------------------------
+    fn rgb(width: u32, height: u32, data: &[u8]) -> Samples<'_> {
+        Samples {
+            width,
+            height,
+            channels: 3,
+            sixteen_bits: false,
+            data,
+        }
+    }
+
+    // ----------------------------------------------------------------------------
+    fn u16_at(bytes: &[u8], offset: usize) -> u16 {
+        u16::from_le_bytes(bytes[offset..offset + 2].try_into().unwrap())
+    }
+
+    // ----------------------------------------------------------------------------
+    fn u32_at(bytes: &[u8], offset: usize) -> u32 {
+        u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap())
+    }
+
+    // ----------------------------------------------------------------------------
+    // the type, the count and the value (or offset) of a tag of the first IFD
+    fn tiff_entry(bytes: &[u8], tag: u16) -> (u16, u32, u32) {
+        let ifd = u32_at(bytes, 4) as usize;
+        let entry = (0..u16_at(bytes, ifd) as usize)
+            .map(|i| ifd + 2 + 12 * i)
+            .find(|&entry| u16_at(bytes, entry) == tag)
+            .unwrap_or_else(|| panic!("no tag {tag}"));
+        (
+            u16_at(bytes, entry + 2),
+            u32_at(bytes, entry + 4),
+            u32_at(bytes, entry + 8),
+        )
+    }
+
+    // ----------------------------------------------------------------------------
+    #[test]
+    fn qoi_runs_stop_at_62() {
+        let bytes = encode(&Qoi, &rgb(64, 1, &[0; 64 * 3]));
+        // 62 then 2 times the start pixel (0, 0, 0, 255)
+        assert_eq!(bytes[14..], [0xc0 | 61, 0xc0 | 1, 0, 0, 0, 0, 0, 0, 0, 1]);
+    }
+
+    // ----------------------------------------------------------------------------
+    #[test]
+    fn qoi_differences_wrap_around() {
+        let bytes = encode(&Qoi, &rgb(3, 1, &[255, 255, 255, 0, 0, 0, 250, 250, 250]));
+        // 0 - 1 = 255, then 255 + 1 = 0 (QOI_OP_DIFF), then 0 - 6 = 250 (QOI_OP_LUMA)
+        assert_eq!(bytes[14..18], [0x40 | 0x15, 0x40 | 0x3f, 0x80 | 26, 0x88]);
+    }
+
+    // ----------------------------------------------------------------------------
+    #[test]
+    fn bmp_rows_are_padded_to_4_bytes() {
+        let data: Vec<u8> = (1..=18).collect();
+        let bytes = encode(&Bmp, &rgb(3, 2, &data));
+        assert_eq!(bytes.len(), 54 + 2 * 12);
+        assert_eq!(u32_at(&bytes, 10), 54);
+        // the bottom row first, bgr, then 3 bytes of padding
+        assert_eq!(bytes[54..66], [12, 11, 10, 15, 14, 13, 18, 17, 16, 0, 0, 0]);
+        assert_eq!(bytes[66..78], [3, 2, 1, 6, 5, 4, 9, 8, 7, 0, 0, 0]);
+    }
+
+    // ----------------------------------------------------------------------------
+    #[test]
+    fn bmp_with_alpha_has_the_v4_header() {
+        let data = [10, 20, 30, 40, 50, 60, 70, 80, 90, 100, 110, 120];
+        let image = Samples {
+            width: 3,
+            height: 1,
+            channels: 4,
+            sixteen_bits: false,
+            data: &data,
+        };
+        let bytes = encode(&Bmp, &image);
+        assert_eq!(bytes.len(), 14 + 108 + 12);
+        assert_eq!(u32_at(&bytes, 14), 108);
+        assert_eq!(u16_at(&bytes, 28), 32);
+        // BI_BITFIELDS, then the red, green, blue and alpha masks
+        assert_eq!(u32_at(&bytes, 30), 3);
+        assert_eq!(u32_at(&bytes, 54), 0x00ff0000);
+        assert_eq!(u32_at(&bytes, 66), 0xff000000);
+        assert_eq!(&bytes[70..74], b"BGRs");
+        assert_eq!(
+            bytes[122..],
+            [30, 20, 10, 40, 70, 60, 50, 80, 110, 100, 90, 120]
+        );
+    }
+
+    // ----------------------------------------------------------------------------
+    #[test]
+    fn tiff_gray_and_alpha_packs_bits_per_sample() {
+        let data = [0x12, 0x34, 0x56, 0x78];
+        let image = Samples {
+            width: 1,
+            height: 1,
+            channels: 2,
+            sixteen_bits: true,
+            data: &data,
+        };
+        let bytes = encode(&Tiff, &image);
+        // 2 shorts of 16 in the value itself
+        assert_eq!(tiff_entry(&bytes, 258), (3, 2, 16 | 16 << 16));
+        assert_eq!(tiff_entry(&bytes, 277), (3, 1, 2));
+        assert_eq!(tiff_entry(&bytes, 338), (3, 1, 2));
+        let (_, _, offset) = tiff_entry(&bytes, 273);
+        // little endian samples
+        assert_eq!(bytes[offset as usize..], [0x34, 0x12, 0x78, 0x56]);
+    }
+
+    // ----------------------------------------------------------------------------
+    #[test]
+    fn tiff_has_the_baseline_tags() {
+        let bytes = encode(&Tiff, &rgb(2, 1, &[1, 2, 3, 4, 5, 6]));
+        let ifd = u32_at(&bytes, 4) as usize;
+        let tags: Vec<u16> = (0..u16_at(&bytes, ifd) as usize)
+            .map(|i| u16_at(&bytes, ifd + 2 + 12 * i))
+            .collect();
+        assert!(tags.is_sorted(), "{tags:?}");
+        let (_, count, offset) = tiff_entry(&bytes, 258);
+        assert_eq!(count, 3);
+        for i in 0..3 {
+            assert_eq!(u16_at(&bytes, offset as usize + 2 * i), 8);
+        }
+        for tag in [282, 283] {
+            let (kind, count, offset) = tiff_entry(&bytes, tag);
+            assert_eq!((kind, count), (5, 1));
+            assert_eq!(u32_at(&bytes, offset as usize), 72);
+            assert_eq!(u32_at(&bytes, offset as usize + 4), 1);
+        }
+        assert_eq!(tiff_entry(&bytes, 296), (3, 1, 2));
+        let (_, _, offset) = tiff_entry(&bytes, 273);
+        assert_eq!(bytes[offset as usize..], [1, 2, 3, 4, 5, 6]);
+    }
+}
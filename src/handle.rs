@@ -0,0 +1,136 @@
+// progress and cancellation of a long rendering (a deep zoom can take hours)
+// the workers add the pixels they finish (advance()) and check the cancel flag between their rows (is_cancelled())
+// the caller watches progress() and eta() from another thread, and can cancel() : the workers stop at their next row
+// a cancelled rendering returns normally with the pixels done so far, the caller checks is_cancelled()
+// RenderHandle is a shared pointer, the clones see the same counters (for a Ctrl-C handler for example)
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+#[derive(Debug)]
+struct Counters {
+    total: AtomicU64,
+    done: AtomicU64,
+    cancelled: AtomicBool,
+    // set_total() was called
+    fixed: AtomicBool,
+    start: Instant,
+}
+
+#[derive(Debug, Clone)]
+pub struct RenderHandle {
+    counters: Arc<Counters>,
+}
+
+impl Default for RenderHandle {
+    // ----------------------------------------------------------------------------
+    fn default() -> Self {
+        RenderHandle::new()
+    }
+}
+
+impl RenderHandle {
+    // ----------------------------------------------------------------------------
+    // the clock of the ETA starts here
+    pub fn new() -> Self {
+        RenderHandle {
+            counters: Arc::new(Counters {
+                total: AtomicU64::new(0),
+                done: AtomicU64::new(0),
+                cancelled: AtomicBool::new(false),
+                fixed: AtomicBool::new(false),
+                start: Instant::now(),
+            }),
+        }
+    }
+
+    // ----------------------------------------------------------------------------
+    // the work to do can grow while rendering (the glitched pixels of deep.rs)
+    pub fn add_total(&self, units: u64) {
+        if !self.counters.fixed.load(Ordering::Relaxed) {
+            self.counters.total.fetch_add(units, Ordering::Relaxed);
+        }
+    }
+
+    // ----------------------------------------------------------------------------
+    // the caller knows the whole work in advance (the tiles of a pyramid.rs) : the add_total() of the renderings are ignored
+    pub fn set_total(&self, units: u64) {
+        self.counters.total.store(units, Ordering::Relaxed);
+        self.counters.fixed.store(true, Ordering::Relaxed);
+    }
+
+    // ----------------------------------------------------------------------------
+    pub fn advance(&self, units: u64) {
+        self.counters.done.fetch_add(units, Ordering::Relaxed);
+    }
+
+    // ----------------------------------------------------------------------------
+    // 0.0 .. 1.0
+    pub fn progress(&self) -> f64 {
+        let total = self.counters.total.load(Ordering::Relaxed);
+        let done = self.counters.done.load(Ordering::Relaxed);
+        if total == 0 {
+            0.0
+        } else {
+            (done as f64 / total as f64).min(1.0)
+        }
+    }
+
+    // ----------------------------------------------------------------------------
+    pub fn elapsed(&self) -> Duration {
+        self.counters.start.elapsed()
+    }
+
+    // ----------------------------------------------------------------------------
+    // linear extrapolation of the time spent so far, None before the first pixels
+    pub fn eta(&self) -> Option<Duration> {
+        let progress = self.progress();
+        if progress <= 0.0 {
+            return None;
+        }
+        let elapsed = self.elapsed().as_secs_f64();
+        Some(Duration::from_secs_f64(
+            elapsed * (1.0 - progress) / progress,
+        ))
+    }
+
+    // ----------------------------------------------------------------------------
+    pub fn cancel(&self) {
+        self.counters.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    // ----------------------------------------------------------------------------
+    pub fn is_cancelled(&self) -> bool {
+        self.counters.cancelled.load(Ordering::Relaxed)
+    }
+
+    // ----------------------------------------------------------------------------
+    // [##########..........]  50.0 %  elapsed 00:00:12  ETA 00:00:12
+    pub fn bar(&self, width: usize) -> String {
+        let progress = self.progress();
+        let filled = (progress * width as f64).round() as usize;
+        let eta = self
+            .eta()
+            .map(hms)
+            .unwrap_or_else(|| "--:--:--".to_string());
+        format!(
+            "[{}{}] {:5.1} %  elapsed {}  ETA {eta}",
+            "#".repeat(filled),
+            ".".repeat(width - filled),
+            100.0 * progress,
+            hms(self.elapsed())
+        )
+    }
+}
+
+// ----------------------------------------------------------------------------
+fn hms(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    format!(
+        "{:02}:{:02}:{:02}",
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    )
+}
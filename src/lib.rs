@@ -0,0 +1,35 @@
+// the library part of the project (since main_12)
+// main.rs is only a small command line front end on top of these modules
+// main_00.rs ... main_11.rs are still stand alone versions, they do not use the library
+
+pub mod anim;
+pub mod average;
+pub mod batch;
+pub mod bench;
+pub mod bignum;
+pub mod checkpoint;
+pub mod color;
+pub mod config;
+pub mod coord;
+pub mod cycle;
+pub mod ddouble;
+pub mod deep;
+pub mod explore;
+pub mod expmap;
+pub mod formats;
+pub mod handle;
+pub mod mariani;
+pub mod output;
+pub mod pool;
+pub mod progressive;
+pub mod pyramid;
+pub mod raw;
+pub mod real;
+pub mod render;
+pub mod series;
+pub mod serve;
+pub mod simd;
+pub mod terminal;
+pub mod trap;
+pub mod video;
+pub mod view;
@@ -1,29 +1,117 @@
-// main_10
-// the code has been refactored
-// the processing is done by one function render_zone() (code factorization)
-// render_zone() is called in both single-threaded and multithreaded portion of the code
-// the image is pre-allocated and the processing is done in place
-// no need to join the stripes at the end of the multitrheaded version
-// this require to use scope. I don't "use crossbeam::thread;" but call "crossbeam::thread::scope" instead
+// main_12
+// the code has been split. main_10 is saved in main_10.rs
+// render_zone(), mt_build_mandelbrot() and mandelbrot_color() are in render.rs, save_image() is in output.rs
+// they are part of a library (see lib.rs) so that several commands can share them
+// main() reads the first argument and runs the corresponding command
+//
+//      mandel                          => same as main_10, compare single-threaded vs multithreaded (and vs SIMD, see simd.rs)
+//      mandel batch jobs.toml [--force] => render all the images of a job list with one pool of threads (see batch.rs)
+//      mandel animate zoom.toml [--force] => render the frames of a zoom animation described by keyframes (see anim.rs)
+//      mandel animate zoom.toml --y4m file.y4m (or - for stdout) | --apng file.png => the same frames in one video file (see video.rs)
+//      mandel expmap strip expmap.toml => render the exponential map of a zoom video (see expmap.rs)
+//      mandel expmap frames expmap.toml [--y4m ... | --apng ...] => the frames of the zoom video, reprojected from the exponential map
+//      mandel cycle [view options] --frames 64 (--gif file.gif | --apng ... | --png directory | --y4m ...) => palette cycling loop (see cycle.rs)
+//      mandel render [view options] [--precision auto] [--iterations 250] [--progressive | --mariani [--check] | --resume] [--trap point:re,im | line:re,im,angle | cross:re,im | circle:re,im,radius | image:texture.png,re,im,size [--trap-color distance | angle] [--trap-scale 10]] [--average stripe | tia [--stripes 5] [--average-scale 10]] [--color palette | gray | bw] [--alpha] [--depth 8 | 16] (--output image.png | --terminal [blocks | sixel]) => one image, iterated with f32, f64, double-double or perturbation (see real.rs)
+//      mandel deep --center re,im --zoom 1e20 [--iterations 5000] [--size 640x480] --output deep.png => perturbation rendering (see deep.rs)
+//      mandel pyramid [view options] [--levels 5] [--iterations 250] --output directory => 256 x 256 tiles z/x/y.png and pyramid.dzi for the web viewers (see pyramid.rs)
+//      mandel serve [--bind 127.0.0.1] [--port 8080] [--cache tile_cache] [--memory 1024] [--disk 256] [--iterations 1000] [--threads 4] => tile server and viewer for a browser (see serve.rs)
+//      mandel explore [view options] [--iterations 250] [--directory .] => interactive explorer in the terminal, --size is the size of the saved images (see explore.rs)
+//      mandel export [view options] [--precision auto] [--iterations 250] [--format npy | pfm | tiff] --output stem => iterations, smooth iterations, distance estimates and final z as arrays (see raw.rs)
+//      mandel recolor --input stem_smooth.npy [--offset 0.0] --output image.png => the colors of an exported smooth or iterations field (npy, pfm or tiff)
+//      mandel bench [--strategies single,stripes,...] [--sizes 320x240,640x480] [--threads 1,2,4] [--views whole,seahorse,spiral] [--warmup 1] [--runs 5] [--csv file] [--json file] => timings of the strategies (see bench.rs)
+//
+// render, deep and pyramid show a progress bar, Ctrl-C stops them cleanly (see handle.rs)
+// the view options are --center re,im --zoom 1.0 --rotation 0.0 --size 640x480
+// the center is read as a decimal ComplexCoord, all its digits are kept (see coord.rs)
+// the images are png, ppm, pgm, bmp, qoi or tiff files, chosen by the extension of the output (see formats.rs)
 
 // TODO : issues with scaling
 // TODO : add a zoom and be able to move the centre of the view_rectangle in complex space
 
-extern crate num_complex;
-extern crate png;
-
+use mandel::anim::AnimOutput;
+use mandel::average::{AverageJob, OrbitAverage, mt_average_map};
+use mandel::bench::BenchOptions;
+use mandel::checkpoint::{Checkpoint, mt_resumable_smooth_map};
+use mandel::color::{ColorImage, ColorMode};
+use mandel::coord::ComplexCoord;
+use mandel::cycle::{Cycle, CycleOutput, color_frame};
+use mandel::ddouble::DoubleDouble;
+use mandel::deep::DeepView;
+use mandel::explore::ExploreOptions;
+use mandel::handle::RenderHandle;
+use mandel::mariani::{Escape, check, mt_mariani_silver};
+use mandel::output::{save_color_image, save_image};
+use mandel::progressive::mt_progressive_view;
+use mandel::pyramid::Pyramid;
+use mandel::raw::{RawFormat, RawJob, load_smooth, mt_escape_records};
+use mandel::real::Precision;
+use mandel::render::{
+    EscapeRecord, Fractal, ITERATIONS, SmoothJob, escaped_pixels, mt_build_mandelbrot,
+    mt_smooth_map_with, render_zone,
+};
+use mandel::serve::ServeOptions;
+use mandel::simd::{Kernel, render_zone_simd};
+use mandel::terminal::TerminalFormat;
+use mandel::trap::{OrbitTrap, TrapJob, mt_trap_colors};
+use mandel::video::VideoOutput;
+use mandel::view::Viewport;
 use num_complex::Complex;
-use std::fs::File;
-use std::io::BufWriter;
-use std::time::Instant;
+use std::io::IsTerminal;
+use std::net::Ipv4Addr;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::time::{Duration, Instant};
+
+const USAGE: &str = "Usage :
+    mandel
+    mandel batch jobs.toml [--force]
+    mandel animate zoom.toml [--force | --y4m file.y4m | --y4m - | --apng file.png]
+    mandel expmap strip expmap.toml
+    mandel expmap frames expmap.toml [--y4m file.y4m | --y4m - | --apng file.png]
+    mandel cycle [view options] [--frames 64] [--fps 25] (--gif file.gif | --apng file.png | --png directory | --y4m file.y4m)
+    mandel render [view options] [--precision auto | f32 | f64 | dd | deep] [--iterations 250] [--progressive | --mariani [--check] | --resume] [--trap point:re,im | line:re,im,angle | cross:re,im | circle:re,im,radius | image:texture.png,re,im,size [--trap-color distance | angle] [--trap-scale 10]] [--average stripe | tia [--stripes 5] [--average-scale 10]] [--color palette | gray | bw] [--alpha] [--depth 8 | 16] (--output image.png | --terminal [blocks | sixel])
+    mandel deep [view options] [--iterations 5000] [--references 32] [--series 8] --output deep.png
+    mandel pyramid [view options] [--levels 5] [--iterations 250] --output directory
+    mandel serve [--bind 127.0.0.1] [--port 8080] [--cache tile_cache] [--memory 1024] [--disk 256] [--iterations 1000] [--threads 4]
+    mandel explore [view options] [--iterations 250] [--directory .]
+    mandel export [view options] [--precision auto | f32 | f64 | dd | deep] [--iterations 250] [--format npy | pfm | tiff] --output stem
+    mandel recolor --input stem_smooth.npy [--offset 0.0] --output image.png
+    mandel bench [--strategies single,simd,stripes,tiles,pool,mariani,progressive] [--sizes 320x240,640x480] [--threads 1,2,4] [--views whole,seahorse,spiral] [--warmup 1] [--runs 5] [--csv bench.csv] [--json bench.json]
 
-// std::thread is replaced by crossbeam::thread BUT I want to make sure where crossbeam::thread is used in the code
-// use std::thread;
-// use crossbeam::thread; // => uncomment crossbeam = "0.8.4" in cargo.toml
+view options : --center re,im --zoom 1.0 --rotation 0.0 --size 640x480";
+
+// ----------------------------------------------------------------------------
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+
+    match args.first().map(String::as_str) {
+        None => {
+            compare();
+            ExitCode::SUCCESS
+        }
+        Some("batch") => batch(&args[1..]),
+        Some("animate") => animate(&args[1..]),
+        Some("expmap") => expmap(&args[1..]),
+        Some("cycle") => cycle(&args[1..]),
+        Some("render") => render(&args[1..]),
+        Some("deep") => deep(&args[1..]),
+        Some("pyramid") => pyramid(&args[1..]),
+        Some("serve") => serve(&args[1..]),
+        Some("explore") => explore(&args[1..]),
+        Some("export") => export(&args[1..]),
+        Some("recolor") => recolor(&args[1..]),
+        Some("bench") => bench(&args[1..]),
+        Some(other) => {
+            eprintln!("Unknown command : {other}");
+            eprintln!("{USAGE}");
+            ExitCode::FAILURE
+        }
+    }
+}
 
 // ----------------------------------------------------------------------------
 // image is Box<[u8]> and not Vec<u8> because the image size is invariant
-fn main() {
+fn compare() {
     let (width, height) = (640, 480);
 
     let from = Complex::new(-2.5, -1.315);
@@ -43,137 +131,1122 @@ fn main() {
     println!("Multithreaded   : {} ms.", duration.as_millis());
     save_image("./assets/image_rgb_mt_10.png", &image, width, height)
         .expect("Failed to save image");
+
+    // the same image again, single-threaded, 4 pixels at a time (see simd.rs)
+    let mut reference = vec![0u8; image.len()].into_boxed_slice();
+    render_zone(&from, &to, width, height, &mut reference);
+    let kernel = Kernel::detect();
+    let start = Instant::now();
+    render_zone_simd(kernel, &from, &to, width, height, ITERATIONS, &mut image);
+    let duration = start.elapsed();
+    println!("SIMD ({kernel})     : {} ms.", duration.as_millis());
+    let different = image
+        .chunks_exact(3)
+        .zip(reference.chunks_exact(3))
+        .filter(|(a, b)| a != b)
+        .count();
+    println!("SIMD differences: {different} pixel(s)");
 }
 
 // ----------------------------------------------------------------------------
-// does not return an image
-// no longer joins the stripes to rebuild the image
-// try to be smarter if the number of cores does not divide the height of the image evenly
-// .split_at_mut() is used to help the compiler "understand" (trust in me, just in me...) that each thread work on non-overlapping parts of the image
-fn mt_build_mandelbrot(
-    from: &Complex<f64>,
-    to: &Complex<f64>,
-    width: u32,
-    height: u32,
-    image: &mut [u8],
-) {
-    let nthreads = std::thread::available_parallelism()
-        .map(|n| n.get())
-        .unwrap_or(1);
-    println!("# of threads    : {nthreads}");
-
-    let stripe_width = width;
-    // let stripe_height = height / nthreads;
-    // the value height / nthreads, nthreads times
-    let mut stripe_heights = vec![height / nthreads as u32; nthreads];
-    // handles cases where height is not divisible by nthreads (480 and 7 for example)
-    // 480 % 7 = 4 - the first 4 stripes receive 1 additional line each
-    for i in 0..(height % nthreads as u32) {
-        stripe_heights[i as usize] += 1;
-    }
-
-    let size = to - from;
-    let delta_y = size.im / height as f64;
-
-    let mut y_start = 0;
-    // the scope guarantees that all threads are joined before the end of the block.
-    // so the compiler "knows" that threads won't escape and that local references will live long enough.
-    crossbeam::thread::scope(|my_scope| {
-        let mut remaining = image;
-
-        for stripe_height in stripe_heights {
-            let stripe_byte_count = (stripe_height * stripe_width * 3) as usize;
-
-            // .split_at_mut() returns 2 disjoint mutables slices
-            let (stripe, rest) = remaining.split_at_mut(stripe_byte_count);
-            remaining = rest;
-
-            let y_end = y_start + stripe_height;
-            let stripe_from = from + Complex::new(0.0, y_start as f64 * delta_y);
-            let stripe_to = from + Complex::new(size.re, y_end as f64 * delta_y);
-
-            my_scope.spawn(move |_| {
-                render_zone(
-                    &stripe_from,
-                    &stripe_to,
-                    stripe_width,
-                    stripe_height as u32,
-                    stripe,
-                );
-            });
-
-            y_start = y_end;
+fn batch(args: &[String]) -> ExitCode {
+    let force = args.iter().any(|a| a == "--force");
+    let Some(path) = args.iter().find(|a| !a.starts_with("--")) else {
+        eprintln!("Usage : mandel batch jobs.toml [--force]");
+        return ExitCode::FAILURE;
+    };
+
+    match mandel::batch::run(Path::new(path), force) {
+        Ok(0) => ExitCode::SUCCESS,
+        Ok(_) => ExitCode::FAILURE,
+        Err(e) => {
+            eprintln!("Cannot run {path} : {e}");
+            ExitCode::FAILURE
         }
+    }
+}
+
+// ----------------------------------------------------------------------------
+// runs work in a thread while the main thread draws a progress bar on stderr (when it is a terminal)
+// Ctrl-C cancels the rendering through handle, the workers stop at their next row (see handle.rs)
+fn watch<R: Send>(handle: &RenderHandle, work: impl FnOnce() -> R + Send) -> R {
+    let cancel = handle.clone();
+    // only one handler per process, a command watches one rendering
+    let _ = ctrlc::set_handler(move || cancel.cancel());
+    let terminal = std::io::stderr().is_terminal();
+
+    std::thread::scope(|scope| {
+        let worker = scope.spawn(work);
+        while !worker.is_finished() {
+            if terminal {
+                eprint!("\r{}", handle.bar(40));
+            }
+            std::thread::sleep(Duration::from_millis(200));
+        }
+        if terminal {
+            eprintln!("\r{}", handle.bar(40));
+        }
+        worker
+            .join()
+            .unwrap_or_else(|e| std::panic::resume_unwind(e))
     })
-    .unwrap(); // .expect("A thread panicked during Mandelbrot computation");
 }
 
 // ----------------------------------------------------------------------------
-// does not return an image
-// image is pre-allocated and the processing is done in place
-// fn render_stripe(from: &Complex<f64>, to: &Complex<f64>, width: u32, height: u32) -> Box<[u8]>
-fn render_zone(from: &Complex<f64>, to: &Complex<f64>, width: u32, height: u32, image: &mut [u8]) {
-    let size = to - from;
-    for y in 0..height {
-        for x in 0..width {
-            let c = from
-                + Complex::new(
-                    x as f64 * size.re / width as f64,
-                    y as f64 * size.im / height as f64,
-                );
-            let (r, g, b) = mandelbrot_color(&c);
-            let idx = (y * width + x) as usize * 3;
-            image[idx + 0] = r;
-            image[idx + 1] = g;
-            image[idx + 2] = b;
+// the value of an option like --y4m file.y4m
+fn option_value<'a>(args: &'a [String], name: &str) -> Option<&'a str> {
+    args.iter()
+        .position(|a| a == name)
+        .and_then(|i| args.get(i + 1))
+        .map(String::as_str)
+}
+
+// ----------------------------------------------------------------------------
+// --y4m file.y4m, --y4m - (stdout), --apng file.png or --png directory
+// Err if the option is there but not its value
+fn video_output(args: &[String]) -> Result<Option<VideoOutput>, ()> {
+    if let Some(y4m) = option_value(args, "--y4m") {
+        Ok(Some(VideoOutput::Y4m(
+            (y4m != "-").then(|| PathBuf::from(y4m)),
+        )))
+    } else if let Some(apng) = option_value(args, "--apng") {
+        Ok(Some(VideoOutput::Apng(PathBuf::from(apng))))
+    } else if let Some(directory) = option_value(args, "--png") {
+        Ok(Some(VideoOutput::Png(PathBuf::from(directory))))
+    } else if args
+        .iter()
+        .any(|a| a == "--y4m" || a == "--apng" || a == "--png")
+    {
+        Err(())
+    } else {
+        Ok(None)
+    }
+}
+
+// ----------------------------------------------------------------------------
+fn animate(args: &[String]) -> ExitCode {
+    let usage =
+        "Usage : mandel animate zoom.toml [--force | --y4m file.y4m | --y4m - | --apng file.png]";
+    let Some(path) = args.first().filter(|a| !a.starts_with("--")) else {
+        eprintln!("{usage}");
+        return ExitCode::FAILURE;
+    };
+
+    let output = match video_output(args) {
+        Ok(Some(video)) => AnimOutput::Video(video),
+        Ok(None) => AnimOutput::Frames {
+            force: args.iter().any(|a| a == "--force"),
+        },
+        Err(()) => {
+            eprintln!("{usage}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    match mandel::anim::run(Path::new(path), &output) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Cannot run {path} : {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+// ----------------------------------------------------------------------------
+fn expmap(args: &[String]) -> ExitCode {
+    let usage = "Usage : mandel expmap strip expmap.toml | mandel expmap frames expmap.toml [--y4m file.y4m | --y4m - | --apng file.png]";
+    let (Some(step), Some(path)) = (args.first(), args.get(1)) else {
+        eprintln!("{usage}");
+        return ExitCode::FAILURE;
+    };
+    let Ok(video) = video_output(args) else {
+        eprintln!("{usage}");
+        return ExitCode::FAILURE;
+    };
+
+    let result = match step.as_str() {
+        "strip" => mandel::expmap::run_strip(Path::new(path)),
+        "frames" => mandel::expmap::run_frames(Path::new(path), video.as_ref()),
+        _ => {
+            eprintln!("{usage}");
+            return ExitCode::FAILURE;
+        }
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Cannot run {path} : {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+// ----------------------------------------------------------------------------
+// a number option, default is used when the option is not there
+fn number_option<T: std::str::FromStr>(
+    args: &[String],
+    name: &str,
+    default: T,
+) -> Result<T, String> {
+    match option_value(args, name) {
+        Some(value) => value
+            .parse()
+            .map_err(|_| format!("{name} : {value} is not a valid number")),
+        None if args.iter().any(|a| a == name) => Err(format!("{name} needs a value")),
+        None => Ok(default),
+    }
+}
+
+// ----------------------------------------------------------------------------
+// --center re,im --zoom 1.0 --rotation 0.0 --size 640x480
+// returns the view, the width and the height
+fn view_options(args: &[String]) -> Result<(Viewport, u32, u32), String> {
+    let default = Viewport::default();
+    // all the digits of the center are kept (see coord.rs)
+    let center = match option_value(args, "--center") {
+        Some(value) => value
+            .parse::<ComplexCoord>()
+            .map_err(|e| format!("--center : {e}"))?,
+        None => default.center,
+    };
+    let zoom = number_option(args, "--zoom", default.zoom)?;
+    let rotation = number_option(args, "--rotation", default.rotation)?;
+    let (width, height) = match option_value(args, "--size") {
+        Some(value) => value
+            .split_once('x')
+            .and_then(|(w, h)| Some((w.parse().ok()?, h.parse().ok()?)))
+            .filter(|&(w, h)| w > 0 && h > 0)
+            .ok_or(format!("--size : {value} is not WIDTHxHEIGHT"))?,
+        None => (640, 480),
+    };
+    if zoom <= 0.0 {
+        return Err("--zoom must be greater than 0".to_string());
+    }
+
+    let view = Viewport {
+        center,
+        zoom,
+        rotation,
+    };
+    Ok((view, width, height))
+}
+
+// ----------------------------------------------------------------------------
+fn cycle(args: &[String]) -> ExitCode {
+    let output = match (option_value(args, "--gif"), video_output(args)) {
+        (Some(gif), _) => CycleOutput::Gif(PathBuf::from(gif)),
+        (None, Ok(Some(video))) => CycleOutput::Video(video),
+        _ => {
+            eprintln!("{USAGE}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let options = view_options(args).and_then(|(view, width, height)| {
+        Ok(Cycle {
+            view,
+            width,
+            height,
+            frames: number_option(args, "--frames", 64)?.max(1),
+            fps: number_option(args, "--fps", 25)?.max(1),
+        })
+    });
+    let cycle = match options {
+        Ok(cycle) => cycle,
+        Err(e) => {
+            eprintln!("{e}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    match mandel::cycle::run(&cycle, &output) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Cannot run the palette cycling : {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+// ----------------------------------------------------------------------------
+// the cheapest precision which is adequate for the zoom, unless --precision says otherwise
+// --progressive renders in passes from 1/16 to full resolution and saves the image after each one (see progressive.rs), the precision is the automatic one
+// --mariani renders with the Mariani-Silver subdivision (see mariani.rs), --check compares it with the pixel by pixel rendering
+// otherwise the finished tiles are saved in output.checkpoint while rendering, --resume computes only the missing ones (see checkpoint.rs)
+// the checkpoint is removed once the image is saved, the deep rendering has none (its glitch passes go back over finished tiles)
+// neither have --progressive, --mariani, --trap and --average : --resume is refused with them, and a line says the output has no checkpoint
+// --resume is refused as well with --terminal alone, there is no output file and so no checkpoint
+// --terminal prints the image in the terminal (see terminal.rs), sized to fill it without --size, with or without --output, the status lines go to stderr then
+// --trap colors the pixels with an orbit trap (see trap.rs), --average with the stripe or the triangle inequality average (see average.rs), without checkpoint
+// --trap and --average are refused when the precision is the perturbation, they follow the orbit of each pixel
+fn render(args: &[String]) -> ExitCode {
+    let destination = match terminal_option(args).and_then(|terminal| {
+        Ok(Destination {
+            output: option_value(args, "--output"),
+            terminal,
+            style: ImageStyle::from_args(args)?,
+        })
+    }) {
+        Ok(destination) => destination,
+        Err(e) => {
+            eprintln!("{e}");
+            return ExitCode::FAILURE;
+        }
+    };
+    if destination.output.is_none() && destination.terminal.is_none() {
+        eprintln!("{USAGE}");
+        return ExitCode::FAILURE;
+    }
+
+    let options = view_options(args).and_then(|(view, width, height)| {
+        // the terminal is filled unless --size says otherwise
+        let (width, height) = match destination.terminal {
+            Some(terminal) if option_value(args, "--size").is_none() => terminal.preview_size(),
+            _ => (width, height),
+        };
+        let precision = match option_value(args, "--precision") {
+            None | Some("auto") => Precision::for_view(&view, height),
+            Some(value) => value.parse().map_err(|e| format!("--precision : {e}"))?,
+        };
+        let iterations = number_option(args, "--iterations", ITERATIONS)?;
+        let (trap, average) = (trap_option(args)?, average_option(args)?);
+        if trap.is_some() && average.is_some() {
+            return Err("--trap cannot be combined with --average".to_string());
+        }
+        // the perturbation has no orbit of the pixel itself, only its difference with the reference
+        if precision == Precision::Perturbation
+            && let Some(option) = [("--trap", trap.is_some()), ("--average", average.is_some())]
+                .into_iter()
+                .find_map(|(option, given)| given.then_some(option))
+        {
+            return Err(format!(
+                "{option} needs the orbit of each pixel, the deep (perturbation) rendering has none : zoom out or force --precision dd"
+            ));
+        }
+        Ok((view, width, height, precision, iterations, trap, average))
+    });
+    let (view, width, height, precision, iterations, trap, average) = match options {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{e}");
+            return ExitCode::FAILURE;
+        }
+    };
+    destination.status(&format!("Precision       : {precision}"));
+
+    // only the pixel by pixel rendering of the smooth iterations has a checkpoint, the others say so
+    let no_checkpoint = if trap.is_some() {
+        Some("--trap")
+    } else if average.is_some() {
+        Some("--average")
+    } else if precision == Precision::Perturbation {
+        Some("the deep (perturbation) rendering")
+    } else if destination.output.is_none() {
+        Some("--terminal without --output")
+    } else {
+        ["--progressive", "--mariani"]
+            .into_iter()
+            .find(|&option| args.iter().any(|a| a == option))
+    };
+    if let Some(mode) = no_checkpoint {
+        if args.iter().any(|a| a == "--resume") {
+            eprintln!("--resume : {mode} has no checkpoint, it cannot be resumed");
+            return ExitCode::FAILURE;
+        }
+        if let Some(output) = destination.output {
+            destination.status(&format!(
+                "Checkpoint      : none with {mode}, {output} is only written at the end"
+            ));
+        }
+    }
+
+    let start = Instant::now();
+    let handle = RenderHandle::new();
+    if let Some(trap) = &trap {
+        let job = TrapJob {
+            view: &view,
+            fractal: Fractal::Mandelbrot,
+            precision,
+            iterations,
+            width,
+            height,
+            trap,
+        };
+        let mut colors = vec![None; (width * height) as usize];
+        watch(&handle, || mt_trap_colors(&job, &mut colors, &handle));
+        destination.status(&format!(
+            "Render          : {} ms.",
+            start.elapsed().as_millis()
+        ));
+        if handle.is_cancelled() {
+            eprintln!(
+                "Cancelled at {:.1} %, {} is not saved",
+                100.0 * handle.progress(),
+                destination.name()
+            );
+            return ExitCode::FAILURE;
+        }
+        let style = &destination.style;
+        // the trap colors every orbit, only the misses of an image trap are black (transparent with --alpha)
+        let escaped: Vec<bool> = colors.iter().map(Option::is_some).collect();
+        let image =
+            ColorImage::from_colors(&colors, &escaped, style.mode, style.alpha, width, height);
+        return match destination.write_image(&image) {
+            Ok(()) => ExitCode::SUCCESS,
+            Err(e) => {
+                eprintln!("{e}");
+                ExitCode::FAILURE
+            }
+        };
+    }
+    let smooth = if let Some(average) = average {
+        let job = AverageJob {
+            view: &view,
+            fractal: Fractal::Mandelbrot,
+            precision,
+            iterations,
+            width,
+            height,
+            average,
+        };
+        let mut values = vec![None; (width * height) as usize];
+        watch(&handle, || mt_average_map(&job, &mut values, &handle));
+        values
+    } else if precision == Precision::Perturbation {
+        let deep_view = DeepView {
+            view: view.clone(),
+            iterations,
+            max_references: 32,
+            series_terms: 8,
+        };
+        watch(&handle, || deep_view.render_with(width, height, &handle)).0
+    } else if args.iter().any(|a| a == "--progressive") {
+        if !destination.style.is_rgb8() {
+            eprintln!("--progressive only writes 8 bits rgb images");
+            return ExitCode::FAILURE;
+        }
+        let mut image = vec![0u8; (width * height * 3) as usize].into_boxed_slice();
+        // the output is saved again after each pass, the terminal only shows the last one
+        let mut pass_start = Instant::now();
+        mt_progressive_view(
+            &view,
+            iterations,
+            0.0,
+            width,
+            height,
+            &mut image,
+            |step, image| {
+                let status = match destination
+                    .output
+                    .map(|output| save_image(output, image, width, height))
+                {
+                    Some(Ok(())) => "saved".to_string(),
+                    Some(Err(e)) => format!("cannot save {} : {e}", destination.name()),
+                    None => String::new(),
+                };
+                destination.status(&format!(
+                    "Pass 1/{step:<2}       : {} ms, {status}",
+                    pass_start.elapsed().as_millis()
+                ));
+                pass_start = Instant::now();
+            },
+        );
+        destination.status(&format!(
+            "Render          : {} ms.",
+            start.elapsed().as_millis()
+        ));
+        return match destination.write(&image, width, height) {
+            Ok(()) => ExitCode::SUCCESS,
+            Err(e) => {
+                eprintln!("{e}");
+                ExitCode::FAILURE
+            }
+        };
+    } else if args.iter().any(|a| a == "--mariani") {
+        let mut data = vec![Escape::default(); (width * height) as usize];
+        let stats = match precision {
+            Precision::Single => {
+                mt_mariani_silver::<f32>(&view, iterations, width, height, &mut data)
+            }
+            Precision::Double => {
+                mt_mariani_silver::<f64>(&view, iterations, width, height, &mut data)
+            }
+            _ => mt_mariani_silver::<DoubleDouble>(&view, iterations, width, height, &mut data),
+        };
+        let total = (width * height) as f64;
+        destination.status(&format!(
+            "Computed        : {} pixels ({:.1} %)",
+            stats.computed,
+            100.0 * stats.computed as f64 / total
+        ));
+        destination.status(&format!(
+            "Filled          : {} pixels in {} rectangles",
+            stats.filled, stats.rectangles
+        ));
+        if args.iter().any(|a| a == "--check") {
+            let mismatches = match precision {
+                Precision::Single => check::<f32>(&view, iterations, width, height, &data),
+                Precision::Double => check::<f64>(&view, iterations, width, height, &data),
+                _ => check::<DoubleDouble>(&view, iterations, width, height, &data),
+            };
+            destination.status(&format!(
+                "Mismatches      : {} pixel(s) with another iteration count, {} with an interpolated smooth iteration",
+                mismatches.counts.len(),
+                mismatches.smooth
+            ));
+            for (x, y) in mismatches.counts.iter().take(10) {
+                destination.status(&format!("                  ({x}, {y})"));
+            }
+        }
+        data.iter().map(|e| e.smooth).collect()
+    } else if let Some(output) = destination.output {
+        let job = SmoothJob {
+            view: &view,
+            precision,
+            iterations,
+            width,
+            height,
+        };
+        let path = PathBuf::from(format!("{output}.checkpoint"));
+        let loaded = if args.iter().any(|a| a == "--resume") {
+            Checkpoint::load(&path, &job)
+        } else {
+            Ok(Checkpoint::new(&path, &job))
+        };
+        let mut checkpoint = match loaded {
+            Ok(checkpoint) => checkpoint,
+            Err(e) => {
+                eprintln!("Cannot resume from {} : {e}", path.display());
+                return ExitCode::FAILURE;
+            }
+        };
+        if checkpoint.tiles_done() > 0 {
+            destination.status(&format!(
+                "Resumed         : {}/{} tiles from {}",
+                checkpoint.tiles_done(),
+                checkpoint.tiles(),
+                path.display()
+            ));
+        }
+
+        let mut smooth = vec![None; (width * height) as usize];
+        let saved = watch(&handle, || {
+            mt_resumable_smooth_map(&job, &mut checkpoint, &mut smooth, &handle)
+        });
+        if let Err(e) = saved {
+            eprintln!("Cannot save {} : {e}", path.display());
+        }
+        if handle.is_cancelled() {
+            destination.status(&format!(
+                "Render          : {} ms.",
+                start.elapsed().as_millis()
+            ));
+            eprintln!(
+                "Cancelled at {:.1} %, {output} is not saved, --resume continues from {}",
+                100.0 * handle.progress(),
+                path.display()
+            );
+            return ExitCode::FAILURE;
+        }
+        destination.status(&format!(
+            "Render          : {} ms.",
+            start.elapsed().as_millis()
+        ));
+        let escaped = escaped_pixels(
+            &view,
+            Fractal::Mandelbrot,
+            precision,
+            &smooth,
+            width,
+            height,
+        );
+        return match destination.write_smooth(&smooth, &escaped, width, height) {
+            Ok(()) => {
+                if let Err(e) = checkpoint.remove() {
+                    eprintln!("Cannot remove {} : {e}", path.display());
+                }
+                ExitCode::SUCCESS
+            }
+            Err(e) => {
+                eprintln!("{e}");
+                ExitCode::FAILURE
+            }
+        };
+    } else {
+        // only the terminal, a small image without checkpoint
+        let mut smooth = vec![None; (width * height) as usize];
+        watch(&handle, || {
+            mt_smooth_map_with(
+                &view,
+                precision,
+                iterations,
+                width,
+                height,
+                &mut smooth,
+                &handle,
+            )
+        });
+        smooth
+    };
+    destination.status(&format!(
+        "Render          : {} ms.",
+        start.elapsed().as_millis()
+    ));
+    if handle.is_cancelled() {
+        eprintln!(
+            "Cancelled at {:.1} %, {} is not saved",
+            100.0 * handle.progress(),
+            destination.name()
+        );
+        return ExitCode::FAILURE;
+    }
+
+    let escaped = escaped_pixels(
+        &view,
+        Fractal::Mandelbrot,
+        precision,
+        &smooth,
+        width,
+        height,
+    );
+    match destination.write_smooth(&smooth, &escaped, width, height) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("{e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+// how render colors and writes its png file : --color palette | gray | bw, --alpha, --depth 8 | 16 (see color.rs)
+struct ImageStyle {
+    mode: ColorMode,
+    alpha: bool,
+    sixteen_bits: bool,
+}
+
+impl ImageStyle {
+    // ----------------------------------------------------------------------------
+    fn from_args(args: &[String]) -> Result<Self, String> {
+        let mode = match option_value(args, "--color") {
+            Some(value) => value.parse().map_err(|e| format!("--color : {e}"))?,
+            None => ColorMode::Palette,
+        };
+        let sixteen_bits = match number_option(args, "--depth", 8)? {
+            8 => false,
+            16 => true,
+            depth => return Err(format!("--depth : {depth} is not 8 or 16")),
+        };
+        Ok(ImageStyle {
+            mode,
+            alpha: args.iter().any(|a| a == "--alpha"),
+            sixteen_bits,
+        })
+    }
+
+    // ----------------------------------------------------------------------------
+    // the images of the palette, 8 bits rgb without alpha
+    fn is_rgb8(&self) -> bool {
+        self.mode == ColorMode::Palette && !self.alpha && !self.sixteen_bits
+    }
+}
+
+// where render writes its image : a png file (--output), the terminal (--terminal, see terminal.rs) or both
+struct Destination<'a> {
+    output: Option<&'a str>,
+    terminal: Option<TerminalFormat>,
+    style: ImageStyle,
+}
+
+impl Destination<'_> {
+    // ----------------------------------------------------------------------------
+    fn name(&self) -> &str {
+        self.output.unwrap_or("the image")
+    }
+
+    // ----------------------------------------------------------------------------
+    // a status line of the rendering, on stderr when stdout is the image in the terminal
+    fn status(&self, line: &str) {
+        match self.terminal {
+            Some(_) => eprintln!("{line}"),
+            None => println!("{line}"),
+        }
+    }
+
+    // ----------------------------------------------------------------------------
+    fn write(&self, image: &[u8], width: u32, height: u32) -> Result<(), String> {
+        if let Some(terminal) = self.terminal {
+            print!("{}", terminal.encode(image, width, height));
+        }
+        match self.output {
+            Some(output) => save_image(output, image, width, height)
+                .map_err(|e| format!("Cannot save {output} : {e}")),
+            None => Ok(()),
+        }
+    }
+
+    // ----------------------------------------------------------------------------
+    // the smooth iterations colored with the style, the terminal shows them in 8 bits rgb
+    // escaped tells the set apart for bw and alpha (see escaped_pixels())
+    fn write_smooth(
+        &self,
+        smooth: &[Option<f64>],
+        escaped: &[bool],
+        width: u32,
+        height: u32,
+    ) -> Result<(), String> {
+        let style = &self.style;
+        let image =
+            ColorImage::from_smooth(smooth, escaped, 0.0, style.mode, style.alpha, width, height);
+        self.write_image(&image)
+    }
+
+    // ----------------------------------------------------------------------------
+    fn write_image(&self, image: &ColorImage) -> Result<(), String> {
+        if let Some(terminal) = self.terminal {
+            print!(
+                "{}",
+                terminal.encode(&image.rgb8(), image.width, image.height)
+            );
+        }
+        match self.output {
+            Some(output) => save_color_image(output, image, self.style.sixteen_bits)
+                .map_err(|e| format!("Cannot save {output} : {e}")),
+            None => Ok(()),
+        }
+    }
+}
+
+// ----------------------------------------------------------------------------
+// --trap shape [--trap-color distance | angle] [--trap-scale 10], the png file of an image trap is read here
+fn trap_option(args: &[String]) -> Result<Option<OrbitTrap>, String> {
+    let Some(shape) = option_value(args, "--trap") else {
+        return Ok(None);
+    };
+    if let Some(other) = ["--progressive", "--mariani", "--resume"]
+        .into_iter()
+        .find(|&other| args.iter().any(|a| a == other))
+    {
+        return Err(format!("--trap cannot be combined with {other}"));
+    }
+    let shape = shape.parse().map_err(|e| format!("--trap : {e}"))?;
+    let value = match option_value(args, "--trap-color") {
+        Some(value) => value.parse().map_err(|e| format!("--trap-color : {e}"))?,
+        None => Default::default(),
+    };
+    let scale = number_option(args, "--trap-scale", 10.0)?;
+    OrbitTrap::new(shape, value, scale)
+        .map(Some)
+        .map_err(|e| format!("--trap : {e}"))
+}
+
+// ----------------------------------------------------------------------------
+// --average stripe | tia [--stripes 5] [--average-scale 10]
+fn average_option(args: &[String]) -> Result<Option<OrbitAverage>, String> {
+    let Some(kind) = option_value(args, "--average") else {
+        return Ok(None);
+    };
+    if let Some(other) = ["--progressive", "--mariani", "--resume"]
+        .into_iter()
+        .find(|&other| args.iter().any(|a| a == other))
+    {
+        return Err(format!("--average cannot be combined with {other}"));
+    }
+    Ok(Some(OrbitAverage {
+        kind: kind.parse().map_err(|e| format!("--average : {e}"))?,
+        stripes: number_option(args, "--stripes", 5.0)?,
+        scale: number_option(args, "--average-scale", 10.0)?,
+    }))
+}
+
+// ----------------------------------------------------------------------------
+// --terminal alone is --terminal blocks
+fn terminal_option(args: &[String]) -> Result<Option<TerminalFormat>, String> {
+    let Some(i) = args.iter().position(|a| a == "--terminal") else {
+        return Ok(None);
+    };
+    match args.get(i + 1).filter(|value| !value.starts_with("--")) {
+        Some(value) => value
+            .parse()
+            .map(Some)
+            .map_err(|e| format!("--terminal : {e}")),
+        None => Ok(Some(TerminalFormat::HalfBlocks)),
+    }
+}
+
+// ----------------------------------------------------------------------------
+fn deep(args: &[String]) -> ExitCode {
+    let Some(output) = option_value(args, "--output") else {
+        eprintln!("{USAGE}");
+        return ExitCode::FAILURE;
+    };
+
+    let options = view_options(args).and_then(|(view, width, height)| {
+        let deep_view = DeepView {
+            view,
+            iterations: number_option(args, "--iterations", 5000)?,
+            max_references: number_option(args, "--references", 32)?,
+            series_terms: number_option(args, "--series", 8)?,
+        };
+        Ok((deep_view, width, height))
+    });
+    let (deep_view, width, height) = match options {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{e}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let mut image = vec![0u8; (width * height * 3) as usize].into_boxed_slice();
+    let start = Instant::now();
+    let handle = RenderHandle::new();
+    let stats = watch(&handle, || {
+        deep_view.render_image(width, height, 0.0, &mut image, &handle)
+    });
+    println!("Deep zoom       : {} ms.", start.elapsed().as_millis());
+    if handle.is_cancelled() {
+        eprintln!(
+            "Cancelled at {:.1} %, {output} is not saved",
+            100.0 * handle.progress()
+        );
+        return ExitCode::FAILURE;
+    }
+    println!("Center          : {}", deep_view.view.center);
+    println!(
+        "Precision       : {} bits",
+        64 * deep_view.frac_limbs(height)
+    );
+    println!(
+        "References      : {} (orbits {} ms, pixels {} ms)",
+        stats.references, stats.orbit_ms, stats.render_ms
+    );
+    println!("Glitched pixels : {:?}", stats.glitched);
+    println!(
+        "Series          : {} iterations skipped per pixel ({} ms)",
+        stats.series_skipped, stats.series_ms
+    );
+    let total = (stats.iterations_computed + stats.iterations_skipped).max(1);
+    println!(
+        "Iterations      : {} computed, {} skipped ({:.1} %)",
+        stats.iterations_computed,
+        stats.iterations_skipped,
+        100.0 * stats.iterations_skipped as f64 / total as f64
+    );
+
+    match save_image(output, &image, width, height) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Cannot save {output} : {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+// ----------------------------------------------------------------------------
+// the size of the view options is ignored, the tiles are 256 x 256 and level 0 covers the height of the view
+// run it again to complete an interrupted pyramid, the tiles on disk are skipped
+fn pyramid(args: &[String]) -> ExitCode {
+    let Some(output) = option_value(args, "--output") else {
+        eprintln!("{USAGE}");
+        return ExitCode::FAILURE;
+    };
+
+    let options = view_options(args).and_then(|(view, _, _)| {
+        let levels = number_option(args, "--levels", 5)?;
+        if !(1..=20).contains(&levels) {
+            return Err("--levels must be between 1 and 20".to_string());
+        }
+        Ok(Pyramid {
+            view,
+            levels,
+            iterations: number_option(args, "--iterations", ITERATIONS)?,
+        })
+    });
+    let pyramid = match options {
+        Ok(pyramid) => pyramid,
+        Err(e) => {
+            eprintln!("{e}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let start = Instant::now();
+    let handle = RenderHandle::new();
+    let result = watch(&handle, || {
+        // Box<dyn Error> is not Send
+        pyramid
+            .render(Path::new(output), &handle)
+            .map_err(|e| e.to_string())
+    });
+    println!("Pyramid         : {} ms.", start.elapsed().as_millis());
+    match result {
+        Ok(stats) => {
+            println!(
+                "Tiles           : {} rendered, {} already on disk",
+                stats.rendered, stats.skipped
+            );
+            if handle.is_cancelled() {
+                eprintln!("Cancelled, run the same command again to complete {output}");
+                return ExitCode::FAILURE;
+            }
+            println!(
+                "Size            : {0}x{0} pixels, {output}/pyramid.dzi",
+                pyramid.size()
+            );
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!("Cannot render the pyramid in {output} : {e}");
+            ExitCode::FAILURE
         }
     }
 }
 
 // ----------------------------------------------------------------------------
-fn mandelbrot_color(c: &Complex<f64>) -> (u8, u8, u8) {
-    const ITERATIONS: u32 = 250; //1_000;
-    let mut z = Complex::new(0.0, 0.0);
-    let mut i = 0;
+// --memory is a number of tiles, --disk a number of MB
+fn serve(args: &[String]) -> ExitCode {
+    let options = (|| {
+        Ok::<_, String>(ServeOptions {
+            bind: match option_value(args, "--bind") {
+                Some(value) => value
+                    .parse()
+                    .map_err(|_| format!("--bind : {value} is not an IP address"))?,
+                None => Ipv4Addr::LOCALHOST.into(),
+            },
+            port: number_option(args, "--port", 8080)?,
+            cache: PathBuf::from(option_value(args, "--cache").unwrap_or("tile_cache")),
+            memory_tiles: number_option(args, "--memory", 1024)?,
+            disk_mb: number_option(args, "--disk", 256)?,
+            iterations: number_option(args, "--iterations", 1000)?,
+            threads: number_option(args, "--threads", 4)?,
+        })
+    })();
+    let options = match options {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{e}");
+            return ExitCode::FAILURE;
+        }
+    };
 
-    for t in 0..ITERATIONS {
-        z = z * z + c;
-        if z.norm_sqr() > 4.0 {
-            i = t;
-            break;
+    match mandel::serve::run(&options) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Cannot serve on port {} : {e}", options.port);
+            ExitCode::FAILURE
         }
     }
+}
 
-    if i == 0 {
-        return (0, 0, 0);
+// ----------------------------------------------------------------------------
+// the saved images are 1920x1080 unless --size says otherwise
+fn explore(args: &[String]) -> ExitCode {
+    let options = view_options(args).and_then(|(view, width, height)| {
+        let (width, height) = match option_value(args, "--size") {
+            Some(_) => (width, height),
+            None => (1920, 1080),
+        };
+        Ok(ExploreOptions {
+            view,
+            iterations: number_option(args, "--iterations", ITERATIONS)?,
+            width,
+            height,
+            directory: PathBuf::from(option_value(args, "--directory").unwrap_or(".")),
+        })
+    });
+    let options = match options {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{e}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    match mandel::explore::run(&options) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Cannot explore : {e}");
+            ExitCode::FAILURE
+        }
     }
+}
 
-    let zn = z.norm_sqr().sqrt().ln() / 2.0;
-    let smooth_i = (i as f64) + 1.0 - zn.ln() / std::f64::consts::LN_2;
-    let hue = smooth_i * 0.1;
-    let r = (0.5 + 0.5 * (6.2831 * (hue + 0.0)).cos()) * 255.0;
-    let g = (0.5 + 0.5 * (6.2831 * (hue + 0.33)).cos()) * 255.0;
-    let b = (0.5 + 0.5 * (6.2831 * (hue + 0.66)).cos()) * 255.0;
+// ----------------------------------------------------------------------------
+// the files are stem_iterations.npy, stem_smooth.npy... next to stem
+fn export(args: &[String]) -> ExitCode {
+    let Some(output) = option_value(args, "--output") else {
+        eprintln!("{USAGE}");
+        return ExitCode::FAILURE;
+    };
+    let options = view_options(args).and_then(|(view, width, height)| {
+        let precision = match option_value(args, "--precision") {
+            None | Some("auto") => Precision::for_view(&view, height),
+            Some(value) => value.parse().map_err(|e| format!("--precision : {e}"))?,
+        };
+        let format: RawFormat = option_value(args, "--format")
+            .unwrap_or("npy")
+            .parse()
+            .map_err(|e| format!("--format : {e}"))?;
+        let iterations = number_option(args, "--iterations", ITERATIONS)?;
+        Ok((view, width, height, precision, format, iterations))
+    });
+    let (view, width, height, precision, format, iterations) = match options {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{e}");
+            return ExitCode::FAILURE;
+        }
+    };
+    println!("Precision       : {precision}");
 
-    (r as u8, g as u8, b as u8)
+    let job = RawJob {
+        view: &view,
+        fractal: Fractal::Mandelbrot,
+        precision,
+        iterations,
+        width,
+        height,
+    };
+    let mut records = vec![EscapeRecord::default(); (width * height) as usize];
+    let start = Instant::now();
+    let handle = RenderHandle::new();
+    watch(&handle, || mt_escape_records(&job, &mut records, &handle));
+    println!("Render          : {} ms.", start.elapsed().as_millis());
+    if handle.is_cancelled() {
+        eprintln!(
+            "Cancelled at {:.1} %, nothing is saved",
+            100.0 * handle.progress()
+        );
+        return ExitCode::FAILURE;
+    }
+
+    match mandel::raw::save(Path::new(output), format, &records, width, height) {
+        Ok(files) => {
+            for file in files {
+                println!("Saved           : {}", file.display());
+            }
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!("Cannot save {output} : {e}");
+            ExitCode::FAILURE
+        }
+    }
 }
 
 // ----------------------------------------------------------------------------
-// data is now &[u8] (so far it used to be &Vec<u8>)
-fn save_image(
-    filename: &str,
-    data: &[u8],
-    width: u32,
-    height: u32,
-) -> Result<(), png::EncodingError> {
-    let file = File::create(filename).unwrap();
-    let ref mut w = BufWriter::new(file);
+// the smooth iterations (or the iterations) of an exported field, the set is black (see load_smooth())
+fn recolor(args: &[String]) -> ExitCode {
+    let (Some(input), Some(output)) = (
+        option_value(args, "--input"),
+        option_value(args, "--output"),
+    ) else {
+        eprintln!("{USAGE}");
+        return ExitCode::FAILURE;
+    };
+    let offset = match number_option(args, "--offset", 0.0) {
+        Ok(offset) => offset,
+        Err(e) => {
+            eprintln!("{e}");
+            return ExitCode::FAILURE;
+        }
+    };
+    let (smooth, width, height) = match load_smooth(Path::new(input)) {
+        Ok(field) => field,
+        Err(e) => {
+            eprintln!("Cannot read {input} : {e}");
+            return ExitCode::FAILURE;
+        }
+    };
 
-    let mut encoder = png::Encoder::new(w, width, height);
-    encoder.set_color(png::ColorType::Rgb);
-    encoder.set_depth(png::BitDepth::Eight);
+    let mut image = vec![0u8; (width * height * 3) as usize];
+    color_frame(&smooth, offset, &mut image);
+    match save_image(output, &image, width, height) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Cannot save {output} : {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
 
-    let mut writer = encoder.write_header().unwrap();
-    // writer.write_image_data(data.as_slice())
-    writer.write_image_data(data)
+// ----------------------------------------------------------------------------
+// a list option like --sizes 320x240,640x480, default is used when the option is not there
+fn list_option<T>(
+    args: &[String],
+    name: &str,
+    default: Vec<T>,
+    parse: impl Fn(&str) -> Option<T>,
+) -> Result<Vec<T>, String> {
+    match option_value(args, name) {
+        Some(value) => value
+            .split(',')
+            .map(|item| parse(item).ok_or(format!("{name} : {item} is not valid")))
+            .collect(),
+        None if args.iter().any(|a| a == name) => Err(format!("{name} needs a value")),
+        None => Ok(default),
+    }
+}
+
+// ----------------------------------------------------------------------------
+// the table is printed at the end, one line per measure while it runs
+fn bench(args: &[String]) -> ExitCode {
+    let default = BenchOptions::default();
+    let views = mandel::bench::views();
+    let options = (|| {
+        Ok::<_, String>(BenchOptions {
+            strategies: list_option(args, "--strategies", default.strategies, |s| s.parse().ok())?,
+            sizes: list_option(args, "--sizes", default.sizes, |s| {
+                let (w, h) = s.split_once('x')?;
+                Some((w.parse().ok()?, h.parse().ok()?)).filter(|&(w, h)| w > 0 && h > 0)
+            })?,
+            threads: list_option(args, "--threads", default.threads, |s| {
+                s.parse().ok().filter(|&n| n > 0)
+            })?,
+            views: list_option(args, "--views", default.views, |s| {
+                views.iter().find(|(name, _)| *name == s).cloned()
+            })?,
+            warmup: number_option(args, "--warmup", default.warmup)?,
+            runs: number_option(args, "--runs", default.runs)?,
+        })
+    })();
+    let options = match options {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{e}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let measures = mandel::bench::run(&options, |m| {
+        eprintln!(
+            "{:<12} {:<9} {}x{} {} thread(s) {} : {:.2} ms",
+            m.strategy.to_string(),
+            m.view,
+            m.width,
+            m.height,
+            m.threads,
+            m.precision,
+            m.median()
+        );
+    });
+    println!("{}", mandel::bench::table(&measures));
+    println!("Scaling (median with 1 thread / median with n threads)");
+    print!("{}", mandel::bench::curves(&measures));
+
+    let files = [
+        ("--csv", mandel::bench::csv(&measures)),
+        ("--json", mandel::bench::json(&measures)),
+    ];
+    let mut status = ExitCode::SUCCESS;
+    for (name, text) in files {
+        if let Some(path) = option_value(args, name)
+            && let Err(e) = std::fs::write(path, text)
+        {
+            eprintln!("Cannot write {path} : {e}");
+            status = ExitCode::FAILURE;
+        }
+    }
+    status
 }
@@ -0,0 +1,212 @@
+// Mariani-Silver rendering : large uniform areas (the black interior above all) are not computed pixel by pixel
+// a rectangle is handled like this :
+//      - the pixels of its border are computed
+//      - if they all have the same iteration count, the inside is filled without computing it
+//      - otherwise the rectangle is cut in 2 along its longest side and each half is handled the same way
+// the Mandelbrot set is connected so a rectangle whose border is in the set is in the set, the fill is exact there
+// in the escaped areas the iteration count is exact as long as no thin filament goes through the rectangle without touching its border
+// the smooth iterations inside an escaped rectangle are interpolated from its border (Coons patch), they are close but not the exact values
+// the image is cut in one stripe per thread (mt_for_each_stripe()) and each stripe is subdivided from its whole rectangle
+// check() renders the same view pixel by pixel and lists the pixels which differ
+
+use crate::real::Real;
+use crate::render::{escape_data, mt_for_each_stripe};
+use crate::view::{PixelMapper, Viewport};
+
+// below this width or height the inside of a rectangle is simply computed
+const MIN_SIZE: u32 = 4;
+
+// ----------------------------------------------------------------------------
+// count is the iteration where the pixel escaped, 0 for the set (see escape_data())
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Escape {
+    pub count: u32,
+    pub smooth: Option<f64>,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MarianiStats {
+    pub computed: u64,
+    pub filled: u64,
+    pub rectangles: u64,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Mismatches {
+    // pixels whose iteration count is not the one of the brute force rendering
+    pub counts: Vec<(u32, u32)>,
+    // pixels whose smooth iteration is not exactly the same (the interpolated ones)
+    pub smooth: usize,
+}
+
+// ----------------------------------------------------------------------------
+// data is width * height, the pixels are iterated with the type T (see real.rs)
+pub fn mt_mariani_silver<T: Real>(
+    view: &Viewport,
+    iterations: u32,
+    width: u32,
+    height: u32,
+    data: &mut [Escape],
+) -> MarianiStats {
+    let mapper = view.mapper_for::<T>(width, height);
+    let mut stats = MarianiStats::default();
+    let totals = std::sync::Mutex::new(&mut stats);
+
+    mt_for_each_stripe(height, data, |y_start, y_end, band| {
+        if y_end == y_start {
+            return;
+        }
+        let mut stripe = Stripe {
+            mapper: &mapper,
+            iterations,
+            width,
+            y_start,
+            band,
+            done: vec![false; ((y_end - y_start) * width) as usize],
+            stats: MarianiStats::default(),
+        };
+        stripe.subdivide(0, y_start, width - 1, y_end - 1);
+
+        let mut totals = totals.lock().unwrap();
+        totals.computed += stripe.stats.computed;
+        totals.filled += stripe.stats.filled;
+        totals.rectangles += stripe.stats.rectangles;
+    });
+    stats
+}
+
+// ----------------------------------------------------------------------------
+// the brute force rendering of the same view, compared pixel by pixel
+pub fn check<T: Real>(
+    view: &Viewport,
+    iterations: u32,
+    width: u32,
+    height: u32,
+    data: &[Escape],
+) -> Mismatches {
+    let mapper = view.mapper_for::<T>(width, height);
+    let mut reference = vec![Escape::default(); data.len()];
+    mt_for_each_stripe(height, &mut reference, |y_start, y_end, band| {
+        for y in y_start..y_end {
+            for x in 0..width {
+                band[((y - y_start) * width + x) as usize] = compute(&mapper, iterations, x, y);
+            }
+        }
+    });
+
+    let mut mismatches = Mismatches::default();
+    for (i, (a, b)) in data.iter().zip(&reference).enumerate() {
+        if a.count != b.count {
+            mismatches.counts.push((i as u32 % width, i as u32 / width));
+        } else if a.smooth != b.smooth {
+            mismatches.smooth += 1;
+        }
+    }
+    mismatches
+}
+
+// ----------------------------------------------------------------------------
+fn compute<T: Real>(mapper: &PixelMapper<T>, iterations: u32, x: u32, y: u32) -> Escape {
+    let (count, smooth) = escape_data(&mapper.map(x, y), iterations);
+    Escape { count, smooth }
+}
+
+// the rows y_start.. of the image handled by one thread
+struct Stripe<'a, T: Real> {
+    mapper: &'a PixelMapper<T>,
+    iterations: u32,
+    width: u32,
+    y_start: u32,
+    band: &'a mut [Escape],
+    // the borders are shared by 2 rectangles, they are computed once
+    done: Vec<bool>,
+    stats: MarianiStats,
+}
+
+impl<T: Real> Stripe<'_, T> {
+    // ----------------------------------------------------------------------------
+    fn index(&self, x: u32, y: u32) -> usize {
+        ((y - self.y_start) * self.width + x) as usize
+    }
+
+    // ----------------------------------------------------------------------------
+    fn pixel(&mut self, x: u32, y: u32) -> Escape {
+        let i = self.index(x, y);
+        if !self.done[i] {
+            self.band[i] = compute(self.mapper, self.iterations, x, y);
+            self.done[i] = true;
+            self.stats.computed += 1;
+        }
+        self.band[i]
+    }
+
+    // ----------------------------------------------------------------------------
+    // the rectangle x0..=x1, y0..=y1 (the borders included)
+    fn subdivide(&mut self, x0: u32, y0: u32, x1: u32, y1: u32) {
+        if x1 - x0 < MIN_SIZE || y1 - y0 < MIN_SIZE {
+            for y in y0..=y1 {
+                for x in x0..=x1 {
+                    self.pixel(x, y);
+                }
+            }
+            return;
+        }
+
+        let mut border = Vec::with_capacity(2 * (x1 - x0 + y1 - y0) as usize);
+        for x in x0..=x1 {
+            border.push(self.pixel(x, y0));
+            border.push(self.pixel(x, y1));
+        }
+        for y in y0 + 1..y1 {
+            border.push(self.pixel(x0, y));
+            border.push(self.pixel(x1, y));
+        }
+
+        if border.iter().all(|e| e.count == border[0].count) {
+            self.fill(x0, y0, x1, y1, border[0].count);
+            return;
+        }
+
+        if x1 - x0 >= y1 - y0 {
+            let xm = (x0 + x1) / 2;
+            self.subdivide(x0, y0, xm, y1);
+            self.subdivide(xm, y0, x1, y1);
+        } else {
+            let ym = (y0 + y1) / 2;
+            self.subdivide(x0, y0, x1, ym);
+            self.subdivide(x0, ym, x1, y1);
+        }
+    }
+
+    // ----------------------------------------------------------------------------
+    // the inside of the rectangle gets the count of its border
+    // smooth : None in the set, otherwise the Coons patch of the 4 borders (bilinear blend of the opposite sides minus the bilinear of the corners)
+    fn fill(&mut self, x0: u32, y0: u32, x1: u32, y1: u32, count: u32) {
+        let smooth = |s: &mut Self, x: u32, y: u32| s.band[s.index(x, y)].smooth.unwrap_or(0.0);
+        let (c00, c10) = (smooth(self, x0, y0), smooth(self, x1, y0));
+        let (c01, c11) = (smooth(self, x0, y1), smooth(self, x1, y1));
+
+        for y in y0 + 1..y1 {
+            let v = (y - y0) as f64 / (y1 - y0) as f64;
+            let (left, right) = (smooth(self, x0, y), smooth(self, x1, y));
+            for x in x0 + 1..x1 {
+                let i = self.index(x, y);
+                self.band[i] = Escape {
+                    count,
+                    smooth: (count != 0).then(|| {
+                        let u = (x - x0) as f64 / (x1 - x0) as f64;
+                        let (top, bottom) = (smooth(self, x, y0), smooth(self, x, y1));
+                        (1.0 - u) * left + u * right + (1.0 - v) * top + v * bottom
+                            - ((1.0 - u) * (1.0 - v) * c00
+                                + u * (1.0 - v) * c10
+                                + (1.0 - u) * v * c01
+                                + u * v * c11)
+                    }),
+                };
+                self.done[i] = true;
+            }
+        }
+        self.stats.filled += ((x1 - x0 - 1) * (y1 - y0 - 1)) as u64;
+        self.stats.rectangles += 1;
+    }
+}
@@ -0,0 +1,88 @@
+// writing the images to disk (and reading them back)
+
+use crate::color::ColorImage;
+use crate::formats::{ImageWriter, Png, Samples, writer_for};
+use std::error::Error;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Write};
+use std::path::Path;
+
+// ----------------------------------------------------------------------------
+// data is now &[u8] (so far it used to be &Vec<u8>)
+// the format is the one of the extension (see formats.rs), png by default
+pub fn save_image(
+    filename: &str,
+    data: &[u8],
+    width: u32,
+    height: u32,
+) -> Result<(), Box<dyn Error>> {
+    write_samples(filename, &rgb_samples(data, width, height))
+}
+
+// ----------------------------------------------------------------------------
+// the png file in memory (the tiles sent by serve.rs for example), encoded by the png writer of formats.rs
+pub fn encode_image(data: &[u8], width: u32, height: u32) -> Result<Vec<u8>, Box<dyn Error>> {
+    let mut bytes = Vec::new();
+    Png.write(&mut bytes, &rgb_samples(data, width, height))?;
+    Ok(bytes)
+}
+
+// ----------------------------------------------------------------------------
+// the 8 bits rgb images of save_image() and encode_image()
+fn rgb_samples(data: &[u8], width: u32, height: u32) -> Samples<'_> {
+    Samples {
+        width,
+        height,
+        channels: 3,
+        sixteen_bits: false,
+        data,
+    }
+}
+
+// ----------------------------------------------------------------------------
+// a ColorImage (see color.rs) : grayscale or rgb, with or without alpha, 8 or 16 bits per sample
+// the formats which cannot hold it return an error (16 bits in a bmp for example)
+pub fn save_color_image(
+    filename: &str,
+    image: &ColorImage,
+    sixteen_bits: bool,
+) -> Result<(), Box<dyn Error>> {
+    let data = match sixteen_bits {
+        true => image.samples16(),
+        false => image.samples8(),
+    };
+    let samples = Samples {
+        width: image.width,
+        height: image.height,
+        channels: if image.gray { 1 } else { 3 } + image.alpha as u8,
+        sixteen_bits,
+        data: &data,
+    };
+    write_samples(filename, &samples)
+}
+
+// ----------------------------------------------------------------------------
+// the file is only created once the image is encoded : nothing is left behind when the format does not fit
+fn write_samples(filename: &str, samples: &Samples) -> Result<(), Box<dyn Error>> {
+    let mut bytes = Vec::new();
+    writer_for(Path::new(filename)).write(&mut bytes, samples)?;
+    let mut w = BufWriter::new(File::create(filename)?);
+    w.write_all(&bytes)?;
+    w.flush()?;
+    Ok(())
+}
+
+// ----------------------------------------------------------------------------
+// reads back an rgb png file written by save_image() (the strips of expmap.rs for example)
+// returns the pixels, the width and the height
+pub fn load_image(filename: &str) -> Result<(Vec<u8>, u32, u32), Box<dyn Error>> {
+    let decoder = png::Decoder::new(BufReader::new(File::open(filename)?));
+    let mut reader = decoder.read_info()?;
+    let mut data = vec![0u8; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut data)?;
+    if info.color_type != png::ColorType::Rgb || info.bit_depth != png::BitDepth::Eight {
+        return Err(format!("{filename} is not an 8 bits rgb png file").into());
+    }
+    data.truncate(info.buffer_size());
+    Ok((data, info.width, info.height))
+}
@@ -0,0 +1,88 @@
+// a very small pool of worker threads
+// mt_build_mandelbrot() spawns a new set of threads for every image. This is fine for one image but not for hundreds of thumbnails.
+// here the threads are created once and they wait for jobs on a channel (see the final project of the Rust book)
+// the receiver is shared between the workers with an Arc<Mutex<>>. The lock is only held while a worker picks a job, not while it runs it.
+
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+
+type Job = Box<dyn FnOnce() + Send + 'static>;
+
+pub struct ThreadPool {
+    sender: Option<Sender<Job>>,
+    workers: Vec<JoinHandle<()>>,
+}
+
+impl ThreadPool {
+    // ----------------------------------------------------------------------------
+    // nthreads = 0 means "as many threads as available cores"
+    pub fn new(nthreads: usize) -> ThreadPool {
+        let nthreads = if nthreads == 0 {
+            thread::available_parallelism()
+                .map(|n| n.get())
+                .unwrap_or(1)
+        } else {
+            nthreads
+        };
+
+        let (sender, receiver) = mpsc::channel::<Job>();
+        let receiver = Arc::new(Mutex::new(receiver));
+
+        let workers = (0..nthreads)
+            .map(|_| {
+                let receiver = Arc::clone(&receiver);
+                thread::spawn(move || worker_loop(receiver))
+            })
+            .collect();
+
+        ThreadPool {
+            sender: Some(sender),
+            workers,
+        }
+    }
+
+    // ----------------------------------------------------------------------------
+    pub fn size(&self) -> usize {
+        self.workers.len()
+    }
+
+    // ----------------------------------------------------------------------------
+    pub fn execute<F>(&self, f: F)
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        self.sender
+            .as_ref()
+            .expect("the pool is shutting down")
+            .send(Box::new(f))
+            .expect("all the workers are gone");
+    }
+}
+
+// ----------------------------------------------------------------------------
+// dropping the sender closes the channel, recv() fails in every worker and they leave their loop
+impl Drop for ThreadPool {
+    fn drop(&mut self) {
+        drop(self.sender.take());
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
+
+// ----------------------------------------------------------------------------
+// the guard returned by lock() is a temporary. It is dropped at the end of the "let" statement, before the job runs
+// a job which panics must not kill the worker, otherwise the pool shrinks every time a job fails
+fn worker_loop(receiver: Arc<Mutex<Receiver<Job>>>) {
+    loop {
+        let message = receiver.lock().unwrap().recv();
+        match message {
+            Ok(job) => {
+                let _ = panic::catch_unwind(AssertUnwindSafe(job));
+            }
+            Err(_) => break,
+        }
+    }
+}
@@ -0,0 +1,112 @@
+// progressive rendering for the interactive uses : a coarse image comes fast, then it is refined
+// the passes sample one pixel every 16, 8, 4, 2 then 1 pixel(s) in both directions
+// each sample paints the whole step x step block below and to the right of it, so every pass gives a complete image
+// a pixel sampled by a pass is never computed again : a pass only computes the pixels which are not on the grid of the previous one
+// and its blocks never cover the samples of the previous passes (a block of the new grid contains no point of the old grid but its own corner when it is one)
+// as in mt_build_mandelbrot() the image is allocated once and the passes write in place, there is no copy
+// the rows are dealt to the threads by bands of 16 (mt_for_each_band()) so that a block never straddles 2 threads
+// on_pass(step, image) is called after each pass, with ITERATIONS the last image is exactly the one of mt_render_view()
+// the Julia sets are rendered the same way (see Fractal in render.rs)
+
+use crate::ddouble::DoubleDouble;
+use crate::handle::RenderHandle;
+use crate::real::{Precision, Real};
+use crate::render::{Fractal, mt_for_each_band, palette};
+use crate::view::Viewport;
+
+pub const STEPS: [u32; 5] = [16, 8, 4, 2, 1];
+
+// what mt_progressive() renders
+#[derive(Debug, Clone, Copy)]
+pub struct ProgressiveJob<'a> {
+    pub view: &'a Viewport,
+    pub fractal: Fractal,
+    pub iterations: u32,
+    pub palette_offset: f64,
+    pub width: u32,
+    pub height: u32,
+}
+
+// ----------------------------------------------------------------------------
+// the Mandelbrot set, the type of the iterations is chosen as in mt_render_view()
+pub fn mt_progressive_view<F>(
+    view: &Viewport,
+    iterations: u32,
+    palette_offset: f64,
+    width: u32,
+    height: u32,
+    image: &mut [u8],
+    on_pass: F,
+) where
+    F: FnMut(u32, &[u8]),
+{
+    let job = ProgressiveJob {
+        view,
+        fractal: Fractal::Mandelbrot,
+        iterations,
+        palette_offset,
+        width,
+        height,
+    };
+    mt_progressive(&job, image, &RenderHandle::new(), on_pass);
+}
+
+// ----------------------------------------------------------------------------
+// any fractal, handle can stop the rendering (an interactive view which changed, see explore.rs)
+// the workers check the cancel flag between their rows, a cancelled pass is not given to on_pass
+pub fn mt_progressive<F>(job: &ProgressiveJob, image: &mut [u8], handle: &RenderHandle, on_pass: F)
+where
+    F: FnMut(u32, &[u8]),
+{
+    match Precision::for_view(job.view, job.height) {
+        Precision::Single => progressive::<f32, F>(job, image, handle, on_pass),
+        Precision::Double => progressive::<f64, F>(job, image, handle, on_pass),
+        Precision::DoubleDouble | Precision::Perturbation => {
+            progressive::<DoubleDouble, F>(job, image, handle, on_pass)
+        }
+    }
+}
+
+// ----------------------------------------------------------------------------
+fn progressive<T: Real, F>(
+    job: &ProgressiveJob,
+    image: &mut [u8],
+    handle: &RenderHandle,
+    mut on_pass: F,
+) where
+    F: FnMut(u32, &[u8]),
+{
+    let (width, height) = (job.width, job.height);
+    let mapper = job.view.mapper_for::<T>(width, height);
+
+    for (pass, &step) in STEPS.iter().enumerate() {
+        mt_for_each_band(height, STEPS[0], image, |y_start, y_end, band| {
+            for y in (y_start..y_end).step_by(step as usize) {
+                if handle.is_cancelled() {
+                    return;
+                }
+                for x in (0..width).step_by(step as usize) {
+                    // already sampled by the previous pass
+                    if pass > 0 && x % (2 * step) == 0 && y % (2 * step) == 0 {
+                        continue;
+                    }
+                    let smooth = job.fractal.escape_time(&mapper.map(x, y), job.iterations);
+                    let (r, g, b) = match smooth {
+                        Some(smooth_i) => palette(smooth_i, job.palette_offset),
+                        None => (0, 0, 0),
+                    };
+                    for block_y in y..(y + step).min(y_end) {
+                        for block_x in x..(x + step).min(width) {
+                            let idx = ((block_y - y_start) * width + block_x) as usize * 3;
+                            band[idx..idx + 3].copy_from_slice(&[r, g, b]);
+                        }
+                    }
+                }
+            }
+        });
+        if handle.is_cancelled() {
+            return;
+        }
+        on_pass(step, image);
+    }
+}
@@ -0,0 +1,168 @@
+// tile pyramid for the web viewers : mandel pyramid [view options] --levels 5 --output directory
+// level z is the view rendered as a square image of 256 * 2^z pixels, cut in 2^z * 2^z tiles of 256 x 256
+// level 0 is one tile, each level doubles the resolution of the previous one
+// the tiles are saved in the XYZ layout directory/z/x/y.png (x to the right, y downwards) used by the slippy map viewers
+// pyramid.dzi describes the same tiles for the DeepZoom viewers : its level z + 8 is our level z
+// (the DeepZoom levels below 256 pixels are not rendered, a viewer such as OpenSeadragon is given minLevel: 8
+// and getTileUrl: (level, x, y) => (level - 8) + "/" + x + "/" + y + ".png")
+// a tile is a view of its own (its center and zoom are computed from the pyramid view) rendered with the multithreaded pipeline
+// and the precision its zoom needs, perturbation included (see real.rs and deep.rs)
+// a tile already on disk is skipped, an interrupted pyramid is completed by running the command again
+// the progress of handle is the one of the whole pyramid (see RenderHandle::set_total())
+// the tiles are written to a .tmp file then renamed, a tile on disk is always a complete one
+
+use crate::cycle::color_frame;
+use crate::deep::DeepView;
+use crate::handle::RenderHandle;
+use crate::output::save_image;
+use crate::real::Precision;
+use crate::render::mt_smooth_map_with;
+use crate::view::Viewport;
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub const TILE_SIZE: u32 = 256;
+
+// DeepZoom level of our level 0 (2^8 = TILE_SIZE)
+pub const DZI_LEVEL_OFFSET: u32 = 8;
+
+#[derive(Debug, Clone)]
+pub struct Pyramid {
+    // the whole pyramid, level 0 covers the height of this view (and the same width)
+    pub view: Viewport,
+    // levels 0..levels
+    pub levels: u32,
+    pub iterations: u32,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PyramidStats {
+    pub rendered: u32,
+    pub skipped: u32,
+}
+
+impl Pyramid {
+    // ----------------------------------------------------------------------------
+    // tiles per side at level z
+    pub fn tiles(&self, z: u32) -> u32 {
+        1 << z
+    }
+
+    // ----------------------------------------------------------------------------
+    // width and height of the last level in pixels
+    pub fn size(&self) -> u32 {
+        TILE_SIZE << (self.levels - 1)
+    }
+
+    // ----------------------------------------------------------------------------
+    // the view of the tile (x, y) of level z, rendered at TILE_SIZE x TILE_SIZE
+    // its center is the center of the tile in the whole level, its pixels are exactly the pixels of the level
+    // the position of the center is computed in pixels of level 0 (divided by 2^z, exact in f64) : the level can be wider than a u32
+    pub fn tile_view(&self, z: u32, x: u32, y: u32) -> Viewport {
+        let scale = self.tiles(z) as f64;
+        let offset = self.view.mapper(TILE_SIZE, TILE_SIZE).offset_f64(
+            (x as f64 + 0.5) * TILE_SIZE as f64 / scale,
+            (y as f64 + 0.5) * TILE_SIZE as f64 / scale,
+        );
+        Viewport {
+            center: self.view.center.add_offset(offset),
+            zoom: self.view.zoom * self.tiles(z) as f64,
+            rotation: self.view.rotation,
+        }
+    }
+
+    // ----------------------------------------------------------------------------
+    // rgb image of TILE_SIZE * TILE_SIZE * 3 bytes
+    // a cancelled tile is incomplete, the caller checks handle.is_cancelled()
+    pub fn render_tile(&self, z: u32, x: u32, y: u32, image: &mut [u8], handle: &RenderHandle) {
+        let view = self.tile_view(z, x, y);
+        match Precision::for_view(&view, TILE_SIZE) {
+            Precision::Perturbation => {
+                let deep_view = DeepView {
+                    view,
+                    iterations: self.iterations,
+                    max_references: 32,
+                    series_terms: 8,
+                };
+                deep_view.render_image(TILE_SIZE, TILE_SIZE, 0.0, image, handle);
+            }
+            precision => {
+                let mut smooth = vec![None; (TILE_SIZE * TILE_SIZE) as usize];
+                mt_smooth_map_with(
+                    &view,
+                    precision,
+                    self.iterations,
+                    TILE_SIZE,
+                    TILE_SIZE,
+                    &mut smooth,
+                    handle,
+                );
+                color_frame(&smooth, 0.0, image);
+            }
+        }
+    }
+
+    // ----------------------------------------------------------------------------
+    // the DeepZoom descriptor of the last level
+    pub fn dzi(&self) -> String {
+        format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+             <Image xmlns=\"http://schemas.microsoft.com/deepzoom/2008\" Format=\"png\" Overlap=\"0\" TileSize=\"{TILE_SIZE}\">\n\
+             \x20   <Size Width=\"{0}\" Height=\"{0}\"/>\n\
+             </Image>\n",
+            self.size()
+        )
+    }
+
+    // ----------------------------------------------------------------------------
+    // all the tiles, level after level (an interrupted pyramid has its coarse levels)
+    // stops at the first tile which cannot be saved, or when handle is cancelled
+    pub fn render(
+        &self,
+        directory: &Path,
+        handle: &RenderHandle,
+    ) -> Result<PyramidStats, Box<dyn Error>> {
+        fs::create_dir_all(directory)?;
+        fs::write(directory.join("pyramid.dzi"), self.dzi())?;
+
+        // the tiles on disk are looked for level after level, when the level starts (a big pyramid has millions of tiles)
+        // until then the tiles of a level are counted as missing, the total only shrinks and the progress never goes back
+        let tile_units = (TILE_SIZE * TILE_SIZE) as u64;
+        let mut stats = PyramidStats::default();
+        let mut image = vec![0u8; (TILE_SIZE * TILE_SIZE * 3) as usize];
+        for z in 0..self.levels {
+            let missing: Vec<(u32, u32)> = (0..self.tiles(z))
+                .flat_map(|x| (0..self.tiles(z)).map(move |y| (x, y)))
+                .filter(|&(x, y)| !tile_path(directory, z, x, y).exists())
+                .collect();
+            stats.skipped += self.tiles(z) * self.tiles(z) - missing.len() as u32;
+            let later: u64 = (z + 1..self.levels)
+                .map(|z| self.tiles(z) as u64 * self.tiles(z) as u64)
+                .sum();
+            handle.set_total((stats.rendered as u64 + missing.len() as u64 + later) * tile_units);
+
+            for (x, y) in missing {
+                let path = tile_path(directory, z, x, y);
+                self.render_tile(z, x, y, &mut image, handle);
+                if handle.is_cancelled() {
+                    return Ok(stats);
+                }
+                fs::create_dir_all(path.parent().unwrap())?;
+                let tmp = path.with_extension("png.tmp");
+                save_image(&tmp.to_string_lossy(), &image, TILE_SIZE, TILE_SIZE)?;
+                fs::rename(&tmp, &path)?;
+                stats.rendered += 1;
+            }
+        }
+        Ok(stats)
+    }
+}
+
+// ----------------------------------------------------------------------------
+pub fn tile_path(directory: &Path, z: u32, x: u32, y: u32) -> PathBuf {
+    directory
+        .join(z.to_string())
+        .join(x.to_string())
+        .join(format!("{y}.png"))
+}
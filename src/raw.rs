@@ -0,0 +1,594 @@
+// raw escape data : the numbers behind the colors, for numpy, an image editor or a later recoloring
+// mandel export [view options] [--precision auto] [--iterations 250] [--format npy | pfm | tiff] --output stem
+// each pixel goes through the orbit loop of mandelbrot_color() (Fractal::escape_record(), see render.rs) and gives
+//      - iterations : the number of iterations done when z escaped (1 when z1 already escapes, |c| > 2), 0 for the set
+//      - smooth : the smooth iteration, what palette() colors
+//      - distance : the estimated distance to the set in the complex plane
+//      - z : z when it escaped (the last z of the orbit for the set)
+// the points of the set are NaN in smooth and distance, the points which escape at once are NaN in smooth only (black in the palette, as in render)
+// the files are stem_iterations, stem_smooth, stem_distance and stem_z :
+//      - npy : arrays of shape (height, width), u32 for the iterations, f64 for smooth and distance, complex128 for z
+//      - pfm and tiff : 32 bits float grayscale images, z is split in stem_z_re and stem_z_im
+// mandel recolor --input stem_smooth.npy --output image.png reads back a field (load_smooth()) and colors it with the palette
+// only stem_smooth and stem_iterations are colors (the iterations give the bands of the old images), distance and z are refused
+// Precision::Perturbation is done with DoubleDouble, as in mt_smooth_map_with()
+
+use crate::formats::{TiffLayout, tiff};
+use crate::handle::RenderHandle;
+use crate::real::{Precision, Real};
+use crate::render::{EscapeRecord, Fractal, PerPixel, mt_pixel_map};
+use crate::view::Viewport;
+use num_complex::Complex;
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+const NPY_MAGIC: &[u8] = b"\x93NUMPY";
+
+// what mt_escape_records() computes
+#[derive(Debug, Clone, Copy)]
+pub struct RawJob<'a> {
+    pub view: &'a Viewport,
+    pub fractal: Fractal,
+    pub precision: Precision,
+    pub iterations: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+// ----------------------------------------------------------------------------
+// records is width * height (see mt_pixel_map())
+pub fn mt_escape_records(job: &RawJob, records: &mut [EscapeRecord], handle: &RenderHandle) {
+    mt_pixel_map(
+        job.view,
+        job.precision,
+        job.width,
+        job.height,
+        records,
+        handle,
+        job,
+    );
+}
+
+impl PerPixel<EscapeRecord> for RawJob<'_> {
+    // ----------------------------------------------------------------------------
+    fn pixel<T: Real>(&self, pixel: &Complex<T>) -> EscapeRecord {
+        self.fractal.escape_record(pixel, self.iterations)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RawFormat {
+    Npy,
+    Pfm,
+    Tiff,
+}
+
+impl FromStr for RawFormat {
+    type Err = String;
+
+    // ----------------------------------------------------------------------------
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "npy" => Ok(RawFormat::Npy),
+            "pfm" => Ok(RawFormat::Pfm),
+            "tiff" => Ok(RawFormat::Tiff),
+            _ => Err(format!("{s} is not npy, pfm or tiff")),
+        }
+    }
+}
+
+// ----------------------------------------------------------------------------
+// stem_iterations.npy for example
+fn field_path(stem: &Path, field: &str, extension: &str) -> PathBuf {
+    let mut name = stem.file_name().unwrap_or_default().to_os_string();
+    name.push(format!("_{field}.{extension}"));
+    stem.with_file_name(name)
+}
+
+// ----------------------------------------------------------------------------
+// writes all the fields, returns the files written
+pub fn save(
+    stem: &Path,
+    format: RawFormat,
+    records: &[EscapeRecord],
+    width: u32,
+    height: u32,
+) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+    let nan = f64::NAN;
+    let iterations: Vec<u32> = records.iter().map(|r| r.count).collect();
+    let smooth: Vec<f64> = records.iter().map(|r| r.smooth.unwrap_or(nan)).collect();
+    let distance: Vec<f64> = records.iter().map(|r| r.distance.unwrap_or(nan)).collect();
+
+    let mut files = Vec::new();
+    match format {
+        RawFormat::Npy => {
+            let shape = (height, width);
+            let fields = [
+                (
+                    "iterations",
+                    npy(&iterations, "<u4", shape, |v| v.to_le_bytes()),
+                ),
+                ("smooth", npy(&smooth, "<f8", shape, |v| v.to_le_bytes())),
+                (
+                    "distance",
+                    npy(&distance, "<f8", shape, |v| v.to_le_bytes()),
+                ),
+                (
+                    "z",
+                    npy(records, "<c16", shape, |r| {
+                        let mut bytes = [0u8; 16];
+                        bytes[..8].copy_from_slice(&r.z.re.to_le_bytes());
+                        bytes[8..].copy_from_slice(&r.z.im.to_le_bytes());
+                        bytes
+                    }),
+                ),
+            ];
+            for (field, bytes) in fields {
+                let path = field_path(stem, field, "npy");
+                fs::write(&path, bytes)?;
+                files.push(path);
+            }
+        }
+        RawFormat::Pfm | RawFormat::Tiff => {
+            let fields: [(&str, Vec<f32>); 5] = [
+                ("iterations", iterations.iter().map(|&v| v as f32).collect()),
+                ("smooth", smooth.iter().map(|&v| v as f32).collect()),
+                ("distance", distance.iter().map(|&v| v as f32).collect()),
+                ("z_re", records.iter().map(|r| r.z.re as f32).collect()),
+                ("z_im", records.iter().map(|r| r.z.im as f32).collect()),
+            ];
+            for (field, values) in fields {
+                let (extension, bytes) = match format {
+                    RawFormat::Pfm => ("pfm", pfm(&values, width, height)),
+                    _ => ("tiff", float_tiff(&values, width, height)),
+                };
+                let path = field_path(stem, field, extension);
+                fs::write(&path, bytes)?;
+                files.push(path);
+            }
+        }
+    }
+    Ok(files)
+}
+
+// ----------------------------------------------------------------------------
+// version 1.0 : magic, version, header length (u16), a python dict padded with spaces to a multiple of 64 bytes, the data in C order
+fn npy<T, const N: usize>(
+    values: &[T],
+    descr: &str,
+    (rows, columns): (u32, u32),
+    bytes: impl Fn(&T) -> [u8; N],
+) -> Vec<u8> {
+    let mut header =
+        format!("{{'descr': '{descr}', 'fortran_order': False, 'shape': ({rows}, {columns}), }}");
+    let unpadded = NPY_MAGIC.len() + 4 + header.len() + 1;
+    header.push_str(&" ".repeat(unpadded.next_multiple_of(64) - unpadded));
+    header.push('\n');
+
+    let mut out = NPY_MAGIC.to_vec();
+    out.extend_from_slice(&[1, 0]);
+    out.extend_from_slice(&(header.len() as u16).to_le_bytes());
+    out.extend_from_slice(header.as_bytes());
+    for value in values {
+        out.extend_from_slice(&bytes(value));
+    }
+    out
+}
+
+// ----------------------------------------------------------------------------
+// Pf is one channel, a negative scale means little endian, the rows go from the bottom to the top
+fn pfm(values: &[f32], width: u32, height: u32) -> Vec<u8> {
+    let mut out = format!("Pf\n{width} {height}\n-1.0\n").into_bytes();
+    for row in values.chunks_exact(width as usize).rev() {
+        for value in row {
+            out.extend_from_slice(&value.to_le_bytes());
+        }
+    }
+    out
+}
+
+// ----------------------------------------------------------------------------
+// one 32 bits IEEE float sample per pixel (SampleFormat 3), the rest is the tiff of the images (see formats.rs)
+fn float_tiff(values: &[f32], width: u32, height: u32) -> Vec<u8> {
+    let layout = TiffLayout {
+        width,
+        height,
+        channels: 1,
+        bits: 32,
+        float: true,
+    };
+    let data: Vec<u8> = values.iter().flat_map(|v| v.to_le_bytes()).collect();
+    tiff(&layout, &data)
+}
+
+// ----------------------------------------------------------------------------
+// one field written by save() (or any npy array of shape (height, width) of f8, f4, u4 or i4), chosen by the extension
+// returns the values, the width and the height
+pub fn load_field(path: &Path) -> Result<(Vec<f64>, u32, u32), Box<dyn Error>> {
+    let bytes = fs::read(path)?;
+    match path.extension().and_then(|e| e.to_str()) {
+        Some("npy") => read_npy(&bytes),
+        Some("pfm") => read_pfm(&bytes),
+        Some("tif" | "tiff") => read_float_tiff(&bytes),
+        _ => Err(format!("{} is not a npy, pfm or tiff file", path.display()).into()),
+    }
+}
+
+// the smooth iterations of a field, None for the set
+pub type SmoothField = Vec<Option<f64>>;
+
+// ----------------------------------------------------------------------------
+// the smooth iterations of stem_smooth (NaN is the set) or of stem_iterations (0 is the set), the end of the file name tells which
+// None is a point of the set, as in the smooth maps (see render.rs)
+// an iteration count n is the escape at iteration n - 1 of smooth_from_escape(), its bands are the ones of the old images
+pub fn load_smooth(path: &Path) -> Result<(SmoothField, u32, u32), Box<dyn Error>> {
+    let stem = path
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or_default();
+    let color: fn(f64) -> Option<f64> = if stem.ends_with("_smooth") {
+        |v| (!v.is_nan()).then_some(v)
+    } else if stem.ends_with("_iterations") {
+        |v| (v != 0.0).then_some(v - 1.0)
+    } else {
+        return Err(format!(
+            "{} is not a stem_smooth or stem_iterations field, the others are not colors",
+            path.display()
+        )
+        .into());
+    };
+    let (values, width, height) = load_field(path)?;
+    let smooth = values.into_iter().map(color).collect();
+    Ok((smooth, width, height))
+}
+
+// ----------------------------------------------------------------------------
+// the value of 'key': in the dict of a npy header
+fn header_value<'a>(header: &'a str, key: &str) -> Option<&'a str> {
+    let start = header.find(&format!("'{key}':"))? + key.len() + 3;
+    let rest = header[start..].trim_start();
+    let end = match rest.starts_with('(') {
+        true => rest.find(')')? + 1,
+        false => rest.find([',', '}'])?,
+    };
+    Some(rest[..end].trim())
+}
+
+// ----------------------------------------------------------------------------
+fn read_npy(bytes: &[u8]) -> Result<(Vec<f64>, u32, u32), Box<dyn Error>> {
+    if !bytes.starts_with(NPY_MAGIC) || bytes.len() < 10 {
+        return Err("not a npy file".into());
+    }
+    let (length, start) = match bytes[6] {
+        1 => (u16::from_le_bytes([bytes[8], bytes[9]]) as usize, 10),
+        2 | 3 if bytes.len() >= 12 => (
+            u32::from_le_bytes([bytes[8], bytes[9], bytes[10], bytes[11]]) as usize,
+            12,
+        ),
+        version => return Err(format!("npy version {version} is not supported").into()),
+    };
+    let header = bytes
+        .get(start..start + length)
+        .and_then(|h| std::str::from_utf8(h).ok())
+        .ok_or("truncated npy header")?;
+    if header_value(header, "fortran_order") != Some("False") {
+        return Err("only the npy arrays in C order are supported".into());
+    }
+    let descr = header_value(header, "descr").ok_or("no descr in the npy header")?;
+    let shape: Vec<u32> = header_value(header, "shape")
+        .ok_or("no shape in the npy header")?
+        .trim_matches(['(', ')'])
+        .split(',')
+        .filter(|s| !s.trim().is_empty())
+        .map(|s| s.trim().parse())
+        .collect::<Result<_, _>>()?;
+    let [height, width] = shape[..] else {
+        return Err(format!("the npy array has the shape {shape:?}, not (height, width)").into());
+    };
+
+    let data = &bytes[start + length..];
+    let descr = descr.trim_matches('\'');
+    let value_size = match descr {
+        "<f8" => 8,
+        "<f4" | "<u4" | "<i4" => 4,
+        other => return Err(format!("npy type {other} is not supported").into()),
+    };
+    let data = &data[..data_size(width, height, value_size, data.len())?];
+    let values: Vec<f64> = match descr {
+        "<f8" => data
+            .chunks_exact(8)
+            .map(|b| f64::from_le_bytes(b.try_into().unwrap()))
+            .collect(),
+        "<f4" => data
+            .chunks_exact(4)
+            .map(|b| f32::from_le_bytes(b.try_into().unwrap()) as f64)
+            .collect(),
+        "<u4" => data
+            .chunks_exact(4)
+            .map(|b| u32::from_le_bytes(b.try_into().unwrap()) as f64)
+            .collect(),
+        _ => data
+            .chunks_exact(4)
+            .map(|b| i32::from_le_bytes(b.try_into().unwrap()) as f64)
+            .collect(),
+    };
+    Ok((values, width, height))
+}
+
+// ----------------------------------------------------------------------------
+fn read_pfm(bytes: &[u8]) -> Result<(Vec<f64>, u32, u32), Box<dyn Error>> {
+    // the header is 3 lines : Pf, width height, scale
+    let mut lines = bytes.splitn(4, |&b| b == b'\n');
+    let mut line = || -> Result<&str, Box<dyn Error>> {
+        Ok(std::str::from_utf8(lines.next().ok_or("truncated pfm header")?)?.trim())
+    };
+    if line()? != "Pf" {
+        return Err("only the grayscale pfm files (Pf) are supported".into());
+    }
+    let (width, height) = line()?
+        .split_once(' ')
+        .and_then(|(w, h)| Some((w.trim().parse().ok()?, h.trim().parse().ok()?)))
+        .ok_or("invalid pfm size")?;
+    let scale: f64 = line()?.parse()?;
+    let data = lines.next().unwrap_or_default();
+    let data = &data[..data_size(width, height, 4, data.len())?];
+
+    let value = |b: &[u8]| -> f64 {
+        let b: [u8; 4] = b.try_into().unwrap();
+        match scale < 0.0 {
+            true => f32::from_le_bytes(b) as f64,
+            false => f32::from_be_bytes(b) as f64,
+        }
+    };
+    let values = data
+        .chunks_exact(width as usize * 4)
+        .rev()
+        .flat_map(|row| row.chunks_exact(4).map(value))
+        .collect();
+    Ok((values, width, height))
+}
+
+// ----------------------------------------------------------------------------
+// the tiffs of float_tiff() : little endian, one float sample per pixel, no compression, one strip
+fn read_float_tiff(bytes: &[u8]) -> Result<(Vec<f64>, u32, u32), Box<dyn Error>> {
+    let u16_at = |i: usize| {
+        bytes
+            .get(i..i + 2)
+            .map(|b| u16::from_le_bytes([b[0], b[1]]))
+    };
+    let u32_at = |i: usize| {
+        bytes
+            .get(i..i + 4)
+            .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    };
+    if !bytes.starts_with(b"II*\0") {
+        return Err("only the little endian tiff files are supported".into());
+    }
+    let ifd = u32_at(4).ok_or("truncated tiff")? as usize;
+    let count = u16_at(ifd).ok_or("truncated tiff")? as usize;
+
+    let (mut width, mut height, mut offset) = (0, 0, None);
+    let (mut bits, mut format, mut samples, mut compression) = (1, 1, 1, 1);
+    for entry in (0..count).map(|i| ifd + 2 + i * 12) {
+        let tag = u16_at(entry).ok_or("truncated tiff")?;
+        let kind = u16_at(entry + 2).ok_or("truncated tiff")?;
+        let values = u32_at(entry + 4).ok_or("truncated tiff")?;
+        let value = match kind {
+            3 => u16_at(entry + 8).ok_or("truncated tiff")? as u32,
+            _ => u32_at(entry + 8).ok_or("truncated tiff")?,
+        };
+        match tag {
+            256 => width = value,
+            257 => height = value,
+            258 => bits = value,
+            259 => compression = value,
+            273 if values == 1 => offset = Some(value as usize),
+            273 => return Err("only the tiff files with one strip are supported".into()),
+            277 => samples = value,
+            339 => format = value,
+            _ => (),
+        }
+    }
+    if (bits, format, samples, compression) != (32, 3, 1, 1) {
+        return Err(
+            "only the uncompressed 32 bits float grayscale tiff files are supported".into(),
+        );
+    }
+    let offset = offset.ok_or("no strip in the tiff file")?;
+    let data = bytes.get(offset..).ok_or("truncated tiff")?;
+    let values = data[..data_size(width, height, 4, data.len())?]
+        .chunks_exact(4)
+        .map(|b| f32::from_le_bytes(b.try_into().unwrap()) as f64)
+        .collect();
+    Ok((values, width, height))
+}
+
+// ----------------------------------------------------------------------------
+// the bytes of width * height values, checked before the data is read
+// the header comes from the file : an empty image, a size which overflows or missing bytes are errors, not panics
+fn data_size(
+    width: u32,
+    height: u32,
+    value_size: usize,
+    available: usize,
+) -> Result<usize, Box<dyn Error>> {
+    let size = (width as usize)
+        .checked_mul(height as usize)
+        .and_then(|n| n.checked_mul(value_size))
+        .filter(|&size| size > 0)
+        .ok_or_else(|| format!("invalid size {width}x{height}"))?;
+    if available < size {
+        return Err(
+            format!("{available} bytes for {width}x{height} values of {value_size} bytes").into(),
+        );
+    }
+    Ok(size)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WIDTH: u32 = 3;
+    const HEIGHT: u32 = 2;
+
+    // ----------------------------------------------------------------------------
+    // a different value in each pixel (exact in f32) so that a swapped row shows, the last pixel is in the set
+    fn records() -> Vec<EscapeRecord> {
+        (0..WIDTH * HEIGHT)
+            .map(|i| {
+                let set = i == WIDTH * HEIGHT - 1;
+                let v = i as f64;
+                EscapeRecord {
+                    count: if set { 0 } else { i + 1 },
+                    smooth: (!set && i > 0).then_some(v + 0.25),
+                    distance: (!set).then_some(v * 0.5),
+                    z: Complex::new(v + 2.5, -v),
+                }
+            })
+            .collect()
+    }
+
+    // ----------------------------------------------------------------------------
+    // NaN for None, compared by the bits
+    fn assert_field(path: &Path, expected: impl Iterator<Item = f64>) {
+        let (values, width, height) = load_field(path).unwrap();
+        assert_eq!((width, height), (WIDTH, HEIGHT), "{}", path.display());
+        let bits = |v: Vec<f64>| v.iter().map(|v| v.to_bits()).collect::<Vec<_>>();
+        assert_eq!(bits(values), bits(expected.collect()), "{}", path.display());
+    }
+
+    // ----------------------------------------------------------------------------
+    fn round_trip(format: RawFormat, extension: &str) {
+        let directory = std::env::temp_dir().join(format!("mandel-raw-{}", std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        let stem = directory.join(extension);
+        let records = records();
+        let files = save(&stem, format, &records, WIDTH, HEIGHT).unwrap();
+
+        let field = |name: &str| field_path(&stem, name, extension);
+        let nan = f64::NAN;
+        assert_field(&field("iterations"), records.iter().map(|r| r.count as f64));
+        assert_field(
+            &field("smooth"),
+            records.iter().map(|r| r.smooth.unwrap_or(nan)),
+        );
+        assert_field(
+            &field("distance"),
+            records.iter().map(|r| r.distance.unwrap_or(nan)),
+        );
+        match format {
+            RawFormat::Npy => assert!(
+                load_field(&field("z")).is_err(),
+                "complex128 is not a field"
+            ),
+            _ => {
+                assert_field(&field("z_re"), records.iter().map(|r| r.z.re));
+                assert_field(&field("z_im"), records.iter().map(|r| r.z.im));
+            }
+        }
+        files.iter().for_each(|file| fs::remove_file(file).unwrap());
+    }
+
+    // ----------------------------------------------------------------------------
+    #[test]
+    fn npy_round_trip() {
+        round_trip(RawFormat::Npy, "npy");
+    }
+
+    // ----------------------------------------------------------------------------
+    #[test]
+    fn pfm_round_trip() {
+        round_trip(RawFormat::Pfm, "pfm");
+    }
+
+    // ----------------------------------------------------------------------------
+    #[test]
+    fn tiff_round_trip() {
+        round_trip(RawFormat::Tiff, "tiff");
+    }
+
+    // ----------------------------------------------------------------------------
+    #[test]
+    fn pfm_rows_go_from_the_bottom() {
+        let bytes = pfm(&[1.0, 2.0, 3.0, 4.0], 2, 2);
+        let header = b"Pf\n2 2\n-1.0\n";
+        assert!(bytes.starts_with(header));
+        let first = f32::from_le_bytes(bytes[header.len()..header.len() + 4].try_into().unwrap());
+        assert_eq!(first, 3.0);
+    }
+
+    // ----------------------------------------------------------------------------
+    #[test]
+    fn npy_header_is_padded_to_64_bytes() {
+        for (width, height) in [(1, 1), (3, 2), (1920, 1080), (u32::MAX, u32::MAX)] {
+            let bytes = npy(&[0u32], "<u4", (height, width), |v| v.to_le_bytes());
+            let length = u16::from_le_bytes([bytes[8], bytes[9]]) as usize;
+            assert_eq!((10 + length) % 64, 0, "{width}x{height}");
+            assert_eq!(bytes[10 + length - 1], b'\n');
+        }
+    }
+
+    // ----------------------------------------------------------------------------
+    // a npy of u4 with this shape in its header and this data
+    fn npy_with(shape: &str, data: &[u8]) -> Vec<u8> {
+        let header = format!("{{'descr': '<u4', 'fortran_order': False, 'shape': {shape}, }}\n");
+        let mut bytes = NPY_MAGIC.to_vec();
+        bytes.extend_from_slice(&[1, 0]);
+        bytes.extend_from_slice(&(header.len() as u16).to_le_bytes());
+        bytes.extend_from_slice(header.as_bytes());
+        bytes.extend_from_slice(data);
+        bytes
+    }
+
+    // ----------------------------------------------------------------------------
+    #[test]
+    fn bad_npy_headers_are_errors() {
+        let good = npy_with("(1, 2)", &[0; 8]);
+        assert!(read_npy(&good).is_ok());
+        for length in [0, 6, 9, 20, good.len() - 1] {
+            assert!(read_npy(&good[..length]).is_err(), "truncated at {length}");
+        }
+        assert!(read_npy(&npy_with("(0, 0)", &[])).is_err());
+        assert!(read_npy(&npy_with("(0, 5)", &[0; 8])).is_err());
+        assert!(read_npy(&npy_with("(4294967295, 4294967295)", &[0; 8])).is_err());
+        assert!(read_npy(&npy_with("(4294967296, 1)", &[0; 8])).is_err());
+        assert!(read_npy(&npy_with("(2,)", &[0; 8])).is_err());
+    }
+
+    // ----------------------------------------------------------------------------
+    #[test]
+    fn bad_pfm_headers_are_errors() {
+        let good = pfm(&[0.0; 2], 1, 2);
+        assert!(read_pfm(&good).is_ok());
+        for length in [0, 2, 5, 10, good.len() - 1] {
+            assert!(read_pfm(&good[..length]).is_err(), "truncated at {length}");
+        }
+        assert!(read_pfm(b"Pf\n0 0\n-1.0\n").is_err());
+        assert!(read_pfm(b"Pf\n4294967295 4294967295\n-1.0\n\0\0\0\0").is_err());
+        assert!(read_pfm(b"Pf\n1 1\n-1.0\n").is_err());
+    }
+
+    // ----------------------------------------------------------------------------
+    #[test]
+    fn bad_tiff_headers_are_errors() {
+        let good = float_tiff(&[0.0; 2], 1, 2);
+        assert!(read_float_tiff(&good).is_ok());
+        for length in 0..good.len() {
+            assert!(
+                read_float_tiff(&good[..length]).is_err(),
+                "truncated at {length}"
+            );
+        }
+        // the width and the height are the first two entries (tags 256 and 257)
+        let ifd = u32::from_le_bytes(good[4..8].try_into().unwrap()) as usize;
+        for (width, height) in [(0, 0), (0, 2), (u32::MAX, u32::MAX)] {
+            let mut bad = good.clone();
+            bad[ifd + 10..ifd + 14].copy_from_slice(&width.to_le_bytes());
+            bad[ifd + 22..ifd + 26].copy_from_slice(&height.to_le_bytes());
+            assert!(read_float_tiff(&bad).is_err(), "{width}x{height}");
+        }
+    }
+}
@@ -0,0 +1,138 @@
+// the numeric types of the iterations : f32, f64 and DoubleDouble (see ddouble.rs)
+// render_zone(), smooth_iteration() and the PixelMapper (see view.rs) are generic over Real
+// with f64 the results are bit-identical to the original non generic code, the operations are the same and in the same order
+// Precision is the choice of the type for a view : the cheapest one whose epsilon is still well below the size of a pixel, f64 at least
+// f32 is never chosen automatically : the usual views stay in f64 as they always were, --precision f32 asks for it
+// past the limit of DoubleDouble only perturbation works (see deep.rs)
+
+use crate::coord::Coord;
+use crate::ddouble::DoubleDouble;
+use crate::view::Viewport;
+use std::fmt;
+use std::ops::{Add, Div, Mul, Sub};
+use std::str::FromStr;
+
+// a pixel must be at least PRECISION_MARGIN epsilons wide
+// |z| goes up to 2 and the rounding errors grow along the iterations, a few bits are not enough
+const PRECISION_MARGIN: f64 = 256.0;
+
+pub trait Real:
+    Copy
+    + Send
+    + Sync
+    + PartialOrd
+    + Add<Output = Self>
+    + Sub<Output = Self>
+    + Mul<Output = Self>
+    + Div<Output = Self>
+{
+    // relative precision
+    const EPSILON: f64;
+
+    fn from_f64(value: f64) -> Self;
+    fn to_f64(self) -> f64;
+
+    // ----------------------------------------------------------------------------
+    // the centers of the views keep more digits than a f64 (see coord.rs)
+    fn from_coord(value: &Coord) -> Self {
+        Self::from_f64(value.to_f64())
+    }
+}
+
+impl Real for f32 {
+    const EPSILON: f64 = f32::EPSILON as f64;
+
+    fn from_f64(value: f64) -> Self {
+        value as f32
+    }
+
+    fn to_f64(self) -> f64 {
+        self as f64
+    }
+}
+
+impl Real for f64 {
+    const EPSILON: f64 = f64::EPSILON;
+
+    fn from_f64(value: f64) -> Self {
+        value
+    }
+
+    fn to_f64(self) -> f64 {
+        self
+    }
+}
+
+impl Real for DoubleDouble {
+    // 2^-104
+    const EPSILON: f64 = 4.930380657631324e-32;
+
+    fn from_f64(value: f64) -> Self {
+        DoubleDouble::from_f64(value)
+    }
+
+    fn to_f64(self) -> f64 {
+        DoubleDouble::to_f64(self)
+    }
+
+    fn from_coord(value: &Coord) -> Self {
+        DoubleDouble::from_coord(value)
+    }
+}
+
+// ----------------------------------------------------------------------------
+// from the cheapest to the most expensive
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Precision {
+    Single,
+    Double,
+    DoubleDouble,
+    Perturbation,
+}
+
+impl Precision {
+    // ----------------------------------------------------------------------------
+    pub fn for_pixel_size(pixel_size: f64) -> Self {
+        let adequate = |epsilon: f64| pixel_size >= PRECISION_MARGIN * epsilon;
+        if adequate(<f64 as Real>::EPSILON) {
+            Precision::Double
+        } else if adequate(<DoubleDouble as Real>::EPSILON) {
+            Precision::DoubleDouble
+        } else {
+            Precision::Perturbation
+        }
+    }
+
+    // ----------------------------------------------------------------------------
+    pub fn for_view(view: &Viewport, height: u32) -> Self {
+        Self::for_pixel_size(view.pixel_size(height))
+    }
+}
+
+impl FromStr for Precision {
+    type Err = String;
+
+    // ----------------------------------------------------------------------------
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        match text {
+            "f32" => Ok(Precision::Single),
+            "f64" => Ok(Precision::Double),
+            "dd" => Ok(Precision::DoubleDouble),
+            "deep" => Ok(Precision::Perturbation),
+            _ => Err(format!("{text} is not f32, f64, dd or deep")),
+        }
+    }
+}
+
+impl fmt::Display for Precision {
+    // ----------------------------------------------------------------------------
+    // the names read by from_str()
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Precision::Single => "f32",
+            Precision::Double => "f64",
+            Precision::DoubleDouble => "dd",
+            Precision::Perturbation => "deep",
+        })
+    }
+}
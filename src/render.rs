@@ -0,0 +1,734 @@
+// rendering functions shared by all the commands
+// mt_build_mandelbrot(), render_zone() and mandelbrot_color() come from main_10 (see main_10.rs)
+// render_rows() is new. It renders a band of rows but maps the pixels with the coordinates of the whole image
+// this way a band computed alone gives exactly the same bytes than the same rows computed by render_zone()
+// pool_build_mandelbrot() is new too. It uses render_rows() and the threads of a ThreadPool
+// render_zone(), render_rows(), mandelbrot_color() and smooth_iteration() are generic over the numeric type (f32, f64 or DoubleDouble, see real.rs)
+// with Complex<f64> arguments they are exactly the code of main_10, the views choose the cheapest adequate type (Precision::for_view())
+
+use crate::ddouble::DoubleDouble;
+use crate::handle::RenderHandle;
+use crate::pool::ThreadPool;
+use crate::real::{Precision, Real};
+use crate::simd::Kernel;
+use crate::view::{PixelMapper, Viewport};
+use num_complex::Complex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc;
+
+pub const ITERATIONS: u32 = 250; //1_000;
+
+// the smooth maps are computed by tiles of TILE_ROWS rows, the progress is reported after each one (see handle.rs)
+pub const TILE_ROWS: u32 = 4;
+
+// number of threads of the mt_ functions, 0 for one per core (see set_threads())
+static THREADS: AtomicUsize = AtomicUsize::new(0);
+
+// ----------------------------------------------------------------------------
+// the benchmarks measure each strategy with 1, 2... threads (see bench.rs), 0 goes back to one thread per core
+pub fn set_threads(nthreads: usize) {
+    THREADS.store(nthreads, Ordering::Relaxed);
+}
+
+// ----------------------------------------------------------------------------
+pub fn threads() -> usize {
+    match THREADS.load(Ordering::Relaxed) {
+        0 => std::thread::available_parallelism()
+            .map(|n| n.get())
+            .unwrap_or(1),
+        nthreads => nthreads,
+    }
+}
+
+// ----------------------------------------------------------------------------
+// does not return an image
+// no longer joins the stripes to rebuild the image
+// try to be smarter if the number of cores does not divide the height of the image evenly
+// .split_at_mut() is used to help the compiler "understand" (trust in me, just in me...) that each thread work on non-overlapping parts of the image
+// each stripe is rendered with render_rows() : the corners of a stripe computed on their own were not exactly the rows of the whole image
+// and a few pixels differed from render_zone() (2 in image_rgb_mt_10.png), tests/golden.rs checks they are the same bytes now
+pub fn mt_build_mandelbrot(
+    from: &Complex<f64>,
+    to: &Complex<f64>,
+    width: u32,
+    height: u32,
+    image: &mut [u8],
+) {
+    let nthreads = threads();
+    println!("# of threads    : {nthreads}");
+
+    let stripe_width = width;
+    // let stripe_height = height / nthreads;
+    // the value height / nthreads, nthreads times
+    let mut stripe_heights = vec![height / nthreads as u32; nthreads];
+    // handles cases where height is not divisible by nthreads (480 and 7 for example)
+    // 480 % 7 = 4 - the first 4 stripes receive 1 additional line each
+    for i in 0..(height % nthreads as u32) {
+        stripe_heights[i as usize] += 1;
+    }
+
+    let mut y_start = 0;
+    // the scope guarantees that all threads are joined before the end of the block.
+    // so the compiler "knows" that threads won't escape and that local references will live long enough.
+    crossbeam::thread::scope(|my_scope| {
+        let mut remaining = image;
+
+        for stripe_height in stripe_heights {
+            let stripe_byte_count = (stripe_height * stripe_width * 3) as usize;
+
+            // .split_at_mut() returns 2 disjoint mutables slices
+            let (stripe, rest) = remaining.split_at_mut(stripe_byte_count);
+            remaining = rest;
+
+            let y_end = y_start + stripe_height;
+            my_scope.spawn(move |_| {
+                render_rows(from, to, stripe_width, height, y_start, y_end, stripe);
+            });
+
+            y_start = y_end;
+        }
+    })
+    .unwrap(); // .expect("A thread panicked during Mandelbrot computation");
+}
+
+// ----------------------------------------------------------------------------
+// does not return an image
+// image is pre-allocated and the processing is done in place
+// fn render_stripe(from: &Complex<f64>, to: &Complex<f64>, width: u32, height: u32) -> Box<[u8]>
+pub fn render_zone<T: Real>(
+    from: &Complex<T>,
+    to: &Complex<T>,
+    width: u32,
+    height: u32,
+    image: &mut [u8],
+) {
+    render_rows(from, to, width, height, 0, height, image);
+}
+
+// ----------------------------------------------------------------------------
+// renders the rows y_start..y_end of the width x height image which goes from "from" to "to"
+// band only contains these rows (its size is (y_end - y_start) * width * 3)
+pub fn render_rows<T: Real>(
+    from: &Complex<T>,
+    to: &Complex<T>,
+    width: u32,
+    height: u32,
+    y_start: u32,
+    y_end: u32,
+    band: &mut [u8],
+) {
+    let size = Complex::new(to.re - from.re, to.im - from.im);
+    let (w, h) = (T::from_f64(width as f64), T::from_f64(height as f64));
+    for y in y_start..y_end {
+        for x in 0..width {
+            let c = Complex::new(
+                from.re + T::from_f64(x as f64) * size.re / w,
+                from.im + T::from_f64(y as f64) * size.im / h,
+            );
+            let (r, g, b) = mandelbrot_color(&c);
+            let idx = ((y - y_start) * width + x) as usize * 3;
+            band[idx] = r;
+            band[idx + 1] = g;
+            band[idx + 2] = b;
+        }
+    }
+}
+
+// ----------------------------------------------------------------------------
+// mandelbrot_color() is now split in 2 parts : smooth_iteration() and palette()
+// this way the colors can be changed (palette offset) without recomputing the iterations
+pub fn mandelbrot_color<T: Real>(c: &Complex<T>) -> (u8, u8, u8) {
+    match smooth_iteration(c) {
+        Some(smooth_i) => palette(smooth_i, 0.0),
+        None => (0, 0, 0),
+    }
+}
+
+// ----------------------------------------------------------------------------
+// None when the point is considered in the set (black)
+// as in main_10, a point which escapes at the very first iteration is black too
+pub fn smooth_iteration<T: Real>(c: &Complex<T>) -> Option<f64> {
+    escape_time(c, ITERATIONS)
+}
+
+// ----------------------------------------------------------------------------
+// the loop of smooth_iteration() with any number of iterations
+// z * z + c and |z|^2 are written the way Complex<f64> computes them, the f64 results are the same bits
+pub fn escape_time<T: Real>(c: &Complex<T>, iterations: u32) -> Option<f64> {
+    escape_data(c, iterations).1
+}
+
+// ----------------------------------------------------------------------------
+// the iteration where c escaped (0 if it never did) and its smooth iteration
+pub fn escape_data<T: Real>(c: &Complex<T>, iterations: u32) -> (u32, Option<f64>) {
+    let zero = T::from_f64(0.0);
+    orbit_escape_data(&Complex::new(zero, zero), c, iterations)
+}
+
+// ----------------------------------------------------------------------------
+// the same loop from any z0 : escape_data() starts from 0, a Julia set from the pixel (see Fractal)
+// from 0 the first iteration gives exactly c, the results of escape_data() did not change
+pub fn orbit_escape_data<T: Real>(
+    z0: &Complex<T>,
+    c: &Complex<T>,
+    iterations: u32,
+) -> (u32, Option<f64>) {
+    let (escape, z, _) = orbit::<T, false>(z0, c, iterations, 0.0, |_| ());
+    let i = escape.unwrap_or(0);
+    (i, smooth_from_escape(i, z))
+}
+
+// ----------------------------------------------------------------------------
+// the loop of all the escape functions : z = z * z + c from z0 until |z|^2 > 4
+// returns the iteration of the escape (None if none, Some(0) when z1 already escapes), z at that time and its derivative
+// with DERIVATIVE the derivative dz = 2 * z * dz + dc is iterated along in f64 (dz starts at 1 - dc)
+// dc is 1 for the Mandelbrot set (derivative along c) and 0 for a Julia set (along z0)
+// without DERIVATIVE the compiler removes it, the loop is the one of escape_time()
+// visit sees each new z of the orbit (the one which escapes too), the orbit traps and the averages (see trap.rs) follow it this way
+// |_| () costs nothing, it is removed as well
+fn orbit<T: Real, const DERIVATIVE: bool>(
+    z0: &Complex<T>,
+    c: &Complex<T>,
+    iterations: u32,
+    dc: f64,
+    mut visit: impl FnMut(Complex<f64>),
+) -> (Option<u32>, Complex<f64>, Complex<f64>) {
+    let four = T::from_f64(4.0);
+    let (mut re, mut im) = (z0.re, z0.im);
+    let mut dz = Complex::new(1.0 - dc, 0.0);
+    let mut escape = None;
+
+    for t in 0..iterations {
+        if DERIVATIVE {
+            dz = 2.0 * Complex::new(re.to_f64(), im.to_f64()) * dz + dc;
+        }
+        let square = Complex::new(re * re - im * im, re * im + im * re);
+        re = square.re + c.re;
+        im = square.im + c.im;
+        visit(Complex::new(re.to_f64(), im.to_f64()));
+        if re * re + im * im > four {
+            escape = Some(t);
+            break;
+        }
+    }
+
+    (escape, Complex::new(re.to_f64(), im.to_f64()), dz)
+}
+
+// ----------------------------------------------------------------------------
+// everything the orbit of a pixel tells, for the raw exports (see raw.rs)
+// count is the number of iterations done when z escaped (1 when z1 already escapes), 0 for a point of the set
+// smooth and distance are None for a point of the set, z is then the last value of the orbit
+// smooth is None for count 1 as well, smooth_from_escape() has nothing there (palette() paints it black)
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct EscapeRecord {
+    pub count: u32,
+    pub smooth: Option<f64>,
+    // estimated distance to the set in the complex plane : 2 |z| ln|z| / |dz|
+    // 0 when dz overflowed (a pixel on the border of the set)
+    pub distance: Option<f64>,
+    pub z: Complex<f64>,
+}
+
+// ----------------------------------------------------------------------------
+fn escape_record(escape: Option<u32>, z: Complex<f64>, dz: Complex<f64>) -> EscapeRecord {
+    let count = escape.map_or(0, |t| t + 1);
+    let smooth = smooth_from_escape(escape.unwrap_or(0), z);
+    let distance = escape.map(|_| {
+        let modulus = z.norm();
+        let distance = 2.0 * modulus * modulus.ln() / dz.norm();
+        if distance.is_finite() { distance } else { 0.0 }
+    });
+    EscapeRecord {
+        count,
+        smooth,
+        distance,
+        z,
+    }
+}
+
+// ----------------------------------------------------------------------------
+// the set which is drawn : the Mandelbrot set (z0 = 0 and c is the pixel) or the Julia set of c (z0 is the pixel)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Fractal {
+    Mandelbrot,
+    Julia(Complex<f64>),
+}
+
+impl Fractal {
+    // ----------------------------------------------------------------------------
+    pub fn escape_data<T: Real>(&self, pixel: &Complex<T>, iterations: u32) -> (u32, Option<f64>) {
+        match self {
+            Fractal::Mandelbrot => escape_data(pixel, iterations),
+            Fractal::Julia(c) => orbit_escape_data(
+                pixel,
+                &Complex::new(T::from_f64(c.re), T::from_f64(c.im)),
+                iterations,
+            ),
+        }
+    }
+
+    // ----------------------------------------------------------------------------
+    pub fn escape_time<T: Real>(&self, pixel: &Complex<T>, iterations: u32) -> Option<f64> {
+        self.escape_data(pixel, iterations).1
+    }
+
+    // ----------------------------------------------------------------------------
+    // the same orbit, with the distance estimate and the final z
+    pub fn escape_record<T: Real>(&self, pixel: &Complex<T>, iterations: u32) -> EscapeRecord {
+        let (z0, c, dc) = self.orbit_start(pixel);
+        let (escape, z, dz) = orbit::<T, true>(&z0, &c, iterations, dc, |_| ());
+        escape_record(escape, z, dz)
+    }
+
+    // ----------------------------------------------------------------------------
+    // the orbit of the pixel, each z goes through visit, returns the iteration of the escape (0 if none) and the last z
+    pub fn visit_orbit<T: Real>(
+        &self,
+        pixel: &Complex<T>,
+        iterations: u32,
+        visit: impl FnMut(Complex<f64>),
+    ) -> (u32, Complex<f64>) {
+        let (z0, c, _) = self.orbit_start(pixel);
+        let (escape, z, _) = orbit::<T, false>(&z0, &c, iterations, 0.0, visit);
+        (escape.unwrap_or(0), z)
+    }
+
+    // ----------------------------------------------------------------------------
+    // z1 already escapes (|c| > 2 for the Mandelbrot set) : the point is not in the set, but smooth_from_escape() gives None as for the set
+    pub fn escapes_at_once<T: Real>(&self, pixel: &Complex<T>) -> bool {
+        let (z0, c, _) = self.orbit_start(pixel);
+        orbit::<T, false>(&z0, &c, 1, 0.0, |_| ()).0.is_some()
+    }
+
+    // ----------------------------------------------------------------------------
+    // z0, c and the dc of the derivative (see orbit())
+    pub fn orbit_start<T: Real>(&self, pixel: &Complex<T>) -> (Complex<T>, Complex<T>, f64) {
+        match self {
+            Fractal::Mandelbrot => {
+                let zero = T::from_f64(0.0);
+                (Complex::new(zero, zero), *pixel, 1.0)
+            }
+            Fractal::Julia(c) => (
+                *pixel,
+                Complex::new(T::from_f64(c.re), T::from_f64(c.im)),
+                0.0,
+            ),
+        }
+    }
+}
+
+// ----------------------------------------------------------------------------
+// i is the iteration where z escaped (0 if it never did), z its value at that time
+// shared by all the ways to iterate (see deep.rs)
+pub fn smooth_from_escape(i: u32, z: Complex<f64>) -> Option<f64> {
+    if i == 0 {
+        return None;
+    }
+
+    let zn = z.norm_sqr().sqrt().ln() / 2.0;
+    Some((i as f64) + 1.0 - zn.ln() / std::f64::consts::LN_2)
+}
+
+// ----------------------------------------------------------------------------
+// offset shifts the colors along the palette, 1.0 is a full turn
+// 6.2831 is kept on purpose (and not std::f64::consts::TAU) so that the colors remain the ones of the png files in ./assets
+pub fn palette(smooth_i: f64, offset: f64) -> (u8, u8, u8) {
+    let [r, g, b] = palette_unit(smooth_i, offset);
+    ((r * 255.0) as u8, (g * 255.0) as u8, (b * 255.0) as u8)
+}
+
+// ----------------------------------------------------------------------------
+// the components of palette() between 0.0 and 1.0, before they become bytes (the 16 bits images, see color.rs)
+#[allow(clippy::approx_constant)]
+pub fn palette_unit(smooth_i: f64, offset: f64) -> [f64; 3] {
+    let hue = smooth_i * 0.1 + offset;
+    [
+        0.5 + 0.5 * (6.2831 * (hue + 0.0)).cos(),
+        0.5 + 0.5 * (6.2831 * (hue + 0.33)).cos(),
+        0.5 + 0.5 * (6.2831 * (hue + 0.66)).cos(),
+    ]
+}
+
+// ----------------------------------------------------------------------------
+// same job than mt_build_mandelbrot() but the threads come from a pool (see pool.rs) and are not spawned for each image
+// the image is cut in bands of BAND_HEIGHT rows. There are many more bands than threads so a thread which gets an "easy" band simply picks the next one
+// the jobs sent to the pool must be 'static, they cannot borrow image
+// so each band is rendered in its own buffer, sent back on a channel and copied in place
+// returns an error if a band never came back (a job panicked)
+pub fn pool_build_mandelbrot(
+    pool: &ThreadPool,
+    from: &Complex<f64>,
+    to: &Complex<f64>,
+    width: u32,
+    height: u32,
+    image: &mut [u8],
+) -> Result<(), String> {
+    const BAND_HEIGHT: u32 = 16;
+
+    let (sender, receiver) = mpsc::channel::<(u32, Vec<u8>)>();
+    let (from, to) = (*from, *to);
+
+    let mut nbands = 0;
+    for y_start in (0..height).step_by(BAND_HEIGHT as usize) {
+        let y_end = (y_start + BAND_HEIGHT).min(height);
+        let sender = sender.clone();
+        pool.execute(move || {
+            let mut band = vec![0u8; ((y_end - y_start) * width * 3) as usize];
+            render_rows(&from, &to, width, height, y_start, y_end, &mut band);
+            let _ = sender.send((y_start, band));
+        });
+        nbands += 1;
+    }
+    // otherwise the loop below would wait forever
+    drop(sender);
+
+    let mut received = 0;
+    for (y_start, band) in receiver {
+        let offset = (y_start * width * 3) as usize;
+        image[offset..offset + band.len()].copy_from_slice(&band);
+        received += 1;
+    }
+
+    if received == nbands {
+        Ok(())
+    } else {
+        Err(format!(
+            "{} band(s) out of {nbands} failed",
+            nbands - received
+        ))
+    }
+}
+
+// ----------------------------------------------------------------------------
+// renders the rows y_start..y_end of an image described by a Viewport (see view.rs), the pixels are iterated with the type T
+// like render_rows(), band only contains these rows
+pub fn render_view_rows<T: Real>(
+    view: &Viewport,
+    palette_offset: f64,
+    width: u32,
+    height: u32,
+    y_start: u32,
+    y_end: u32,
+    band: &mut [u8],
+) {
+    let mapper = view.mapper_for::<T>(width, height);
+    for y in y_start..y_end {
+        for x in 0..width {
+            let c = mapper.map(x, y);
+            let (r, g, b) = match smooth_iteration(&c) {
+                Some(smooth_i) => palette(smooth_i, palette_offset),
+                None => (0, 0, 0),
+            };
+            let idx = ((y - y_start) * width + x) as usize * 3;
+            band[idx] = r;
+            band[idx + 1] = g;
+            band[idx + 2] = b;
+        }
+    }
+}
+
+// ----------------------------------------------------------------------------
+// the multithreaded version of render_view_rows(), the image is processed in place
+// the type of the iterations is the cheapest adequate one for the zoom. Past DoubleDouble (Precision::Perturbation) DoubleDouble is still used, see deep.rs for these zooms
+pub fn mt_render_view(
+    view: &Viewport,
+    palette_offset: f64,
+    width: u32,
+    height: u32,
+    image: &mut [u8],
+) {
+    match Precision::for_view(view, height) {
+        Precision::Single => mt_for_each_stripe(height, image, |y_start, y_end, stripe| {
+            render_view_rows::<f32>(view, palette_offset, width, height, y_start, y_end, stripe)
+        }),
+        Precision::Double => mt_for_each_stripe(height, image, |y_start, y_end, stripe| {
+            render_view_rows::<f64>(view, palette_offset, width, height, y_start, y_end, stripe)
+        }),
+        Precision::DoubleDouble | Precision::Perturbation => {
+            mt_for_each_stripe(height, image, |y_start, y_end, stripe| {
+                render_view_rows::<DoubleDouble>(
+                    view,
+                    palette_offset,
+                    width,
+                    height,
+                    y_start,
+                    y_end,
+                    stripe,
+                )
+            })
+        }
+    }
+}
+
+// ----------------------------------------------------------------------------
+// the smooth iteration of every pixel, no color. None is a point of the set
+// computed once, the image can then be colored again and again (see cycle.rs)
+pub fn mt_smooth_map(view: &Viewport, width: u32, height: u32, smooth: &mut [Option<f64>]) {
+    let precision = Precision::for_view(view, height);
+    let handle = RenderHandle::new();
+    mt_smooth_map_with(view, precision, ITERATIONS, width, height, smooth, &handle);
+}
+
+// ----------------------------------------------------------------------------
+// the same with a given precision and number of iterations (Precision::Perturbation is done with DoubleDouble)
+pub fn mt_smooth_map_with(
+    view: &Viewport,
+    precision: Precision,
+    iterations: u32,
+    width: u32,
+    height: u32,
+    smooth: &mut [Option<f64>],
+    handle: &RenderHandle,
+) {
+    let job = SmoothJob {
+        view,
+        precision,
+        iterations,
+        width,
+        height,
+    };
+    mt_smooth_map_tiles(&job, smooth, handle, |_| false, |_, _| ());
+}
+
+// ----------------------------------------------------------------------------
+// what mt_smooth_map_tiles() computes
+#[derive(Debug, Clone, Copy)]
+pub struct SmoothJob<'a> {
+    pub view: &'a Viewport,
+    pub precision: Precision,
+    pub iterations: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+// ----------------------------------------------------------------------------
+// the tiles are bands of TILE_ROWS rows, dealt to the threads (mt_for_each_band())
+// skip(y_start) is true for a tile which is already in smooth (see checkpoint.rs), it is not computed again
+// finished(y_start, tile) is called by the worker after each tile it computed
+// the progress is reported and the cancel flag checked after each tile (see handle.rs)
+// f64 goes through the SIMD kernel (see simd.rs), the values are the same as the scalar escape_time()
+pub fn mt_smooth_map_tiles<S, D>(
+    job: &SmoothJob,
+    smooth: &mut [Option<f64>],
+    handle: &RenderHandle,
+    skip: S,
+    finished: D,
+) where
+    S: Fn(u32) -> bool + Sync,
+    D: Fn(u32, &[Option<f64>]) + Sync,
+{
+    let (width, height, iterations) = (job.width, job.height, job.iterations);
+    let todo: u32 = (0..height)
+        .step_by(TILE_ROWS as usize)
+        .filter(|&y_start| !skip(y_start))
+        .map(|y_start| (y_start + TILE_ROWS).min(height) - y_start)
+        .sum();
+    handle.add_total((todo * width) as u64);
+
+    let kernel = Kernel::detect();
+    let mapper_f32 = job.view.mapper_for::<f32>(width, height);
+    let mapper_f64 = job.view.mapper_for::<f64>(width, height);
+    let mapper_dd = job.view.mapper_for::<DoubleDouble>(width, height);
+
+    mt_for_each_band(height, TILE_ROWS, smooth, |y_start, y_end, tile| {
+        if handle.is_cancelled() || skip(y_start) {
+            return;
+        }
+        match job.precision {
+            Precision::Single => scalar_rows(&mapper_f32, iterations, width, y_start, y_end, tile),
+            Precision::Double => {
+                let points: Vec<Complex<f64>> = (y_start..y_end)
+                    .flat_map(|y| (0..width).map(move |x| mapper_f64.map(x, y)))
+                    .collect();
+                kernel.escape_times(&points, iterations, tile);
+            }
+            Precision::DoubleDouble | Precision::Perturbation => {
+                scalar_rows(&mapper_dd, iterations, width, y_start, y_end, tile)
+            }
+        }
+        handle.advance(tile.len() as u64);
+        finished(y_start, tile);
+    });
+}
+
+// ----------------------------------------------------------------------------
+fn scalar_rows<T: Real>(
+    mapper: &PixelMapper<T>,
+    iterations: u32,
+    width: u32,
+    y_start: u32,
+    y_end: u32,
+    tile: &mut [Option<f64>],
+) {
+    for y in y_start..y_end {
+        for x in 0..width {
+            tile[((y - y_start) * width + x) as usize] = escape_time(&mapper.map(x, y), iterations);
+        }
+    }
+}
+
+// ----------------------------------------------------------------------------
+// what is computed for each pixel by mt_pixel_map(), from the pixel in the complex plane at the precision of the job
+// a trait and not a closure : the same computation is done with f32, f64 or DoubleDouble
+pub trait PerPixel<R>: Sync {
+    fn pixel<T: Real>(&self, pixel: &Complex<T>) -> R;
+}
+
+// ----------------------------------------------------------------------------
+// out is width * height, out[y * width + x] = per_pixel.pixel(the point of x, y)
+// the tiles are bands of TILE_ROWS rows, dealt to the threads (mt_for_each_band())
+// the progress is reported and the cancel flag checked after each tile (see handle.rs)
+// Precision::Perturbation is done with DoubleDouble, as in mt_smooth_map_tiles()
+pub fn mt_pixel_map<R: Send, P: PerPixel<R>>(
+    view: &Viewport,
+    precision: Precision,
+    width: u32,
+    height: u32,
+    out: &mut [R],
+    handle: &RenderHandle,
+    per_pixel: &P,
+) {
+    match precision {
+        Precision::Single => {
+            pixel_map_with::<f32, R, P>(view, width, height, out, handle, per_pixel)
+        }
+        Precision::Double => {
+            pixel_map_with::<f64, R, P>(view, width, height, out, handle, per_pixel)
+        }
+        Precision::DoubleDouble | Precision::Perturbation => {
+            pixel_map_with::<DoubleDouble, R, P>(view, width, height, out, handle, per_pixel)
+        }
+    }
+}
+
+// ----------------------------------------------------------------------------
+fn pixel_map_with<T: Real, R: Send, P: PerPixel<R>>(
+    view: &Viewport,
+    width: u32,
+    height: u32,
+    out: &mut [R],
+    handle: &RenderHandle,
+    per_pixel: &P,
+) {
+    handle.add_total((width * height) as u64);
+    let mapper = view.mapper_for::<T>(width, height);
+    mt_for_each_band(height, TILE_ROWS, out, |y_start, y_end, tile| {
+        if handle.is_cancelled() {
+            return;
+        }
+        for y in y_start..y_end {
+            for x in 0..width {
+                tile[((y - y_start) * width + x) as usize] = per_pixel.pixel(&mapper.map(x, y));
+            }
+        }
+        handle.advance(tile.len() as u64);
+    });
+}
+
+// ----------------------------------------------------------------------------
+// the pixels which are not in the set : the ones with a smooth iteration and the ones which escape at the first iteration
+// bw and alpha color them apart from the set (see color.rs), the precision is the one of the rendering
+pub fn escaped_pixels(
+    view: &Viewport,
+    fractal: Fractal,
+    precision: Precision,
+    smooth: &[Option<f64>],
+    width: u32,
+    height: u32,
+) -> Vec<bool> {
+    let mut escaped = vec![false; smooth.len()];
+    mt_pixel_map(
+        view,
+        precision,
+        width,
+        height,
+        &mut escaped,
+        &RenderHandle::new(),
+        &EscapesAtOnce(fractal),
+    );
+    for (escaped, smooth) in escaped.iter_mut().zip(smooth) {
+        *escaped |= smooth.is_some();
+    }
+    escaped
+}
+
+// the PerPixel of escaped_pixels()
+struct EscapesAtOnce(Fractal);
+
+impl PerPixel<bool> for EscapesAtOnce {
+    // ----------------------------------------------------------------------------
+    fn pixel<T: Real>(&self, pixel: &Complex<T>) -> bool {
+        self.0.escapes_at_once(pixel)
+    }
+}
+
+// ----------------------------------------------------------------------------
+// the stripes of mt_build_mandelbrot() but the work done on each stripe is a parameter
+// render(y_start, y_end, stripe) is called once per thread, stripe is the part of image between the rows y_start and y_end
+// image can be a buffer of anything (rgb bytes, iteration counts...) as long as all the rows have the same length
+pub fn mt_for_each_stripe<T, F>(height: u32, image: &mut [T], render: F)
+where
+    T: Send,
+    F: Fn(u32, u32, &mut [T]) + Sync,
+{
+    let nthreads = threads();
+
+    let mut stripe_heights = vec![height / nthreads as u32; nthreads];
+    for i in 0..(height % nthreads as u32) {
+        stripe_heights[i as usize] += 1;
+    }
+
+    let row_len = image.len() / height.max(1) as usize;
+    let render = &render;
+    let mut y_start = 0;
+    crossbeam::thread::scope(|my_scope| {
+        let mut remaining = image;
+
+        for stripe_height in stripe_heights {
+            let (stripe, rest) = remaining.split_at_mut(stripe_height as usize * row_len);
+            remaining = rest;
+
+            let y_end = y_start + stripe_height;
+            my_scope.spawn(move |_| render(y_start, y_end, stripe));
+            y_start = y_end;
+        }
+    })
+    .unwrap();
+}
+
+// ----------------------------------------------------------------------------
+// the image is cut in bands of band_height rows (the last one can be smaller) and the bands are dealt to the threads like cards
+// a band never straddles 2 threads, this is what the progressive rendering needs (see progressive.rs)
+// small bands also balance the load and give regular progress reports (see handle.rs)
+// render(y_start, y_end, band) is called once per band
+pub fn mt_for_each_band<T, F>(height: u32, band_height: u32, image: &mut [T], render: F)
+where
+    T: Send,
+    F: Fn(u32, u32, &mut [T]) + Sync,
+{
+    let nthreads = threads();
+
+    let row_len = image.len() / height.max(1) as usize;
+    let band_height = band_height.max(1);
+    let mut hands: Vec<Vec<(u32, &mut [T])>> = (0..nthreads).map(|_| Vec::new()).collect();
+    for (i, band) in image
+        .chunks_mut((band_height as usize * row_len).max(1))
+        .enumerate()
+    {
+        hands[i % nthreads].push((i as u32 * band_height, band));
+    }
+
+    let render = &render;
+    crossbeam::thread::scope(|my_scope| {
+        for hand in hands {
+            my_scope.spawn(move |_| {
+                for (y_start, band) in hand {
+                    let y_end = (y_start + band_height).min(height);
+                    render(y_start, y_end, band);
+                }
+            });
+        }
+    })
+    .unwrap();
+}
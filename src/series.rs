@@ -0,0 +1,114 @@
+// series approximation for the deep zooms (see deep.rs)
+// with perturbation, a pixel c = C + dc iterates d[n + 1] = 2 Z[n] d[n] + d[n]^2 + dc
+// while d[n] stays small, d[n] is a polynomial in dc (the map is analytic so the bivariate series in dc.re, dc.im is a series in the complex dc) :
+//      d[n] = A1[n] dc + A2[n] dc^2 + A3[n] dc^3 + ...
+//      A1[n + 1] = 2 Z[n] A1[n] + 1
+//      Ak[n + 1] = 2 Z[n] Ak[n] + sum(Ai[n] Aj[n], i + j = k)
+// the coefficients only depend on the reference orbit : they are iterated once, then every pixel starts at d[N] = sum(Ak[N] dc^k) and skips N iterations
+// at deep zooms Ak grows like (1 / pixel size)^k and overflows a f64, so dc is divided by radius (the distance to the farthest pixel) :
+//      u = dc / radius, d[n] = sum(Bk[n] u^k) with Bk = Ak radius^k, the Bk stay of the size of d
+// validity : a few probe pixels are iterated exactly (perturbation) next to the coefficients
+// N is the last iteration where the series agrees with all the probes within SERIES_TOLERANCE
+// the probes also stop the series when they escape or glitch (nothing is skipped past those events)
+
+use crate::deep::GLITCH_TOLERANCE;
+use num_complex::Complex;
+
+// relative error between the series and the exact difference of a probe
+const SERIES_TOLERANCE: f64 = 1e-9;
+
+#[derive(Debug, Clone)]
+pub struct Series {
+    // the iterations skipped by every pixel
+    pub skipped: usize,
+    radius: f64,
+    // Bk[skipped], k = 1..terms
+    coefficients: Vec<Complex<f64>>,
+}
+
+impl Series {
+    // ----------------------------------------------------------------------------
+    // nothing skipped, d[0] = 0
+    pub fn none() -> Self {
+        Series {
+            skipped: 0,
+            radius: 1.0,
+            coefficients: Vec::new(),
+        }
+    }
+
+    // ----------------------------------------------------------------------------
+    // orbit is the reference orbit, probes are the dc of a few pixels (the farthest ones at least)
+    pub fn compute(
+        orbit: &[Complex<f64>],
+        probes: &[Complex<f64>],
+        terms: usize,
+        iterations: u32,
+    ) -> Self {
+        let radius = probes.iter().map(|dc| dc.norm()).fold(0.0, f64::max);
+        if terms == 0 || radius == 0.0 {
+            return Series::none();
+        }
+        let units: Vec<Complex<f64>> = probes.iter().map(|dc| dc / radius).collect();
+
+        let mut series = Series {
+            skipped: 0,
+            radius,
+            coefficients: vec![Complex::new(0.0, 0.0); terms],
+        };
+        let mut exact = vec![Complex::new(0.0, 0.0); probes.len()];
+        let last = (iterations as usize).min(orbit.len().saturating_sub(1));
+
+        for n in 0..last {
+            let two_z = 2.0 * orbit[n];
+            let b = &series.coefficients;
+            let next: Vec<Complex<f64>> = (0..terms)
+                .map(|k| {
+                    // b[i] is B(i + 1), the products Bi Bj with i + j = k + 1
+                    let products: Complex<f64> = (0..k).map(|i| b[i] * b[k - 1 - i]).sum();
+                    let constant = if k == 0 { radius } else { 0.0 };
+                    two_z * b[k] + products + constant
+                })
+                .collect();
+            if !next.iter().all(|b| b.re.is_finite() && b.im.is_finite()) {
+                break;
+            }
+
+            let reference = orbit[n + 1];
+            let mut valid = true;
+            for ((d, dc), u) in exact.iter_mut().zip(probes).zip(&units) {
+                *d = two_z * *d + *d * *d + dc;
+                let z = reference + *d;
+                if z.norm_sqr() > 4.0 || z.norm_sqr() < GLITCH_TOLERANCE * reference.norm_sqr() {
+                    valid = false;
+                    break;
+                }
+                let error = (evaluate(&next, *u) - *d).norm();
+                if error > SERIES_TOLERANCE * d.norm() {
+                    valid = false;
+                    break;
+                }
+            }
+            if !valid {
+                break;
+            }
+            series.coefficients = next;
+            series.skipped = n + 1;
+        }
+        series
+    }
+
+    // ----------------------------------------------------------------------------
+    // d[skipped] for the pixel dc
+    pub fn evaluate(&self, dc: Complex<f64>) -> Complex<f64> {
+        evaluate(&self.coefficients, dc / self.radius)
+    }
+}
+
+// ----------------------------------------------------------------------------
+// sum(b[k - 1] u^k), Horner
+fn evaluate(b: &[Complex<f64>], u: Complex<f64>) -> Complex<f64> {
+    b.iter()
+        .rev()
+        .fold(Complex::new(0.0, 0.0), |acc, bk| (acc + bk) * u)
+}
@@ -0,0 +1,349 @@
+// tile server : mandel serve --port 8080, then open http://localhost:8080/ in a browser
+// it listens on 127.0.0.1 only : there is no authentication and any tile request can start a long rendering
+// --bind 0.0.0.0 (or the address of one interface) opens it to the network, on purpose
+// nothing is rendered in advance, the tiles are rendered when the browser asks for them
+//      GET /                               => the viewer (viewer.html, bundled in the binary)
+//      GET /{formula}/{z}/{x}/{y}.png      => the tile (x, y) of level z of the pyramid of the formula (see pyramid.rs)
+// the tiles go through 2 LRU caches :
+//      - memory : the png bytes of the last --memory tiles (1024 by default)
+//      - disk : the last --disk MB of tiles (256 by default) in the --cache directory, as formula/z/x/y.png
+// the disk cache survives a restart : the files already there are scanned at start, the oldest one is evicted first
+// the header X-Cache tells where a tile comes from (memory, disk or rendered)
+// --threads requests are handled at the same time (4 by default), each tile is rendered with all the cores (see render.rs)
+// the caches are locked while they are read or updated, never while a tile is rendered
+
+use crate::handle::RenderHandle;
+use crate::output::encode_image;
+use crate::pyramid::{Pyramid, TILE_SIZE, tile_path};
+use crate::view::Viewport;
+use lru::LruCache;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::net::{IpAddr, SocketAddr};
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::{Instant, SystemTime};
+use tiny_http::{Header, Method, Request, Response, Server};
+
+// the tiles are addressed with u32, 2^31 tiles per side at the last level (a zoom of 2e9)
+pub const MAX_LEVEL: u32 = 31;
+
+const VIEWER: &str = include_str!("viewer.html");
+
+// the bytes of a png file, shared by the memory cache and the responses
+pub type Png = Arc<Vec<u8>>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Formula {
+    Mandelbrot,
+}
+
+impl Formula {
+    // ----------------------------------------------------------------------------
+    // level 0 is the whole set
+    pub fn pyramid(self, iterations: u32) -> Pyramid {
+        match self {
+            Formula::Mandelbrot => Pyramid {
+                view: Viewport::default(),
+                levels: MAX_LEVEL + 1,
+                iterations,
+            },
+        }
+    }
+}
+
+impl FromStr for Formula {
+    type Err = String;
+
+    // ----------------------------------------------------------------------------
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "mandelbrot" => Ok(Formula::Mandelbrot),
+            _ => Err(format!("unknown formula {s}")),
+        }
+    }
+}
+
+impl fmt::Display for Formula {
+    // ----------------------------------------------------------------------------
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Formula::Mandelbrot => "mandelbrot",
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TileKey {
+    pub formula: Formula,
+    pub z: u32,
+    pub x: u32,
+    pub y: u32,
+}
+
+impl TileKey {
+    // ----------------------------------------------------------------------------
+    // /mandelbrot/3/5/2.png, None if it is not a tile of the pyramids
+    pub fn from_url(url: &str) -> Option<Self> {
+        let path = url.split('?').next()?;
+        let mut parts = path.strip_prefix('/')?.split('/');
+        let formula = parts.next()?.parse().ok()?;
+        let z: u32 = parts.next()?.parse().ok()?;
+        let x: u32 = parts.next()?.parse().ok()?;
+        let y: u32 = parts.next()?.strip_suffix(".png")?.parse().ok()?;
+        let key = TileKey { formula, z, x, y };
+        (parts.next().is_none() && z <= MAX_LEVEL && x < (1 << z) && y < (1 << z)).then_some(key)
+    }
+
+    // ----------------------------------------------------------------------------
+    fn path(&self, directory: &Path) -> PathBuf {
+        tile_path(
+            &directory.join(self.formula.to_string()),
+            self.z,
+            self.x,
+            self.y,
+        )
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ServeOptions {
+    pub bind: IpAddr,
+    pub port: u16,
+    pub cache: PathBuf,
+    pub memory_tiles: usize,
+    pub disk_mb: u64,
+    pub iterations: u32,
+    pub threads: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Source {
+    Memory,
+    Disk,
+    Rendered,
+}
+
+impl fmt::Display for Source {
+    // ----------------------------------------------------------------------------
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Source::Memory => "memory",
+            Source::Disk => "disk",
+            Source::Rendered => "rendered",
+        })
+    }
+}
+
+// the files of the disk cache, the least recently used first, with their size
+struct DiskCache {
+    directory: PathBuf,
+    limit: u64,
+    size: u64,
+    files: LruCache<TileKey, u64>,
+}
+
+impl DiskCache {
+    // ----------------------------------------------------------------------------
+    // the tiles already in the directory, the least recently modified are the least recently used
+    fn open(directory: &Path, limit: u64) -> Result<Self, Box<dyn Error>> {
+        fs::create_dir_all(directory)?;
+        let mut found: Vec<(SystemTime, TileKey, u64)> = Vec::new();
+        scan(directory, directory, &mut found)?;
+        found.sort_by_key(|&(modified, _, _)| modified);
+
+        let mut cache = DiskCache {
+            directory: directory.to_path_buf(),
+            limit,
+            size: 0,
+            files: LruCache::unbounded(),
+        };
+        for (_, key, size) in found {
+            cache.files.put(key, size);
+            cache.size += size;
+        }
+        cache.evict();
+        Ok(cache)
+    }
+
+    // ----------------------------------------------------------------------------
+    // a file which cannot be read any more is forgotten
+    fn get(&mut self, key: &TileKey) -> Option<Vec<u8>> {
+        self.files.get(key)?;
+        match fs::read(key.path(&self.directory)) {
+            Ok(bytes) => Some(bytes),
+            Err(_) => {
+                if let Some(size) = self.files.pop(key) {
+                    self.size -= size;
+                }
+                None
+            }
+        }
+    }
+
+    // ----------------------------------------------------------------------------
+    // written to a .tmp file then renamed, a crash never leaves half a tile in the cache
+    fn put(&mut self, key: TileKey, bytes: &[u8]) -> std::io::Result<()> {
+        let path = key.path(&self.directory);
+        fs::create_dir_all(path.parent().unwrap())?;
+        let tmp = path.with_extension("png.tmp");
+        fs::write(&tmp, bytes)?;
+        fs::rename(&tmp, &path)?;
+        if let Some(size) = self.files.put(key, bytes.len() as u64) {
+            self.size -= size;
+        }
+        self.size += bytes.len() as u64;
+        self.evict();
+        Ok(())
+    }
+
+    // ----------------------------------------------------------------------------
+    fn evict(&mut self) {
+        while self.size > self.limit {
+            let Some((key, size)) = self.files.pop_lru() else {
+                break;
+            };
+            let _ = fs::remove_file(key.path(&self.directory));
+            self.size -= size;
+        }
+    }
+}
+
+// ----------------------------------------------------------------------------
+// the files formula/z/x/y.png below directory
+fn scan(
+    root: &Path,
+    directory: &Path,
+    found: &mut Vec<(SystemTime, TileKey, u64)>,
+) -> std::io::Result<()> {
+    for entry in fs::read_dir(directory)? {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+        if metadata.is_dir() {
+            scan(root, &entry.path(), found)?;
+            continue;
+        }
+        let path = entry.path();
+        let url = path
+            .strip_prefix(root)
+            .ok()
+            .and_then(|relative| relative.to_str())
+            .map(|relative| format!("/{}", relative.replace('\\', "/")));
+        if let Some(key) = url.as_deref().and_then(TileKey::from_url) {
+            found.push((metadata.modified()?, key, metadata.len()));
+        }
+    }
+    Ok(())
+}
+
+pub struct TileServer {
+    iterations: u32,
+    memory: Mutex<LruCache<TileKey, Png>>,
+    disk: Mutex<DiskCache>,
+}
+
+impl TileServer {
+    // ----------------------------------------------------------------------------
+    pub fn new(options: &ServeOptions) -> Result<Self, Box<dyn Error>> {
+        let capacity =
+            NonZeroUsize::new(options.memory_tiles).ok_or("--memory must be at least 1")?;
+        Ok(TileServer {
+            iterations: options.iterations,
+            memory: Mutex::new(LruCache::new(capacity)),
+            disk: Mutex::new(DiskCache::open(&options.cache, options.disk_mb << 20)?),
+        })
+    }
+
+    // ----------------------------------------------------------------------------
+    // the png file of the tile : from memory, from the disk, or rendered (and put in both caches)
+    pub fn tile(&self, key: TileKey) -> Result<(Png, Source), Box<dyn Error>> {
+        if let Some(bytes) = self.memory.lock().unwrap().get(&key) {
+            return Ok((Arc::clone(bytes), Source::Memory));
+        }
+        let on_disk = self.disk.lock().unwrap().get(&key);
+        if let Some(bytes) = on_disk {
+            let bytes = Arc::new(bytes);
+            self.memory.lock().unwrap().put(key, Arc::clone(&bytes));
+            return Ok((bytes, Source::Disk));
+        }
+
+        let mut image = vec![0u8; (TILE_SIZE * TILE_SIZE * 3) as usize];
+        key.formula.pyramid(self.iterations).render_tile(
+            key.z,
+            key.x,
+            key.y,
+            &mut image,
+            &RenderHandle::new(),
+        );
+        let bytes = Arc::new(encode_image(&image, TILE_SIZE, TILE_SIZE)?);
+        if let Err(e) = self.disk.lock().unwrap().put(key, &bytes) {
+            eprintln!("Cannot cache the tile {key:?} : {e}");
+        }
+        self.memory.lock().unwrap().put(key, Arc::clone(&bytes));
+        Ok((bytes, Source::Rendered))
+    }
+
+    // ----------------------------------------------------------------------------
+    // one request, one line on stdout
+    fn respond(&self, request: Request) {
+        let start = Instant::now();
+        let url = request.url().to_string();
+        let header = |name: &str, value: &str| Header::from_bytes(name, value).unwrap();
+
+        let (status, detail, result) = if *request.method() != Method::Get {
+            (405, "-".to_string(), request.respond(Response::empty(405)))
+        } else if url == "/" || url.starts_with("/?") || url == "/index.html" {
+            let response = Response::from_string(VIEWER)
+                .with_header(header("Content-Type", "text/html; charset=utf-8"));
+            (200, "-".to_string(), request.respond(response))
+        } else if let Some(key) = TileKey::from_url(&url) {
+            match self.tile(key) {
+                Ok((bytes, source)) => {
+                    let response = Response::from_data(bytes.as_slice())
+                        .with_header(header("Content-Type", "image/png"))
+                        .with_header(header("Cache-Control", "public, max-age=86400"))
+                        .with_header(header("X-Cache", &source.to_string()));
+                    (200, source.to_string(), request.respond(response))
+                }
+                Err(e) => (
+                    500,
+                    e.to_string(),
+                    request.respond(Response::from_string(e.to_string()).with_status_code(500)),
+                ),
+            }
+        } else {
+            (
+                404,
+                "-".to_string(),
+                request.respond(Response::from_string("not found").with_status_code(404)),
+            )
+        };
+        if let Err(e) = result {
+            eprintln!("GET {url} : cannot answer : {e}");
+        }
+        println!("{status} {url} {detail} {} ms", start.elapsed().as_millis());
+    }
+}
+
+// ----------------------------------------------------------------------------
+// serves until the process is stopped
+pub fn run(options: &ServeOptions) -> Result<(), Box<dyn Error>> {
+    let tiles = TileServer::new(options)?;
+    let address = SocketAddr::new(options.bind, options.port);
+    let server = Server::http(address).map_err(|e| e.to_string())?;
+    println!("Serving on http://{address}/");
+
+    std::thread::scope(|scope| {
+        for _ in 0..options.threads.max(1) {
+            scope.spawn(|| {
+                while let Ok(request) = server.recv() {
+                    tiles.respond(request);
+                }
+            });
+        }
+    });
+    Ok(())
+}
@@ -0,0 +1,231 @@
+// SIMD escape time : 4 pixels are iterated together, one per lane
+// the instructions are chosen at run time (Kernel::detect()) : AVX (4 f64 per register), SSE2 (2 x 2 f64) or the scalar escape_time()
+// each lane does exactly the operations of escape_time() (see render.rs), in the same order and without fused multiply-add
+// so the iteration counts, the values of z and therefore the colors are bit-identical to the scalar path
+// a lane which escapes is recorded (iteration and z) and masked, the group stops when all its lanes escaped
+// render_zone_simd() is render_zone() with this kernel, compare() in main.rs shows the speedup on the view of main_10
+
+use crate::render::{escape_time, palette, smooth_from_escape};
+use num_complex::Complex;
+use std::fmt;
+
+pub const LANES: usize = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kernel {
+    Scalar,
+    Sse2,
+    Avx,
+}
+
+impl Kernel {
+    // ----------------------------------------------------------------------------
+    // the best set of instructions of this CPU
+    pub fn detect() -> Self {
+        #[cfg(target_arch = "x86_64")]
+        {
+            if is_x86_feature_detected!("avx") {
+                return Kernel::Avx;
+            }
+            if is_x86_feature_detected!("sse2") {
+                return Kernel::Sse2;
+            }
+        }
+        Kernel::Scalar
+    }
+
+    // ----------------------------------------------------------------------------
+    // the smooth iterations of LANES points (None for the set, as smooth_iteration())
+    pub fn escape_time_lanes(
+        self,
+        re: [f64; LANES],
+        im: [f64; LANES],
+        iterations: u32,
+    ) -> [Option<f64>; LANES] {
+        let (counts, z_re, z_im) = match self {
+            // SAFETY : detect() only returns Avx or Sse2 when the CPU has these instructions
+            #[cfg(target_arch = "x86_64")]
+            Kernel::Avx => unsafe { x86::iterate_avx(&re, &im, iterations) },
+            #[cfg(target_arch = "x86_64")]
+            Kernel::Sse2 => unsafe { x86::iterate_sse2(&re, &im, iterations) },
+            _ => {
+                return std::array::from_fn(|lane| {
+                    escape_time(&Complex::new(re[lane], im[lane]), iterations)
+                });
+            }
+        };
+        std::array::from_fn(|lane| {
+            smooth_from_escape(counts[lane], Complex::new(z_re[lane], z_im[lane]))
+        })
+    }
+
+    // ----------------------------------------------------------------------------
+    // any number of points, the last group is padded with copies of its first point
+    pub fn escape_times(
+        self,
+        points: &[Complex<f64>],
+        iterations: u32,
+        smooth: &mut [Option<f64>],
+    ) {
+        for (group, out) in points.chunks(LANES).zip(smooth.chunks_mut(LANES)) {
+            let re = std::array::from_fn(|lane| group.get(lane).unwrap_or(&group[0]).re);
+            let im = std::array::from_fn(|lane| group.get(lane).unwrap_or(&group[0]).im);
+            let result = self.escape_time_lanes(re, im, iterations);
+            out.copy_from_slice(&result[..out.len()]);
+        }
+    }
+}
+
+impl fmt::Display for Kernel {
+    // ----------------------------------------------------------------------------
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Kernel::Scalar => "scalar",
+            Kernel::Sse2 => "sse2",
+            Kernel::Avx => "avx",
+        })
+    }
+}
+
+// ----------------------------------------------------------------------------
+// same job and same bytes than render_zone() with Complex<f64>, a row at a time
+pub fn render_zone_simd(
+    kernel: Kernel,
+    from: &Complex<f64>,
+    to: &Complex<f64>,
+    width: u32,
+    height: u32,
+    iterations: u32,
+    image: &mut [u8],
+) {
+    let size = to - from;
+    let mut points = vec![Complex::new(0.0, 0.0); width as usize];
+    let mut smooth = vec![None; width as usize];
+    for y in 0..height {
+        for (x, c) in points.iter_mut().enumerate() {
+            *c = from
+                + Complex::new(
+                    x as f64 * size.re / width as f64,
+                    y as f64 * size.im / height as f64,
+                );
+        }
+        kernel.escape_times(&points, iterations, &mut smooth);
+
+        let row = &mut image[(y * width * 3) as usize..((y + 1) * width * 3) as usize];
+        for (pixel, smooth_i) in row.chunks_exact_mut(3).zip(&smooth) {
+            let (r, g, b) = match smooth_i {
+                Some(smooth_i) => palette(*smooth_i, 0.0),
+                None => (0, 0, 0),
+            };
+            pixel.copy_from_slice(&[r, g, b]);
+        }
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+mod x86 {
+    use super::LANES;
+    use std::arch::x86_64::*;
+
+    // iteration of escape (0 if never) and z at that time, for each lane
+    type Escapes = ([u32; LANES], [f64; LANES], [f64; LANES]);
+
+    // ----------------------------------------------------------------------------
+    #[target_feature(enable = "avx")]
+    pub unsafe fn iterate_avx(re: &[f64; LANES], im: &[f64; LANES], iterations: u32) -> Escapes {
+        let mut counts = [0u32; LANES];
+        let mut z_re = [0.0; LANES];
+        let mut z_im = [0.0; LANES];
+
+        let c_re = unsafe { _mm256_loadu_pd(re.as_ptr()) };
+        let c_im = unsafe { _mm256_loadu_pd(im.as_ptr()) };
+        let four = _mm256_set1_pd(4.0);
+        let mut zr = _mm256_setzero_pd();
+        let mut zi = _mm256_setzero_pd();
+        // all ones in the lanes which escaped
+        let mut escaped = _mm256_setzero_pd();
+
+        for t in 0..iterations {
+            // (zr * zr - zi * zi) + c_re, (zr * zi + zi * zr) + c_im : the order of escape_time()
+            let next_re = _mm256_add_pd(
+                _mm256_sub_pd(_mm256_mul_pd(zr, zr), _mm256_mul_pd(zi, zi)),
+                c_re,
+            );
+            let next_im = _mm256_add_pd(
+                _mm256_add_pd(_mm256_mul_pd(zr, zi), _mm256_mul_pd(zi, zr)),
+                c_im,
+            );
+            zr = next_re;
+            zi = next_im;
+
+            let norm = _mm256_add_pd(_mm256_mul_pd(zr, zr), _mm256_mul_pd(zi, zi));
+            let out = _mm256_cmp_pd::<_CMP_GT_OQ>(norm, four);
+            let newly = _mm256_movemask_pd(_mm256_andnot_pd(escaped, out));
+            if newly != 0 {
+                let (mut lanes_re, mut lanes_im) = ([0.0; LANES], [0.0; LANES]);
+                unsafe {
+                    _mm256_storeu_pd(lanes_re.as_mut_ptr(), zr);
+                    _mm256_storeu_pd(lanes_im.as_mut_ptr(), zi);
+                }
+                for lane in (0..LANES).filter(|lane| newly & (1 << lane) != 0) {
+                    counts[lane] = t;
+                    z_re[lane] = lanes_re[lane];
+                    z_im[lane] = lanes_im[lane];
+                }
+                escaped = _mm256_or_pd(escaped, out);
+                if _mm256_movemask_pd(escaped) == (1 << LANES) - 1 {
+                    break;
+                }
+            }
+        }
+        (counts, z_re, z_im)
+    }
+
+    // ----------------------------------------------------------------------------
+    // 2 registers of 2 lanes
+    #[target_feature(enable = "sse2")]
+    pub unsafe fn iterate_sse2(re: &[f64; LANES], im: &[f64; LANES], iterations: u32) -> Escapes {
+        let mut counts = [0u32; LANES];
+        let mut z_re = [0.0; LANES];
+        let mut z_im = [0.0; LANES];
+
+        let c_re = unsafe { [_mm_loadu_pd(re.as_ptr()), _mm_loadu_pd(re[2..].as_ptr())] };
+        let c_im = unsafe { [_mm_loadu_pd(im.as_ptr()), _mm_loadu_pd(im[2..].as_ptr())] };
+        let four = _mm_set1_pd(4.0);
+        let mut zr = [_mm_setzero_pd(); 2];
+        let mut zi = [_mm_setzero_pd(); 2];
+        let mut escaped = [_mm_setzero_pd(); 2];
+
+        for t in 0..iterations {
+            let mut all = true;
+            for half in 0..2 {
+                let (r, i) = (zr[half], zi[half]);
+                zr[half] = _mm_add_pd(_mm_sub_pd(_mm_mul_pd(r, r), _mm_mul_pd(i, i)), c_re[half]);
+                zi[half] = _mm_add_pd(_mm_add_pd(_mm_mul_pd(r, i), _mm_mul_pd(i, r)), c_im[half]);
+
+                let (r, i) = (zr[half], zi[half]);
+                let norm = _mm_add_pd(_mm_mul_pd(r, r), _mm_mul_pd(i, i));
+                let out = _mm_cmpgt_pd(norm, four);
+                let newly = _mm_movemask_pd(_mm_andnot_pd(escaped[half], out));
+                if newly != 0 {
+                    let (mut lanes_re, mut lanes_im) = ([0.0; 2], [0.0; 2]);
+                    unsafe {
+                        _mm_storeu_pd(lanes_re.as_mut_ptr(), r);
+                        _mm_storeu_pd(lanes_im.as_mut_ptr(), i);
+                    }
+                    for lane in (0..2).filter(|lane| newly & (1 << lane) != 0) {
+                        counts[2 * half + lane] = t;
+                        z_re[2 * half + lane] = lanes_re[lane];
+                        z_im[2 * half + lane] = lanes_im[lane];
+                    }
+                    escaped[half] = _mm_or_pd(escaped[half], out);
+                }
+                all &= _mm_movemask_pd(escaped[half]) == 0b11;
+            }
+            if all {
+                break;
+            }
+        }
+        (counts, z_re, z_im)
+    }
+}
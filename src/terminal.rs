@@ -0,0 +1,182 @@
+// the rendered image printed in the terminal, for the sessions over ssh where the png files cannot be opened
+// 2 encodings :
+//      - half blocks : one character per column and per 2 rows, the upper half block ▀ in the color of the upper pixel
+//        on the background color of the lower one (24 bits ANSI colors, most terminals understand them)
+//      - sixel : the real pixels (xterm -ti vt340, mlterm, foot, wezterm...), 256 colors at most
+//        the image is reduced to the 6 x 6 x 6 color cube, the black interior stays black
+// the image fits the terminal by default (see preview_size()), one line is kept for the prompt
+
+use std::fmt::Write;
+use std::str::FromStr;
+
+// when the size is unknown (not a terminal) or the terminal does not tell its size in pixels
+const DEFAULT_COLUMNS: u16 = 80;
+const DEFAULT_ROWS: u16 = 24;
+const CELL_WIDTH: u32 = 8;
+const CELL_HEIGHT: u32 = 16;
+
+// levels of each component in the sixel palette
+const CUBE: u32 = 6;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TerminalFormat {
+    HalfBlocks,
+    Sixel,
+}
+
+impl FromStr for TerminalFormat {
+    type Err = String;
+
+    // ----------------------------------------------------------------------------
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "blocks" => Ok(TerminalFormat::HalfBlocks),
+            "sixel" => Ok(TerminalFormat::Sixel),
+            _ => Err(format!("{s} is not blocks or sixel")),
+        }
+    }
+}
+
+impl TerminalFormat {
+    // ----------------------------------------------------------------------------
+    // the image in the escape sequences of the format, to print as it is
+    pub fn encode(self, image: &[u8], width: u32, height: u32) -> String {
+        match self {
+            TerminalFormat::HalfBlocks => half_blocks(image, width, height),
+            TerminalFormat::Sixel => sixel(image, width, height),
+        }
+    }
+
+    // ----------------------------------------------------------------------------
+    // the size of the image in pixels which fills the terminal
+    pub fn preview_size(self) -> (u32, u32) {
+        let size = crossterm::terminal::window_size().ok();
+        let (columns, rows) = match &size {
+            Some(size) if size.columns > 0 && size.rows > 1 => (size.columns, size.rows),
+            _ => (DEFAULT_COLUMNS, DEFAULT_ROWS),
+        };
+        let (columns, rows) = (columns as u32, rows as u32 - 1);
+        match self {
+            TerminalFormat::HalfBlocks => (columns, 2 * rows),
+            TerminalFormat::Sixel => match size {
+                Some(size) if size.width > 0 && size.height > 0 => {
+                    (size.width as u32, size.height as u32 * rows / (rows + 1))
+                }
+                _ => (columns * CELL_WIDTH, rows * CELL_HEIGHT),
+            },
+        }
+    }
+}
+
+// ----------------------------------------------------------------------------
+pub fn half_blocks(image: &[u8], width: u32, height: u32) -> String {
+    half_block_rows(image, width, height)
+        .into_iter()
+        .map(|row| row + "\n")
+        .collect()
+}
+
+// ----------------------------------------------------------------------------
+// one string per line of the terminal, each one ends with a reset of the colors (a full screen view moves the cursor itself, see explore.rs)
+// the escape sequences are only written when a color changes, an odd last row has the default background
+pub fn half_block_rows(image: &[u8], width: u32, height: u32) -> Vec<String> {
+    let pixel = |x: u32, y: u32| {
+        let i = ((y * width + x) * 3) as usize;
+        (image[i], image[i + 1], image[i + 2])
+    };
+
+    let mut rows = Vec::with_capacity(height.div_ceil(2) as usize);
+    for y in (0..height).step_by(2) {
+        let mut out = String::new();
+        let (mut fg, mut bg) = (None, None);
+        for x in 0..width {
+            let top = pixel(x, y);
+            if fg != Some(top) {
+                let _ = write!(out, "\x1b[38;2;{};{};{}m", top.0, top.1, top.2);
+                fg = Some(top);
+            }
+            if y + 1 < height {
+                let bottom = pixel(x, y + 1);
+                if bg != Some(bottom) {
+                    let _ = write!(out, "\x1b[48;2;{};{};{}m", bottom.0, bottom.1, bottom.2);
+                    bg = Some(bottom);
+                }
+            }
+            out.push('▀');
+        }
+        out.push_str("\x1b[0m");
+        rows.push(out);
+    }
+    rows
+}
+
+// ----------------------------------------------------------------------------
+// DCS q, the raster attributes, the palette, then bands of 6 rows : for each color of the band
+// one character per column whose 6 bits are the rows of this color, $ goes back to the start of the band, - to the next band
+// the repeated characters are run-length encoded (!count character)
+pub fn sixel(image: &[u8], width: u32, height: u32) -> String {
+    let level = |value: u8| (value as u32 * (CUBE - 1) + 127) / 255;
+    let colors: Vec<u8> = image
+        .chunks_exact(3)
+        .map(|rgb| (level(rgb[0]) * CUBE * CUBE + level(rgb[1]) * CUBE + level(rgb[2])) as u8)
+        .collect();
+
+    let mut out = format!("\x1bPq\"1;1;{width};{height}");
+    for color in 0..CUBE * CUBE * CUBE {
+        let percent = |component: u32| component * 100 / (CUBE - 1);
+        let _ = write!(
+            out,
+            "#{color};2;{};{};{}",
+            percent(color / (CUBE * CUBE)),
+            percent(color / CUBE % CUBE),
+            percent(color % CUBE)
+        );
+    }
+
+    let mut sixels = vec![0u8; width as usize];
+    for band in (0..height).step_by(6) {
+        let rows = band..(band + 6).min(height);
+        let mut used = [false; 256];
+        for y in rows.clone() {
+            for x in 0..width {
+                used[colors[(y * width + x) as usize] as usize] = true;
+            }
+        }
+
+        let mut first = true;
+        for color in (0..256).filter(|&color| used[color]) {
+            sixels.fill(0);
+            for y in rows.clone() {
+                for x in 0..width {
+                    if colors[(y * width + x) as usize] as usize == color {
+                        sixels[x as usize] |= 1 << (y - band);
+                    }
+                }
+            }
+            if !first {
+                out.push('$');
+            }
+            first = false;
+            let _ = write!(out, "#{color}");
+            run_length(&sixels, &mut out);
+        }
+        out.push('-');
+    }
+    out.push_str("\x1b\\");
+    out
+}
+
+// ----------------------------------------------------------------------------
+fn run_length(sixels: &[u8], out: &mut String) {
+    let mut i = 0;
+    while i < sixels.len() {
+        let count = sixels[i..].iter().take_while(|&&s| s == sixels[i]).count();
+        let character = (63 + sixels[i]) as char;
+        if count > 3 {
+            let _ = write!(out, "!{count}{character}");
+        } else {
+            (0..count).for_each(|_| out.push(character));
+        }
+        i += count;
+    }
+}
@@ -0,0 +1,405 @@
+// orbit traps : the color of a pixel comes from the points of its orbit instead of the iteration of its escape
+//      mandel render [view options] --trap point:0,0 [--trap-color distance | angle] [--trap-scale 10] --output traps.png
+// the traps (the numbers have defaults, "--trap cross" is the cross at 0,0) :
+//      - point:re,im
+//      - line:re,im,angle : the line through re,im, the angle in degrees (counterclockwise like --rotation), 0 is horizontal
+//      - cross:re,im : the horizontal and the vertical lines through re,im
+//      - circle:re,im,radius : the radius is 0 or more
+//      - image:texture.png,re,im,size : an 8 bits rgb png centered on re,im, size is its width in the complex plane (more than 0)
+// the orbit is followed up to its escape (or to the last iteration), the points of the set have a color too
+// the value given to the palette (palette_unit(), see render.rs) is scale * :
+//      - distance : the minimum distance of the orbit to the trap
+//      - angle : where the orbit came the closest, the angle around the point (in turns) or the position along the line
+// an image trap has no distance, the pixel takes the color of the texture where its orbit entered the image the first time
+// the orbits which never enter it are black (transparent with --alpha)
+
+use crate::handle::RenderHandle;
+use crate::output::load_image;
+use crate::real::{Precision, Real};
+use crate::render::{Fractal, PerPixel, mt_pixel_map, palette_unit};
+use crate::view::Viewport;
+use num_complex::Complex;
+use std::error::Error;
+use std::f64::consts::TAU;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TrapShape {
+    Point(Complex<f64>),
+    // a point of the line and its direction (a unit vector)
+    Line(Complex<f64>, Complex<f64>),
+    Cross(Complex<f64>),
+    Circle(Complex<f64>, f64),
+    Image {
+        path: String,
+        center: Complex<f64>,
+        size: f64,
+    },
+}
+
+impl FromStr for TrapShape {
+    type Err = String;
+
+    // ----------------------------------------------------------------------------
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, parameters) = s.split_once(':').unwrap_or((s, ""));
+        // the path of the image may contain commas, the numbers are the last ones
+        let (path, parameters) = match name {
+            "image" => {
+                let mut fields: Vec<&str> = parameters.rsplitn(4, ',').collect();
+                let path = fields.pop().unwrap_or_default();
+                fields.reverse();
+                (path, fields)
+            }
+            _ => (
+                "",
+                parameters.split(',').filter(|p| !p.is_empty()).collect(),
+            ),
+        };
+        let numbers = |defaults: &[f64]| -> Result<Vec<f64>, String> {
+            if parameters.len() > defaults.len() {
+                return Err(format!("{s} : too many numbers"));
+            }
+            let mut numbers = defaults.to_vec();
+            for (number, p) in numbers.iter_mut().zip(&parameters) {
+                *number = p
+                    .trim()
+                    .parse()
+                    .ok()
+                    .filter(|n: &f64| n.is_finite())
+                    .ok_or_else(|| format!("{s} : {p} is not a number"))?;
+            }
+            Ok(numbers)
+        };
+        match name {
+            "point" => numbers(&[0.0, 0.0]).map(|n| TrapShape::Point(Complex::new(n[0], n[1]))),
+            "line" => numbers(&[0.0, 0.0, 0.0]).map(|n| {
+                let direction = Complex::from_polar(1.0, n[2].to_radians());
+                TrapShape::Line(Complex::new(n[0], n[1]), direction)
+            }),
+            "cross" => numbers(&[0.0, 0.0]).map(|n| TrapShape::Cross(Complex::new(n[0], n[1]))),
+            "circle" => match numbers(&[0.0, 0.0, 1.0])? {
+                n if n[2] < 0.0 => Err(format!("{s} : the radius cannot be negative")),
+                n => Ok(TrapShape::Circle(Complex::new(n[0], n[1]), n[2])),
+            },
+            "image" if !path.is_empty() => match numbers(&[0.0, 0.0, 2.0])? {
+                n if n[2] <= 0.0 => Err(format!("{s} : the size must be positive")),
+                n => Ok(TrapShape::Image {
+                    path: path.to_string(),
+                    center: Complex::new(n[0], n[1]),
+                    size: n[2],
+                }),
+            },
+            "image" => Err("image:texture.png,re,im,size needs the png file".to_string()),
+            _ => Err(format!("{name} is not point, line, cross, circle or image")),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TrapValue {
+    #[default]
+    Distance,
+    Angle,
+}
+
+impl FromStr for TrapValue {
+    type Err = String;
+
+    // ----------------------------------------------------------------------------
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "distance" => Ok(TrapValue::Distance),
+            "angle" => Ok(TrapValue::Angle),
+            _ => Err(format!("{s} is not distance or angle")),
+        }
+    }
+}
+
+// the pixels of an image trap, between 0.0 and 1.0
+#[derive(Debug, Clone)]
+struct Texture {
+    pixels: Vec<[f64; 3]>,
+    width: u32,
+    height: u32,
+    // the corner at the top left and the size of a pixel, in the complex plane
+    corner: Complex<f64>,
+    pixel_size: f64,
+}
+
+impl Texture {
+    // ----------------------------------------------------------------------------
+    // None outside of the image
+    fn color(&self, z: Complex<f64>) -> Option<[f64; 3]> {
+        let x = (z.re - self.corner.re) / self.pixel_size;
+        let y = (self.corner.im - z.im) / self.pixel_size;
+        if x < 0.0 || y < 0.0 || x >= self.width as f64 || y >= self.height as f64 {
+            return None;
+        }
+        Some(self.pixels[y as usize * self.width as usize + x as usize])
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct OrbitTrap {
+    shape: TrapShape,
+    value: TrapValue,
+    scale: f64,
+    // the image of TrapShape::Image, loaded once
+    texture: Option<Texture>,
+}
+
+impl OrbitTrap {
+    // ----------------------------------------------------------------------------
+    // reads the png file of an image trap
+    pub fn new(shape: TrapShape, value: TrapValue, scale: f64) -> Result<Self, Box<dyn Error>> {
+        let texture = match &shape {
+            TrapShape::Image { path, center, size } => {
+                let (data, width, height) = load_image(path)?;
+                let pixel_size = size / width as f64;
+                Some(Texture {
+                    pixels: data
+                        .chunks_exact(3)
+                        .map(|p| {
+                            [
+                                p[0] as f64 / 255.0,
+                                p[1] as f64 / 255.0,
+                                p[2] as f64 / 255.0,
+                            ]
+                        })
+                        .collect(),
+                    width,
+                    height,
+                    corner: center + Complex::new(-0.5 * size, 0.5 * height as f64 * pixel_size),
+                    pixel_size,
+                })
+            }
+            _ => None,
+        };
+        Ok(OrbitTrap {
+            shape,
+            value,
+            scale,
+            texture,
+        })
+    }
+
+    // ----------------------------------------------------------------------------
+    // the distance of z to the trap, and where z is relatively to it (see TrapValue::Angle)
+    fn measure(&self, z: Complex<f64>) -> (f64, f64) {
+        let turns = |d: Complex<f64>| d.arg().rem_euclid(TAU) / TAU;
+        match self.shape {
+            TrapShape::Point(p) => ((z - p).norm(), turns(z - p)),
+            TrapShape::Line(p, direction) => {
+                // z - p in the frame of the line : re along it, im across it
+                let d = (z - p) * direction.conj();
+                (d.im.abs(), d.re)
+            }
+            TrapShape::Cross(p) => {
+                let d = z - p;
+                (d.re.abs().min(d.im.abs()), turns(d))
+            }
+            TrapShape::Circle(p, radius) => (((z - p).norm() - radius).abs(), turns(z - p)),
+            TrapShape::Image { .. } => (f64::INFINITY, 0.0),
+        }
+    }
+
+    // ----------------------------------------------------------------------------
+    // the color of a pixel, None when the orbit missed the image of an image trap
+    pub fn color<T: Real>(
+        &self,
+        fractal: &Fractal,
+        pixel: &Complex<T>,
+        iterations: u32,
+    ) -> Option<[f64; 3]> {
+        if let Some(texture) = &self.texture {
+            let mut hit = None;
+            fractal.visit_orbit(pixel, iterations, |z| {
+                if hit.is_none() {
+                    hit = texture.color(z);
+                }
+            });
+            return hit;
+        }
+
+        let (mut distance, mut position) = (f64::INFINITY, 0.0);
+        fractal.visit_orbit(pixel, iterations, |z| {
+            let (d, p) = self.measure(z);
+            if d < distance {
+                (distance, position) = (d, p);
+            }
+        });
+        let value = match self.value {
+            TrapValue::Distance => distance,
+            TrapValue::Angle => position,
+        };
+        Some(palette_unit(self.scale * value, 0.0))
+    }
+}
+
+// what mt_trap_colors() computes
+#[derive(Debug, Clone, Copy)]
+pub struct TrapJob<'a> {
+    pub view: &'a Viewport,
+    pub fractal: Fractal,
+    pub precision: Precision,
+    pub iterations: u32,
+    pub width: u32,
+    pub height: u32,
+    pub trap: &'a OrbitTrap,
+}
+
+// ----------------------------------------------------------------------------
+// colors is width * height (see mt_pixel_map())
+pub fn mt_trap_colors(job: &TrapJob, colors: &mut [Option<[f64; 3]>], handle: &RenderHandle) {
+    mt_pixel_map(
+        job.view,
+        job.precision,
+        job.width,
+        job.height,
+        colors,
+        handle,
+        job,
+    );
+}
+
+impl PerPixel<Option<[f64; 3]>> for TrapJob<'_> {
+    // ----------------------------------------------------------------------------
+    fn pixel<T: Real>(&self, pixel: &Complex<T>) -> Option<[f64; 3]> {
+        self.trap.color(&self.fractal, pixel, self.iterations)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::output::save_image;
+
+    // ----------------------------------------------------------------------------
+    fn trap(shape: &str) -> OrbitTrap {
+        OrbitTrap::new(shape.parse().unwrap(), TrapValue::Distance, 1.0).unwrap()
+    }
+
+    // ----------------------------------------------------------------------------
+    fn assert_measure(trap: &OrbitTrap, z: Complex<f64>, distance: f64, position: f64) {
+        let (d, p) = trap.measure(z);
+        assert!(
+            (d - distance).abs() < 1e-12 && (p - position).abs() < 1e-12,
+            "{:?} at {z} : ({d}, {p}) instead of ({distance}, {position})",
+            trap.shape
+        );
+    }
+
+    // ----------------------------------------------------------------------------
+    #[test]
+    fn shapes() {
+        let c = Complex::new;
+        assert_eq!("point".parse(), Ok(TrapShape::Point(c(0.0, 0.0))));
+        assert_eq!("point:1,-2".parse(), Ok(TrapShape::Point(c(1.0, -2.0))));
+        assert_eq!(
+            "line:0,1".parse(),
+            Ok(TrapShape::Line(c(0.0, 1.0), c(1.0, 0.0)))
+        );
+        assert_eq!("cross:0.5,0".parse(), Ok(TrapShape::Cross(c(0.5, 0.0))));
+        assert_eq!("circle".parse(), Ok(TrapShape::Circle(c(0.0, 0.0), 1.0)));
+        assert_eq!(
+            "circle:0,0,0".parse(),
+            Ok(TrapShape::Circle(c(0.0, 0.0), 0.0))
+        );
+        // the commas of the path stay in it
+        assert_eq!(
+            "image:a,b.png,0,0,2".parse(),
+            Ok(TrapShape::Image {
+                path: "a,b.png".to_string(),
+                center: c(0.0, 0.0),
+                size: 2.0
+            })
+        );
+        assert_eq!(
+            "image:a.png".parse(),
+            Ok(TrapShape::Image {
+                path: "a.png".to_string(),
+                center: c(0.0, 0.0),
+                size: 2.0
+            })
+        );
+        let TrapShape::Line(_, direction) = "line:0,0,90".parse().unwrap() else {
+            panic!("not a line");
+        };
+        assert!((direction - c(0.0, 1.0)).norm() < 1e-15);
+    }
+
+    // ----------------------------------------------------------------------------
+    #[test]
+    fn bad_shapes() {
+        for s in [
+            "square",
+            "point:1,2,3",
+            "point:a,0",
+            "point:nan,0",
+            "line:0,0,inf",
+            "circle:0,0,-1",
+            "image",
+            "image:",
+            "image:a.png,0,0,0",
+            "image:a.png,0,0,-2",
+        ] {
+            assert!(s.parse::<TrapShape>().is_err(), "{s}");
+        }
+    }
+
+    // ----------------------------------------------------------------------------
+    #[test]
+    fn measures() {
+        let c = Complex::new;
+        let point = trap("point:1,0");
+        assert_measure(&point, c(4.0, 4.0), 5.0, 0.8f64.atan2(0.6) / TAU);
+        assert_measure(&point, c(0.0, 0.0), 1.0, 0.5);
+        assert_measure(&point, c(1.0, -2.0), 2.0, 0.75);
+
+        // the vertical line through 0,1 : the position is along the line, from 0,1
+        let line = trap("line:0,1,90");
+        assert_measure(&line, c(2.0, 3.0), 2.0, 2.0);
+        assert_measure(&line, c(-0.5, 0.0), 0.5, -1.0);
+        assert_measure(&trap("line:0,1"), c(2.0, 3.0), 2.0, 2.0);
+
+        let cross = trap("cross:1,1");
+        assert_measure(&cross, c(4.0, 3.0), 2.0, 2.0f64.atan2(3.0) / TAU);
+        assert_measure(&cross, c(1.0, -5.0), 0.0, 0.75);
+
+        let circle = trap("circle:0,0,2");
+        assert_measure(&circle, c(0.0, 3.0), 1.0, 0.25);
+        assert_measure(&circle, c(0.5, 0.0), 1.5, 0.0);
+        assert_measure(&circle, c(-2.0, 0.0), 0.0, 0.5);
+    }
+
+    // ----------------------------------------------------------------------------
+    #[test]
+    fn texture_lookup() {
+        // 3x3 pixels, the red of pixel k is 20 * k, centered on 1,1 with a size of 3 : from -0.5 to 2.5
+        let data: Vec<u8> = (0..9).flat_map(|k| [20 * k, 0, 255]).collect();
+        let path = std::env::temp_dir().join(format!("mandel-trap-{}.png", std::process::id()));
+        let path = path.to_str().unwrap();
+        save_image(path, &data, 3, 3).unwrap();
+        let trap = trap(&format!("image:{path},1,1,3"));
+        std::fs::remove_file(path).unwrap();
+
+        let texture = trap.texture.as_ref().unwrap();
+        let red = |re, im| {
+            texture
+                .color(Complex::new(re, im))
+                .map(|p| (p[0] * 255.0).round() as u8)
+        };
+        assert_eq!(red(1.0, 1.0), Some(80));
+        assert_eq!(red(-0.5, 2.5), Some(0));
+        assert_eq!(red(2.49, 2.49), Some(40));
+        assert_eq!(red(-0.49, -0.49), Some(120));
+        assert_eq!(red(2.49, -0.49), Some(160));
+        // the right and the bottom borders are outside
+        assert_eq!(red(2.5, 1.0), None);
+        assert_eq!(red(1.0, -0.5), None);
+        assert_eq!(red(-0.51, 1.0), None);
+        assert_eq!(red(1.0, 2.51), None);
+        assert_eq!(texture.color(Complex::new(1.0, 1.0)).unwrap()[2], 1.0);
+    }
+}
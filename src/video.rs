@@ -0,0 +1,277 @@
+// animations written as ONE file instead of thousands of png files
+//      - Y4mWriter : uncompressed YUV4MPEG2 stream. Any encoder can read it (ffmpeg -i zoom.y4m zoom.mp4) even from stdout (mandel ... --y4m - | ffmpeg -i - ...)
+//      - ApngWriter : animated png, the png crate already knows how to write the animation chunks (acTL, fcTL, fdAT). Good for short loops
+//      - PngSequence : numbered png files, as the still images
+// all of them receive the rgb frames (width * height * 3 bytes) of the same frame producer than the still images (see anim.rs)
+
+use crate::output::save_image;
+use std::error::Error;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::PathBuf;
+use std::time::Instant;
+
+pub trait FrameSink {
+    // rgb is width * height * 3 bytes
+    fn write_frame(&mut self, rgb: &[u8]) -> Result<(), Box<dyn Error>>;
+
+    // called once, after the last frame
+    fn finish(&mut self) -> Result<(), Box<dyn Error>> {
+        Ok(())
+    }
+}
+
+// ----------------------------------------------------------------------------
+// YUV4MPEG2 : a one line text header then, for each frame, "FRAME\n" followed by the Y, U and V planes
+// the colors are converted with BT.601 in limited range (16..235), this is what the encoders expect by default
+// chroma is 4:2:0 (one U and one V for 2x2 pixels), the most widely supported
+pub struct Y4mWriter<W: Write> {
+    out: W,
+    width: u32,
+    height: u32,
+    fps: u32,
+    header_written: bool,
+    // the planes are allocated once
+    y: Vec<u8>,
+    u: Vec<u8>,
+    v: Vec<u8>,
+}
+
+impl<W: Write> Y4mWriter<W> {
+    pub fn new(out: W, width: u32, height: u32, fps: u32) -> Self {
+        let chroma_len = (width.div_ceil(2) * height.div_ceil(2)) as usize;
+        Y4mWriter {
+            out,
+            width,
+            height,
+            fps,
+            header_written: false,
+            y: vec![0; (width * height) as usize],
+            u: vec![0; chroma_len],
+            v: vec![0; chroma_len],
+        }
+    }
+}
+
+impl<W: Write> FrameSink for Y4mWriter<W> {
+    // ----------------------------------------------------------------------------
+    fn write_frame(&mut self, rgb: &[u8]) -> Result<(), Box<dyn Error>> {
+        if !self.header_written {
+            writeln!(
+                self.out,
+                "YUV4MPEG2 W{} H{} F{}:1 Ip A1:1 C420jpeg",
+                self.width, self.height, self.fps
+            )?;
+            self.header_written = true;
+        }
+
+        rgb_to_yuv420(
+            rgb,
+            self.width,
+            self.height,
+            &mut self.y,
+            &mut self.u,
+            &mut self.v,
+        );
+        self.out.write_all(b"FRAME\n")?;
+        self.out.write_all(&self.y)?;
+        self.out.write_all(&self.u)?;
+        self.out.write_all(&self.v)?;
+        Ok(())
+    }
+
+    // ----------------------------------------------------------------------------
+    fn finish(&mut self) -> Result<(), Box<dyn Error>> {
+        self.out.flush()?;
+        Ok(())
+    }
+}
+
+// ----------------------------------------------------------------------------
+// the chroma of a 2x2 block is the average of its pixels. On the right and bottom borders of an odd size image the blocks are smaller
+fn rgb_to_yuv420(rgb: &[u8], width: u32, height: u32, y: &mut [u8], u: &mut [u8], v: &mut [u8]) {
+    let (width, height) = (width as usize, height as usize);
+    let chroma_width = width.div_ceil(2);
+
+    for (i, pixel) in rgb.chunks_exact(3).enumerate() {
+        let (r, g, b) = (pixel[0] as f64, pixel[1] as f64, pixel[2] as f64);
+        y[i] = (16.0 + 0.257 * r + 0.504 * g + 0.098 * b).round() as u8;
+    }
+
+    for cy in 0..height.div_ceil(2) {
+        for cx in 0..chroma_width {
+            let (mut r, mut g, mut b, mut n) = (0.0, 0.0, 0.0, 0.0);
+            for py in (2 * cy)..(2 * cy + 2).min(height) {
+                for px in (2 * cx)..(2 * cx + 2).min(width) {
+                    let idx = (py * width + px) * 3;
+                    r += rgb[idx] as f64;
+                    g += rgb[idx + 1] as f64;
+                    b += rgb[idx + 2] as f64;
+                    n += 1.0;
+                }
+            }
+            let (r, g, b) = (r / n, g / n, b / n);
+            u[cy * chroma_width + cx] = (128.0 - 0.148 * r - 0.291 * g + 0.439 * b).round() as u8;
+            v[cy * chroma_width + cx] = (128.0 + 0.439 * r - 0.368 * g - 0.071 * b).round() as u8;
+        }
+    }
+}
+
+// ----------------------------------------------------------------------------
+// the number of frames must be known before the first one is written (it is in the acTL chunk)
+// plays = 0 means "loop forever"
+pub struct ApngWriter<W: Write> {
+    writer: Option<png::Writer<W>>,
+}
+
+impl<W: Write> ApngWriter<W> {
+    pub fn new(
+        out: W,
+        width: u32,
+        height: u32,
+        frames: u32,
+        fps: u32,
+        plays: u32,
+    ) -> Result<Self, png::EncodingError> {
+        let mut encoder = png::Encoder::new(out, width, height);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.set_animated(frames, plays)?;
+        // each frame lasts 1/fps second
+        encoder.set_frame_delay(1, fps.clamp(1, u16::MAX as u32) as u16)?;
+
+        Ok(ApngWriter {
+            writer: Some(encoder.write_header()?),
+        })
+    }
+}
+
+impl<W: Write> FrameSink for ApngWriter<W> {
+    // ----------------------------------------------------------------------------
+    fn write_frame(&mut self, rgb: &[u8]) -> Result<(), Box<dyn Error>> {
+        let writer = self
+            .writer
+            .as_mut()
+            .ok_or("the animation is already finished")?;
+        writer.write_image_data(rgb)?;
+        Ok(())
+    }
+
+    // ----------------------------------------------------------------------------
+    // png::Writer::finish() checks that all the announced frames were written
+    fn finish(&mut self) -> Result<(), Box<dyn Error>> {
+        if let Some(writer) = self.writer.take() {
+            writer.finish()?;
+        }
+        Ok(())
+    }
+}
+
+// ----------------------------------------------------------------------------
+// directory/frame_00000.png, directory/frame_00001.png...
+pub struct PngSequence {
+    directory: PathBuf,
+    width: u32,
+    height: u32,
+    next: u32,
+}
+
+impl PngSequence {
+    pub fn new(directory: PathBuf, width: u32, height: u32) -> Result<Self, Box<dyn Error>> {
+        fs::create_dir_all(&directory)?;
+        Ok(PngSequence {
+            directory,
+            width,
+            height,
+            next: 0,
+        })
+    }
+}
+
+impl FrameSink for PngSequence {
+    // ----------------------------------------------------------------------------
+    fn write_frame(&mut self, rgb: &[u8]) -> Result<(), Box<dyn Error>> {
+        let filename = self.directory.join(format!("frame_{:05}.png", self.next));
+        let filename = filename
+            .to_str()
+            .ok_or("the output path is not valid UTF-8")?;
+        save_image(filename, rgb, self.width, self.height)?;
+        self.next += 1;
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum VideoOutput {
+    // None is stdout
+    Y4m(Option<PathBuf>),
+    Apng(PathBuf),
+    // a directory
+    Png(PathBuf),
+}
+
+// ----------------------------------------------------------------------------
+// an animated png loops forever
+pub fn open_sink(
+    output: &VideoOutput,
+    width: u32,
+    height: u32,
+    frames: u32,
+    fps: u32,
+) -> Result<Box<dyn FrameSink>, Box<dyn Error>> {
+    Ok(match output {
+        VideoOutput::Y4m(None) => Box::new(Y4mWriter::new(
+            BufWriter::new(io::stdout().lock()),
+            width,
+            height,
+            fps,
+        )),
+        VideoOutput::Y4m(Some(filename)) => Box::new(Y4mWriter::new(
+            BufWriter::new(File::create(filename)?),
+            width,
+            height,
+            fps,
+        )),
+        VideoOutput::Apng(filename) => Box::new(ApngWriter::new(
+            BufWriter::new(File::create(filename)?),
+            width,
+            height,
+            frames,
+            fps,
+            0,
+        )?),
+        VideoOutput::Png(directory) => {
+            Box::new(PngSequence::new(directory.clone(), width, height)?)
+        }
+    })
+}
+
+// ----------------------------------------------------------------------------
+// asks produce(n, frame) for the frames 0..frames and sends them to the sink, one after the other
+// the progress goes to stderr because stdout may be the video itself
+pub fn write_frames<F>(
+    frames: u32,
+    frame_len: usize,
+    mut produce: F,
+    sink: &mut dyn FrameSink,
+) -> Result<(), Box<dyn Error>>
+where
+    F: FnMut(u32, &mut [u8]),
+{
+    let mut frame = vec![0u8; frame_len].into_boxed_slice();
+    let start = Instant::now();
+
+    for n in 0..frames {
+        let frame_start = Instant::now();
+        produce(n, &mut frame);
+        sink.write_frame(&frame)?;
+        eprintln!(
+            "frame {n:5}/{frames} : {} ms",
+            frame_start.elapsed().as_millis()
+        );
+    }
+    sink.finish()?;
+
+    eprintln!("Total           : {} ms", start.elapsed().as_millis());
+    Ok(())
+}
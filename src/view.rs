@@ -0,0 +1,97 @@
+// the view is no longer described by 2 corners (from, to) but by a center, a zoom and a rotation
+// this is much easier to animate : the zoom can grow exponentially while the center does not move
+// at zoom 1.0 the height of the image covers BASE_HEIGHT in the complex plane (-1.315..1.315 as in main_10)
+// the rotation is in degrees, counterclockwise
+// the center is a ComplexCoord (see coord.rs) so that a view keeps all its digits, even far beyond the precision of a f64
+// the pixels are the center in the precision T (see real.rs) plus a small offset computed in f64 (see deep.rs when even DoubleDouble is not enough)
+
+use crate::coord::ComplexCoord;
+use crate::real::Real;
+use num_complex::Complex;
+
+pub const BASE_HEIGHT: f64 = 2.63;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Viewport {
+    pub center: ComplexCoord,
+    pub zoom: f64,
+    pub rotation: f64,
+}
+
+impl Default for Viewport {
+    // ----------------------------------------------------------------------------
+    // the whole set, roughly the view of main_10
+    fn default() -> Self {
+        Viewport {
+            center: ComplexCoord::from_complex(Complex::new(-0.75, 0.0)),
+            zoom: 1.0,
+            rotation: 0.0,
+        }
+    }
+}
+
+impl Viewport {
+    // ----------------------------------------------------------------------------
+    // size of one pixel in the complex plane
+    pub fn pixel_size(&self, height: u32) -> f64 {
+        BASE_HEIGHT / self.zoom / height as f64
+    }
+
+    // ----------------------------------------------------------------------------
+    pub fn mapper(&self, width: u32, height: u32) -> PixelMapper {
+        self.mapper_for(width, height)
+    }
+
+    // ----------------------------------------------------------------------------
+    // the rotation and the pixel size are computed once per image, not once per pixel
+    // the center is converted to T with all the digits T can hold
+    pub fn mapper_for<T: Real>(&self, width: u32, height: u32) -> PixelMapper<T> {
+        let pixel_size = self.pixel_size(height);
+        let rotation = Complex::from_polar(1.0, self.rotation.to_radians());
+        PixelMapper {
+            center: Complex::new(
+                T::from_coord(&self.center.re),
+                T::from_coord(&self.center.im),
+            ),
+            half_width: width as f64 / 2.0,
+            half_height: height as f64 / 2.0,
+            step_x: rotation * pixel_size,
+            step_y: rotation * Complex::new(0.0, pixel_size),
+        }
+    }
+}
+
+// ----------------------------------------------------------------------------
+// converts pixel coordinates into a point of the complex plane
+// like in render_zone() the imaginary part grows with y
+#[derive(Debug, Clone, Copy)]
+pub struct PixelMapper<T: Real = f64> {
+    center: Complex<T>,
+    half_width: f64,
+    half_height: f64,
+    step_x: Complex<f64>,
+    step_y: Complex<f64>,
+}
+
+impl<T: Real> PixelMapper<T> {
+    pub fn map(&self, x: u32, y: u32) -> Complex<T> {
+        self.map_f64(x as f64, y as f64)
+    }
+
+    // ----------------------------------------------------------------------------
+    // fractional pixels are useful to sample in between pixels
+    pub fn map_f64(&self, x: f64, y: f64) -> Complex<T> {
+        let offset = self.offset_f64(x, y);
+        Complex::new(
+            self.center.re + T::from_f64(offset.re),
+            self.center.im + T::from_f64(offset.im),
+        )
+    }
+
+    // ----------------------------------------------------------------------------
+    // the position of the pixel relative to the center
+    // when the zoom is deep, the offset is much more accurate than map_f64() - center
+    pub fn offset_f64(&self, x: f64, y: f64) -> Complex<f64> {
+        self.step_x * (x - self.half_width) + self.step_y * (y - self.half_height)
+    }
+}
@@ -0,0 +1,566 @@
+// golden images : canonical views rendered at small sizes and compared with the files of tests/golden
+//      - name.pgm : the iteration counts (0 for the set), 16 bits grayscale, they must be exactly the same
+//      - name.png : the colors, each component may differ by COLOR_TOLERANCE (a libm or a compiler can move the last bits of ln() or cos())
+// on failure the actual image and a diff image are written in target/tmp/golden-diff (the pixels which differ are red)
+// MANDEL_BLESS=1 cargo test --test golden writes the goldens again, after a change of the rendering which is wanted
+// the second part checks that every multithreaded strategy gives the bytes of its single threaded counterpart
+// (render_zone() for the from/to renderers, render_view_rows() for the views)
+// mt_mariani_silver() is not there : its interpolated pixels are close but not exact by design, see mariani::check()
+
+use mandel::checkpoint::{Checkpoint, mt_resumable_smooth_map};
+use mandel::coord::ComplexCoord;
+use mandel::cycle::color_frame;
+use mandel::ddouble::DoubleDouble;
+use mandel::deep::DeepView;
+use mandel::handle::RenderHandle;
+use mandel::output::{load_image, save_image};
+use mandel::pool::ThreadPool;
+use mandel::progressive::{ProgressiveJob, mt_progressive, mt_progressive_view};
+use mandel::real::{Precision, Real};
+use mandel::render::{
+    Fractal, ITERATIONS, SmoothJob, mt_build_mandelbrot, mt_render_view, mt_smooth_map_with,
+    pool_build_mandelbrot, render_rows, render_view_rows, render_zone,
+};
+use mandel::simd::{Kernel, render_zone_simd};
+use mandel::view::Viewport;
+use num_complex::Complex;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const COLOR_TOLERANCE: u8 = 2;
+
+// the size of most goldens, small enough for a debug build
+const WIDTH: u32 = 160;
+const HEIGHT: u32 = 120;
+
+struct Golden {
+    name: &'static str,
+    view: Viewport,
+    fractal: Fractal,
+    precision: Precision,
+    width: u32,
+    height: u32,
+}
+
+// ----------------------------------------------------------------------------
+fn view(re: f64, im: f64, zoom: f64, rotation: f64) -> Viewport {
+    Viewport {
+        center: ComplexCoord::from_complex(Complex::new(re, im)),
+        zoom,
+        rotation,
+    }
+}
+
+// ----------------------------------------------------------------------------
+// one view per precision, plus a rotation and a Julia set
+fn goldens() -> Vec<Golden> {
+    let golden = |name, view, fractal, precision, width, height| Golden {
+        name,
+        view,
+        fractal,
+        precision,
+        width,
+        height,
+    };
+    vec![
+        golden(
+            "whole_set",
+            Viewport::default(),
+            Fractal::Mandelbrot,
+            Precision::Double,
+            WIDTH,
+            HEIGHT,
+        ),
+        golden(
+            "whole_set_f32",
+            Viewport::default(),
+            Fractal::Mandelbrot,
+            Precision::Single,
+            WIDTH,
+            HEIGHT,
+        ),
+        golden(
+            "seahorse",
+            view(-0.743, 0.131, 200.0, 0.0),
+            Fractal::Mandelbrot,
+            Precision::Double,
+            WIDTH,
+            HEIGHT,
+        ),
+        golden(
+            "rotated",
+            view(-0.1, 0.9, 8.0, 30.0),
+            Fractal::Mandelbrot,
+            Precision::Double,
+            WIDTH,
+            HEIGHT,
+        ),
+        golden(
+            "double_double",
+            view(-1.7497219297423385, 0.0, 1e14, 0.0),
+            Fractal::Mandelbrot,
+            Precision::DoubleDouble,
+            64,
+            48,
+        ),
+        golden(
+            "julia",
+            view(0.0, 0.0, 0.8, 0.0),
+            Fractal::Julia(Complex::new(-0.8, 0.156)),
+            Precision::Double,
+            WIDTH,
+            HEIGHT,
+        ),
+    ]
+}
+
+// ----------------------------------------------------------------------------
+fn blessing() -> bool {
+    std::env::var_os("MANDEL_BLESS").is_some()
+}
+
+// ----------------------------------------------------------------------------
+fn golden_path(file: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/golden")
+        .join(file)
+}
+
+// ----------------------------------------------------------------------------
+fn diff_path(file: &str) -> PathBuf {
+    let directory = Path::new(env!("CARGO_TARGET_TMPDIR")).join("golden-diff");
+    fs::create_dir_all(&directory).unwrap();
+    directory.join(file)
+}
+
+// ----------------------------------------------------------------------------
+// the iteration counts of the pixels, with the type of the precision
+fn iteration_map<T: Real>(golden: &Golden) -> Vec<u32> {
+    let mapper = golden.view.mapper_for::<T>(golden.width, golden.height);
+    (0..golden.height)
+        .flat_map(|y| (0..golden.width).map(move |x| (x, y)))
+        .map(|(x, y)| golden.fractal.escape_data(&mapper.map(x, y), ITERATIONS).0)
+        .collect()
+}
+
+// ----------------------------------------------------------------------------
+fn counts(golden: &Golden) -> Vec<u32> {
+    match golden.precision {
+        Precision::Single => iteration_map::<f32>(golden),
+        Precision::Double => iteration_map::<f64>(golden),
+        Precision::DoubleDouble | Precision::Perturbation => iteration_map::<DoubleDouble>(golden),
+    }
+}
+
+// ----------------------------------------------------------------------------
+// the rgb image through the multithreaded pipelines of the commands
+fn colors(golden: &Golden) -> Vec<u8> {
+    let mut image = vec![0u8; (golden.width * golden.height * 3) as usize];
+    match golden.fractal {
+        Fractal::Mandelbrot => {
+            let mut smooth = vec![None; (golden.width * golden.height) as usize];
+            mt_smooth_map_with(
+                &golden.view,
+                golden.precision,
+                ITERATIONS,
+                golden.width,
+                golden.height,
+                &mut smooth,
+                &RenderHandle::new(),
+            );
+            color_frame(&smooth, 0.0, &mut image);
+        }
+        Fractal::Julia(_) => {
+            let job = ProgressiveJob {
+                view: &golden.view,
+                fractal: golden.fractal,
+                iterations: ITERATIONS,
+                palette_offset: 0.0,
+                width: golden.width,
+                height: golden.height,
+            };
+            mt_progressive(&job, &mut image, &RenderHandle::new(), |_, _| ());
+        }
+    }
+    image
+}
+
+// ----------------------------------------------------------------------------
+// P5 with a maxval of 65535 : 2 bytes per pixel, big endian
+fn write_pgm(path: &Path, counts: &[u32], width: u32, height: u32) {
+    let mut bytes = format!("P5\n{width} {height}\n65535\n").into_bytes();
+    for &count in counts {
+        bytes.extend_from_slice(&(count.min(65535) as u16).to_be_bytes());
+    }
+    fs::write(path, bytes).unwrap();
+}
+
+// ----------------------------------------------------------------------------
+// only reads back what write_pgm() writes
+fn read_pgm(path: &Path) -> Option<(Vec<u32>, u32, u32)> {
+    let bytes = fs::read(path).ok()?;
+    let mut fields = Vec::new();
+    let mut start = 0;
+    for (i, &byte) in bytes.iter().enumerate() {
+        if byte.is_ascii_whitespace() {
+            fields.push(std::str::from_utf8(&bytes[start..i]).ok()?);
+            start = i + 1;
+            if fields.len() == 4 {
+                break;
+            }
+        }
+    }
+    if fields.len() != 4 || fields[0] != "P5" || fields[3] != "65535" {
+        return None;
+    }
+    let (width, height): (u32, u32) = (fields[1].parse().ok()?, fields[2].parse().ok()?);
+    let counts: Vec<u32> = bytes[start..]
+        .chunks_exact(2)
+        .map(|pair| u16::from_be_bytes([pair[0], pair[1]]) as u32)
+        .collect();
+    (counts.len() == (width * height) as usize).then_some((counts, width, height))
+}
+
+// ----------------------------------------------------------------------------
+// the actual image and the diff (red where different, the actual image darkened elsewhere)
+fn write_diff(name: &str, actual: &[u8], different: &[bool], width: u32, height: u32) -> PathBuf {
+    let diff: Vec<u8> = actual
+        .chunks_exact(3)
+        .zip(different)
+        .flat_map(|(rgb, &different)| match different {
+            true => [255, 0, 0],
+            false => [rgb[0] / 4, rgb[1] / 4, rgb[2] / 4],
+        })
+        .collect();
+    let actual_path = diff_path(&format!("{name}_actual.png"));
+    let diff_path = diff_path(&format!("{name}_diff.png"));
+    save_image(&actual_path.to_string_lossy(), actual, width, height).unwrap();
+    save_image(&diff_path.to_string_lossy(), &diff, width, height).unwrap();
+    diff_path
+}
+
+// ----------------------------------------------------------------------------
+// the iteration counts as gray levels, for the diff images
+fn gray(counts: &[u32]) -> Vec<u8> {
+    counts
+        .iter()
+        .flat_map(|&count| [(count * 255 / ITERATIONS) as u8; 3])
+        .collect()
+}
+
+// ----------------------------------------------------------------------------
+// Err describes the failure, the diff images are written
+fn check_counts(name: &str, actual: &[u32], width: u32, height: u32) -> Result<(), String> {
+    let path = golden_path(&format!("{name}.pgm"));
+    if blessing() {
+        write_pgm(&path, actual, width, height);
+        return Ok(());
+    }
+    let Some((expected, w, h)) = read_pgm(&path) else {
+        return Err(format!("{} is missing or invalid", path.display()));
+    };
+    if (w, h) != (width, height) {
+        return Err(format!(
+            "{name} : golden is {w}x{h}, rendered {width}x{height}"
+        ));
+    }
+    let different: Vec<bool> = actual.iter().zip(&expected).map(|(a, e)| a != e).collect();
+    let count = different.iter().filter(|&&d| d).count();
+    if count == 0 {
+        return Ok(());
+    }
+    let diff = write_diff(name, &gray(actual), &different, width, height);
+    Err(format!(
+        "{name} : {count} iteration count(s) differ, see {}",
+        diff.display()
+    ))
+}
+
+// ----------------------------------------------------------------------------
+fn check_colors(
+    name: &str,
+    actual: &[u8],
+    width: u32,
+    height: u32,
+    golden: &Path,
+) -> Result<(), String> {
+    if blessing() && golden.starts_with(golden_path("")) {
+        save_image(&golden.to_string_lossy(), actual, width, height).unwrap();
+        return Ok(());
+    }
+    let (expected, w, h) =
+        load_image(&golden.to_string_lossy()).map_err(|e| format!("{} : {e}", golden.display()))?;
+    if (w, h) != (width, height) {
+        return Err(format!(
+            "{name} : golden is {w}x{h}, rendered {width}x{height}"
+        ));
+    }
+    let different: Vec<bool> = actual
+        .chunks_exact(3)
+        .zip(expected.chunks_exact(3))
+        .map(|(a, e)| {
+            a.iter()
+                .zip(e)
+                .any(|(a, e)| a.abs_diff(*e) > COLOR_TOLERANCE)
+        })
+        .collect();
+    let count = different.iter().filter(|&&d| d).count();
+    if count == 0 {
+        return Ok(());
+    }
+    let diff = write_diff(name, actual, &different, width, height);
+    Err(format!(
+        "{name} : {count} pixel(s) differ by more than {COLOR_TOLERANCE}, see {}",
+        diff.display()
+    ))
+}
+
+// ----------------------------------------------------------------------------
+// all the goldens are checked before failing, one report lists them all
+fn report(failures: Vec<String>) {
+    assert!(failures.is_empty(), "\n{}", failures.join("\n"));
+}
+
+// ----------------------------------------------------------------------------
+#[test]
+fn iteration_maps_match_goldens() {
+    let failures = goldens()
+        .iter()
+        .filter_map(|golden| {
+            check_counts(golden.name, &counts(golden), golden.width, golden.height).err()
+        })
+        .collect();
+    report(failures);
+}
+
+// ----------------------------------------------------------------------------
+#[test]
+fn colors_match_goldens() {
+    let mut failures: Vec<String> = goldens()
+        .iter()
+        .filter_map(|golden| {
+            let path = golden_path(&format!("{}.png", golden.name));
+            check_colors(
+                golden.name,
+                &colors(golden),
+                golden.width,
+                golden.height,
+                &path,
+            )
+            .err()
+        })
+        .collect();
+
+    // perturbation (see deep.rs), the view of the deep example of the README
+    let deep = DeepView {
+        view: Viewport {
+            center: "-1.749721929742338571710191080378,0.00000000000000000278981160418"
+                .parse()
+                .unwrap(),
+            zoom: 1e14,
+            rotation: 0.0,
+        },
+        iterations: 3000,
+        max_references: 32,
+        series_terms: 8,
+    };
+    let (smooth, _) = deep.render(64, 48);
+    let mut image = vec![0u8; 64 * 48 * 3];
+    color_frame(&smooth, 0.0, &mut image);
+    failures.extend(check_colors("deep", &image, 64, 48, &golden_path("deep.png")).err());
+    report(failures);
+}
+
+// ----------------------------------------------------------------------------
+// the image of main_10 is still the one in ./assets
+#[test]
+fn render_zone_matches_assets() {
+    let (from, to) = (Complex::new(-2.5, -1.315), Complex::new(1.0, 1.315));
+    let mut image = vec![0u8; 640 * 480 * 3];
+    render_zone(&from, &to, 640, 480, &mut image);
+    let asset = Path::new(env!("CARGO_MANIFEST_DIR")).join("assets/image_rgb_10.png");
+    report(
+        check_colors("image_rgb_10", &image, 640, 480, &asset)
+            .err()
+            .into_iter()
+            .collect(),
+    );
+}
+
+// ----------------------------------------------------------------------------
+// the kernels this CPU can run
+fn kernels() -> Vec<Kernel> {
+    match Kernel::detect() {
+        Kernel::Avx => vec![Kernel::Scalar, Kernel::Sse2, Kernel::Avx],
+        Kernel::Sse2 => vec![Kernel::Scalar, Kernel::Sse2],
+        Kernel::Scalar => vec![Kernel::Scalar],
+    }
+}
+
+// ----------------------------------------------------------------------------
+// first differing pixel, to make the failures readable
+fn first_difference(expected: &[u8], actual: &[u8], width: u32) -> Option<(u32, u32)> {
+    let i = expected
+        .chunks_exact(3)
+        .zip(actual.chunks_exact(3))
+        .position(|(e, a)| e != a)? as u32;
+    Some((i % width, i / width))
+}
+
+// ----------------------------------------------------------------------------
+#[test]
+fn from_to_strategies_match_render_zone() {
+    let (from, to) = (Complex::new(-0.8, 0.05), Complex::new(-0.7, 0.2));
+    let (width, height) = (WIDTH, HEIGHT);
+    let size = (width * height * 3) as usize;
+    let mut reference = vec![0u8; size];
+    render_zone(&from, &to, width, height, &mut reference);
+
+    let mut results: Vec<(String, Vec<u8>)> = Vec::new();
+
+    let mut image = vec![0u8; size];
+    mt_build_mandelbrot(&from, &to, width, height, &mut image);
+    results.push(("mt_build_mandelbrot".to_string(), image));
+
+    let pool = ThreadPool::new(3);
+    let mut image = vec![0u8; size];
+    pool_build_mandelbrot(&pool, &from, &to, width, height, &mut image).unwrap();
+    results.push(("pool_build_mandelbrot".to_string(), image));
+
+    // the stripes of every number of threads, whatever the cores of the machine running the test
+    for nthreads in [2, 3, 7, 16] {
+        let mut image = vec![0u8; size];
+        let mut y_start = 0;
+        for (i, stripe) in image
+            .chunks_mut((height.div_ceil(nthreads) * width * 3) as usize)
+            .enumerate()
+        {
+            let y_end = (i as u32 + 1) * height.div_ceil(nthreads);
+            render_rows(
+                &from,
+                &to,
+                width,
+                height,
+                y_start,
+                y_end.min(height),
+                stripe,
+            );
+            y_start = y_end;
+        }
+        results.push((format!("render_rows in {nthreads} stripes"), image));
+    }
+
+    for kernel in kernels() {
+        let mut image = vec![0u8; size];
+        render_zone_simd(kernel, &from, &to, width, height, ITERATIONS, &mut image);
+        results.push((format!("render_zone_simd {kernel}"), image));
+    }
+
+    for (name, image) in results {
+        assert_eq!(
+            first_difference(&reference, &image, width),
+            None,
+            "{name} differs from render_zone"
+        );
+    }
+}
+
+// ----------------------------------------------------------------------------
+// a view where Precision::for_view() chooses f64, like render_view_rows::<f64>()
+#[test]
+fn view_strategies_match_render_view_rows() {
+    let view = view(-0.7435, 0.1314, 1000.0, 15.0);
+    let (width, height) = (WIDTH, HEIGHT);
+    assert_eq!(Precision::for_view(&view, height), Precision::Double);
+    let size = (width * height * 3) as usize;
+    let mut reference = vec![0u8; size];
+    render_view_rows::<f64>(&view, 0.0, width, height, 0, height, &mut reference);
+
+    let mut results: Vec<(&str, Vec<u8>)> = Vec::new();
+
+    let mut image = vec![0u8; size];
+    mt_render_view(&view, 0.0, width, height, &mut image);
+    results.push(("mt_render_view", image));
+
+    let mut smooth = vec![None; (width * height) as usize];
+    let handle = RenderHandle::new();
+    mt_smooth_map_with(
+        &view,
+        Precision::Double,
+        ITERATIONS,
+        width,
+        height,
+        &mut smooth,
+        &handle,
+    );
+    let mut image = vec![0u8; size];
+    color_frame(&smooth, 0.0, &mut image);
+    results.push(("mt_smooth_map_with", image));
+
+    let mut image = vec![0u8; size];
+    mt_progressive_view(&view, ITERATIONS, 0.0, width, height, &mut image, |_, _| ());
+    results.push(("mt_progressive_view", image));
+
+    let job = SmoothJob {
+        view: &view,
+        precision: Precision::Double,
+        iterations: ITERATIONS,
+        width,
+        height,
+    };
+    let path = diff_path("strategies.checkpoint");
+    let mut checkpoint = Checkpoint::new(&path, &job);
+    let mut smooth = vec![None; (width * height) as usize];
+    mt_resumable_smooth_map(&job, &mut checkpoint, &mut smooth, &RenderHandle::new()).unwrap();
+    checkpoint.remove().unwrap();
+    let mut image = vec![0u8; size];
+    color_frame(&smooth, 0.0, &mut image);
+    results.push(("mt_resumable_smooth_map", image));
+
+    for (name, image) in results {
+        assert_eq!(
+            first_difference(&reference, &image, width),
+            None,
+            "{name} differs from render_view_rows"
+        );
+    }
+}
+
+// ----------------------------------------------------------------------------
+// the same with DoubleDouble, which does not go through the SIMD kernel
+#[test]
+fn double_double_strategies_match_render_view_rows() {
+    let view = view(-1.7497219297423385, 0.0, 1e14, 0.0);
+    let (width, height) = (64, 48);
+    assert_eq!(Precision::for_view(&view, height), Precision::DoubleDouble);
+    let size = (width * height * 3) as usize;
+    let mut reference = vec![0u8; size];
+    render_view_rows::<DoubleDouble>(&view, 0.0, width, height, 0, height, &mut reference);
+
+    let mut image = vec![0u8; size];
+    mt_render_view(&view, 0.0, width, height, &mut image);
+    assert_eq!(
+        first_difference(&reference, &image, width),
+        None,
+        "mt_render_view differs"
+    );
+
+    let mut smooth = vec![None; (width * height) as usize];
+    let handle = RenderHandle::new();
+    mt_smooth_map_with(
+        &view,
+        Precision::DoubleDouble,
+        ITERATIONS,
+        width,
+        height,
+        &mut smooth,
+        &handle,
+    );
+    let mut image = vec![0u8; size];
+    color_frame(&smooth, 0.0, &mut image);
+    assert_eq!(
+        first_difference(&reference, &image, width),
+        None,
+        "mt_smooth_map_with differs"
+    );
+}